@@ -0,0 +1,362 @@
+//! Content-addressed storage for attached files.
+//!
+//! A file added to [`Assets`] is stored under the SHA-256 of its contents,
+//! so the same photo attached to several items is kept once. Items refer to
+//! stored files by that name; attachments that are absolute paths refer to
+//! files outside the store.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::Space;
+use crate::canonical::ContentHash;
+use crate::search::{self, Target};
+
+/// Directory in the store that thumbnails are cached in.
+pub const THUMBNAILS: &str = "thumbnails";
+
+/// Longest side of a thumbnail, in pixels.
+#[cfg(feature = "thumbnails")]
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// A directory of files named by their contents.
+#[derive(Debug, Clone)]
+pub struct Assets {
+    dir: PathBuf,
+}
+
+impl Assets {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Copy `file` into the store, unless a file with the same contents is
+    /// stored already, and return the name to attach it by.
+    pub fn add(&self, file: &Path) -> io::Result<String> {
+        let contents = std::fs::read(file)?;
+        let name = content_name(&contents, file);
+        let stored = self.dir.join(&name);
+        if !stored.exists() {
+            std::fs::create_dir_all(&self.dir)?;
+            // Write under another name first, so an interrupted copy never
+            // leaves a file whose contents do not match its name.
+            let partial = self.dir.join(format!(".{name}.partial"));
+            std::fs::write(&partial, &contents)?;
+            std::fs::rename(&partial, &stored)?;
+        }
+        Ok(name)
+    }
+
+    /// The file an attachment refers to.
+    pub fn resolve(&self, attachment: &str) -> PathBuf {
+        let path = Path::new(attachment);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.dir.join(path)
+        }
+    }
+
+    /// Move every attachment under `root` that refers to a file outside the
+    /// store into it, returning how many attachments changed. Receipts count
+    /// as attachments; those whose file is missing are left alone.
+    pub fn store_all(&self, root: &mut Space) -> io::Result<usize> {
+        let mut changed = 0;
+        for item in &mut root.items {
+            let before = changed;
+            for attachment in item.attachments.iter_mut().chain(&mut item.receipts) {
+                let path = Path::new(attachment.as_str());
+                if path.is_absolute() && path.is_file() {
+                    *attachment = self.add(path)?;
+                    changed += 1;
+                }
+            }
+            if changed > before {
+                item.version += 1;
+            }
+        }
+        for child in &mut root.spaces {
+            changed += self.store_all(child)?;
+        }
+        Ok(changed)
+    }
+
+    /// Files in the store that no attachment under `root` refers to,
+    /// including copies left unfinished and thumbnails of such files, in
+    /// name order. Only files named by a hash count, so anything else kept
+    /// in the directory is never reported.
+    pub fn orphans(&self, root: &Space) -> io::Result<Vec<PathBuf>> {
+        let referenced = references(root);
+        let mut orphans = unused_files(&self.dir, |name| referenced.contains(name))?;
+        // Thumbnails are named by the hash of the file they show, which for
+        // a file kept elsewhere has to be read to be known. Thumbnails of
+        // files that cannot be read are left unused.
+        let hashes: BTreeSet<String> = referenced
+            .iter()
+            .filter_map(|&name| {
+                if is_stored(name) {
+                    Some(stem(name).to_string())
+                } else {
+                    let contents = std::fs::read(self.resolve(name)).ok()?;
+                    Some(ContentHash::of_bytes(&contents).to_string())
+                }
+            })
+            .collect();
+        let thumbnails = self.dir.join(THUMBNAILS);
+        orphans.extend(unused_files(&thumbnails, |name| {
+            hashes.contains(stem(name))
+        })?);
+        Ok(orphans)
+    }
+}
+
+#[cfg(feature = "thumbnails")]
+impl Assets {
+    /// A PNG thumbnail of the image an attachment refers to, made on first
+    /// use and cached in the store.
+    pub fn thumbnail(&self, attachment: &str) -> Result<PathBuf, image::ImageError> {
+        let contents = std::fs::read(self.resolve(attachment))?;
+        let hash = ContentHash::of_bytes(&contents).to_string();
+        let dir = self.dir.join(THUMBNAILS);
+        let thumbnail = dir.join(format!("{hash}.png"));
+        if !thumbnail.exists() {
+            let image = image::load_from_memory(&contents)?;
+            std::fs::create_dir_all(&dir)?;
+            let partial = dir.join(format!(".{hash}.png.partial"));
+            image
+                .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+                .save_with_format(&partial, image::ImageFormat::Png)?;
+            std::fs::rename(&partial, &thumbnail)?;
+        }
+        Ok(thumbnail)
+    }
+}
+
+/// The stored files directly in `dir` whose name `used` rejects, in name
+/// order.
+fn unused_files(dir: &Path, used: impl Fn(&str) -> bool) -> io::Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut unused = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let orphan = name
+            .to_str()
+            .is_some_and(|name| is_stored(name) && !used(name));
+        if orphan && entry.file_type()?.is_file() {
+            unused.push(entry.path());
+        }
+    }
+    unused.sort();
+    Ok(unused)
+}
+
+/// A file name up to its first dot.
+fn stem(name: &str) -> &str {
+    name.split('.').next().unwrap_or(name)
+}
+
+/// Whether `name` is one the store gives files: a content hash, maybe with
+/// an extension, or an unfinished copy of such a file.
+fn is_stored(name: &str) -> bool {
+    let hash = stem(name.strip_prefix('.').unwrap_or(name));
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Every attachment and receipt of the items under `root`.
+pub fn references(root: &Space) -> BTreeSet<&str> {
+    let mut references = BTreeSet::new();
+    collect_references(root, &mut references);
+    references
+}
+
+fn collect_references<'a>(space: &'a Space, references: &mut BTreeSet<&'a str>) {
+    for item in &space.items {
+        references.extend(item.attachments.iter().map(String::as_str));
+        references.extend(item.receipts.iter().map(String::as_str));
+    }
+    for child in &space.spaces {
+        collect_references(child, references);
+    }
+}
+
+/// The name `file` is stored under, without storing it.
+pub fn name_of(file: &Path) -> io::Result<String> {
+    Ok(content_name(&std::fs::read(file)?, file))
+}
+
+fn content_name(contents: &[u8], file: &Path) -> String {
+    let hash = ContentHash::of_bytes(contents).to_string();
+    match file.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{hash}.{}", ext.to_ascii_lowercase()),
+        None => hash,
+    }
+}
+
+/// Paths of the items under `root` that hold `attachment`.
+pub fn holders(root: &Space, attachment: &str) -> Vec<String> {
+    search::entries(root)
+        .into_iter()
+        .filter(|entry| {
+            matches!(entry.target, Target::Item(item)
+                if item.attachments.iter().any(|a| a == attachment))
+        })
+        .map(|entry| entry.path_string())
+        .collect()
+}
+
+/// Attachments held by more than one item under `root`, each with the paths
+/// of the items holding it.
+pub fn shared_attachments(root: &Space) -> BTreeMap<String, Vec<String>> {
+    let mut holders: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for entry in search::entries(root) {
+        if let Target::Item(item) = entry.target {
+            for attachment in &item.attachments {
+                holders
+                    .entry(attachment.clone())
+                    .or_default()
+                    .push(entry.path_string());
+            }
+        }
+    }
+    holders.retain(|_, items| items.len() > 1);
+    holders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn identical_files_are_stored_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = Assets::new(dir.path().join("assets"));
+        let a = dir.path().join("a.JPG");
+        let b = dir.path().join("b.jpg");
+        std::fs::write(&a, b"photo").unwrap();
+        std::fs::write(&b, b"photo").unwrap();
+
+        let name = assets.add(&a).unwrap();
+        assert_eq!(name_of(&b).unwrap(), name);
+        assert_eq!(assets.add(&b).unwrap(), name);
+        assert!(name.ends_with(".jpg"));
+        assert_eq!(std::fs::read_dir(assets.dir()).unwrap().count(), 1);
+        assert_eq!(std::fs::read(assets.resolve(&name)).unwrap(), b"photo");
+    }
+
+    #[test]
+    fn stores_outside_files_and_finds_shared_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = Assets::new(dir.path().join("assets"));
+        let photo = dir.path().join("mug.png");
+        std::fs::write(&photo, b"mug").unwrap();
+        let photo = photo.to_string_lossy().into_owned();
+        let mut root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("mug").attachment(&photo).build())
+            .push_item(Item::builder().name("cup").attachment(&photo).build())
+            .push_item(
+                Item::builder()
+                    .name("gone")
+                    .attachment("/no/such.png")
+                    .build(),
+            )
+            .build();
+
+        assert_eq!(assets.store_all(&mut root).unwrap(), 2);
+        let name = &root.items()[0].attachments()[0];
+        assert!(!Path::new(name).is_absolute());
+        assert_eq!(root.items()[2].attachments()[0], "/no/such.png");
+        assert_eq!(holders(&root, name), ["home/mug", "home/cup"]);
+        assert!(references(&root).contains(name.as_str()));
+        assert_eq!(
+            shared_attachments(&root),
+            BTreeMap::from([(
+                name.clone(),
+                vec!["home/mug".to_string(), "home/cup".to_string()]
+            )])
+        );
+    }
+
+    #[cfg(feature = "thumbnails")]
+    #[test]
+    fn thumbnails_are_made_once_and_fit_the_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = Assets::new(dir.path());
+        let photo = dir.path().join("photo.png");
+        image::RgbImage::new(1000, 500).save(&photo).unwrap();
+        let name = assets.add(&photo).unwrap();
+
+        let thumbnail = assets.thumbnail(&name).unwrap();
+        assert_eq!(
+            image::image_dimensions(&thumbnail).unwrap(),
+            (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2)
+        );
+        assert_eq!(
+            assets.thumbnail(photo.to_str().unwrap()).unwrap(),
+            thumbnail
+        );
+        assert!(assets.thumbnail("missing.png").is_err());
+    }
+
+    #[test]
+    fn finds_unreferenced_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = Assets::new(dir.path());
+        let kept = dir.path().join("kept.jpg");
+        std::fs::write(&kept, b"kept").unwrap();
+        let old = format!("{}.jpg", ContentHash::of_bytes(b"old"));
+        let new = format!(".{}.jpg.partial", ContentHash::of_bytes(b"new"));
+        std::fs::write(dir.path().join(&old), b"old").unwrap();
+        std::fs::write(dir.path().join(&new), b"new").unwrap();
+        std::fs::write(dir.path().join("space.json"), b"{}").unwrap();
+        std::fs::create_dir(dir.path().join(ContentHash::of_bytes(b"dir").to_string())).unwrap();
+        let name = assets.add(&kept).unwrap();
+        std::fs::remove_file(&kept).unwrap();
+        let thumbnails = dir.path().join(THUMBNAILS);
+        std::fs::create_dir(&thumbnails).unwrap();
+        std::fs::write(thumbnails.join(format!("{}.png", stem(&name))), b"").unwrap();
+        let dead = format!("{}.png", ContentHash::of_bytes(b"dead"));
+        std::fs::write(thumbnails.join(&dead), b"").unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let outside = elsewhere.path().join("manual.png");
+        std::fs::write(&outside, b"manual").unwrap();
+        let outside_thumbnail = format!("{}.png", ContentHash::of_bytes(b"manual"));
+        std::fs::write(thumbnails.join(outside_thumbnail), b"").unwrap();
+        let root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("mug").attachment(&name).build())
+            .push_item(
+                Item::builder()
+                    .name("kettle")
+                    .receipt("receipt.pdf")
+                    .attachment(outside.to_str().unwrap())
+                    .build(),
+            )
+            .build();
+        std::fs::write(dir.path().join("receipt.pdf"), b"receipt").unwrap();
+
+        assert_eq!(
+            assets.orphans(&root).unwrap(),
+            [
+                dir.path().join(new),
+                dir.path().join(old),
+                thumbnails.join(dead),
+            ]
+        );
+        assert!(
+            Assets::new(dir.path().join("none"))
+                .orphans(&root)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
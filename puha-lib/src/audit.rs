@@ -0,0 +1,192 @@
+//! Stock-taking: confirming that items are where the inventory says.
+//!
+//! An audit records on each item when it was last seen, or when an audit
+//! last found it missing, so an audit can be resumed by skipping the items
+//! checked since it began.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use crate::search::{self, Target};
+use crate::stock::{SECONDS_PER_DAY, Timestamp};
+use crate::value::{Depreciation, Price};
+use uuid::Uuid;
+
+use crate::{Item, Space};
+
+impl Item {
+    /// When an audit last found the item.
+    pub fn last_verified_at(&self) -> Option<Timestamp> {
+        self.last_verified_at
+    }
+
+    /// When an audit last looked for the item and did not find it, unless
+    /// it has been found since.
+    pub fn missing_at(&self) -> Option<Timestamp> {
+        self.missing_at
+    }
+
+    /// Record that the item was found at `at`.
+    pub fn verify(&mut self, at: Timestamp) {
+        self.last_verified_at = Some(at);
+        self.missing_at = None;
+        self.version += 1;
+    }
+
+    /// Record that the item was looked for at `at` and not found.
+    pub fn mark_missing(&mut self, at: Timestamp) {
+        self.missing_at = Some(at);
+        self.version += 1;
+    }
+
+    /// Whether the item was found, or found missing, at or after `since`.
+    pub fn audited_since(&self, since: Timestamp) -> bool {
+        [self.last_verified_at, self.missing_at]
+            .into_iter()
+            .flatten()
+            .any(|at| at >= since)
+    }
+}
+
+/// Ids of the items under `space` not audited since `since`, in tree order.
+/// Items without an id are given one, so each can be found again however
+/// it is named and wherever it is moved.
+pub fn pending(space: &mut Space, since: Timestamp) -> Vec<Uuid> {
+    let mut ids: Vec<Uuid> = space
+        .items
+        .iter_mut()
+        .filter(|item| !item.audited_since(since))
+        .map(Item::ensure_id)
+        .collect();
+    for child in &mut space.spaces {
+        ids.extend(pending(child, since));
+    }
+    ids
+}
+
+/// An item that may not be where the inventory says.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unaccounted {
+    pub path: String,
+    pub last_verified_at: Option<Timestamp>,
+    /// Set if the last audit did not find the item.
+    pub missing_at: Option<Timestamp>,
+    /// What the item is worth, if it has a purchase price.
+    pub value: Option<Price>,
+}
+
+/// The items under `root` that the last audit did not find, and with
+/// `older_than`, those not found by an audit in that many days before
+/// `now`, including items never audited. Most valuable first, valued by
+/// [`Item::current_value`] with `schedules`; items without a value last.
+pub fn unaccounted(
+    root: &Space,
+    now: Timestamp,
+    older_than: Option<u64>,
+    schedules: &BTreeMap<String, Depreciation>,
+) -> Vec<Unaccounted> {
+    let cutoff = older_than
+        .map(|days| Timestamp(now.0.saturating_sub(days.saturating_mul(SECONDS_PER_DAY))));
+    let today = now.date();
+    let mut items: Vec<Unaccounted> = search::entries(root)
+        .into_iter()
+        .filter_map(|entry| {
+            let Target::Item(item) = entry.target else {
+                return None;
+            };
+            let stale =
+                cutoff.is_some_and(|cutoff| item.last_verified_at.is_none_or(|at| at < cutoff));
+            (item.missing_at.is_some() || stale).then(|| Unaccounted {
+                path: entry.path_string(),
+                last_verified_at: item.last_verified_at,
+                missing_at: item.missing_at,
+                value: item.current_value(schedules, today),
+            })
+        })
+        .collect();
+    items.sort_by_key(|item| Reverse(item.value));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finding_an_item_clears_it_being_missing() {
+        let mut item = Item::builder().name("drill").build();
+        assert!(!item.audited_since(Timestamp(0)));
+        item.verify(Timestamp(10));
+        item.mark_missing(Timestamp(20));
+        assert_eq!(item.last_verified_at(), Some(Timestamp(10)));
+        assert_eq!(item.missing_at(), Some(Timestamp(20)));
+        assert!(item.audited_since(Timestamp(15)));
+        assert!(!item.audited_since(Timestamp(25)));
+        item.verify(Timestamp(30));
+        assert_eq!(item.missing_at(), None);
+        assert!(item.audited_since(Timestamp(25)));
+    }
+
+    #[test]
+    fn pending_items_are_told_apart_by_id() {
+        let mut root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("cup").build())
+            .push_item(Item::builder().name("cup").build())
+            .push_space(
+                Space::builder()
+                    .name("shelf")
+                    .push_item(Item::builder().name("cup").build())
+                    .build(),
+            )
+            .build();
+        root.items[0].verify(Timestamp(10));
+
+        let ids = pending(&mut root, Timestamp(5));
+        assert_eq!(ids.len(), 2);
+        assert_eq!(root.items[1].id(), Some(ids[0]));
+        assert_eq!(root.items[0].id(), None);
+        root.find_item_by_id_mut(ids[0])
+            .unwrap()
+            .verify(Timestamp(20));
+        let moved = root.remove_item_by_id(ids[1]).unwrap();
+        assert_eq!(moved.id(), Some(ids[1]));
+        root.add_item(moved);
+        assert_eq!(pending(&mut root, Timestamp(5)), [ids[1]]);
+    }
+
+    #[test]
+    fn lists_missing_and_stale_items_by_value() {
+        let day = |d: u64| Timestamp(d * SECONDS_PER_DAY);
+        let item = |name: &str, price: Option<u64>| {
+            let item = Item::builder().name(name);
+            match price {
+                Some(price) => item.purchase_price(Price(price)).build(),
+                None => item.build(),
+            }
+        };
+        let mut lost = item("lost", Some(100));
+        lost.mark_missing(day(9));
+        let mut seen = item("seen", Some(5_000));
+        seen.verify(day(9));
+        let mut stale = item("stale", Some(1_000));
+        stale.verify(day(1));
+        let root = Space::builder()
+            .name("home")
+            .push_item(lost)
+            .push_item(seen)
+            .push_item(stale)
+            .push_item(item("new", None))
+            .build();
+        let paths = |older_than| -> Vec<String> {
+            unaccounted(&root, day(10), older_than, &BTreeMap::new())
+                .into_iter()
+                .map(|u| u.path)
+                .collect()
+        };
+
+        assert_eq!(paths(None), ["home/lost"]);
+        assert_eq!(paths(Some(5)), ["home/stale", "home/lost", "home/new"]);
+        assert_eq!(paths(Some(u64::MAX)), ["home/lost", "home/new"]);
+    }
+}
@@ -0,0 +1,215 @@
+//! Canonical form of a tree and hashes over it.
+//!
+//! Two trees have the same canonical form when they hold the same data,
+//! regardless of the order of items and child spaces, surrounding whitespace
+//! or line endings in text, and modification counters.
+
+use std::fmt;
+
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+
+use crate::{Item, Space};
+
+/// SHA-256 digest of a canonical serialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContentHash(pub [u8; 32]);
+
+impl ContentHash {
+    pub fn of_bytes(bytes: &[u8]) -> Self {
+        Self(Sha256::digest(bytes).into())
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Canonical JSON value of a space and everything below it.
+pub fn space_value(space: &Space) -> Value {
+    let mut object = Map::new();
+    extend_extra(&mut object, &space.extra);
+    if let Some(id) = space.id {
+        object.insert("id".into(), id.to_string().into());
+    }
+    object.insert("name".into(), normalize(&space.name).into());
+    object.insert("root".into(), space.root.into());
+    if space.locked {
+        object.insert("locked".into(), true.into());
+    }
+    extend_display(&mut object, &space.icon, &space.color);
+    if let Some(size) = space.capacity {
+        object.insert("capacity".into(), size.to_string().into());
+    }
+    if let Some(weight) = space.max_weight {
+        object.insert("max_weight".into(), weight.to_string().into());
+    }
+    if !space.settings.is_default() {
+        let settings = serde_json::to_value(&space.settings).expect("settings serialize");
+        object.insert("settings".into(), settings);
+    }
+    object.insert(
+        "items".into(),
+        sorted(space.items.iter().map(item_value).collect()),
+    );
+    object.insert(
+        "spaces".into(),
+        sorted(space.spaces.iter().map(space_value).collect()),
+    );
+    Value::Object(object)
+}
+
+/// Canonical JSON value of an item.
+pub fn item_value(item: &Item) -> Value {
+    let mut object = Map::new();
+    extend_extra(&mut object, &item.extra);
+    if let Some(id) = item.id {
+        object.insert("id".into(), id.to_string().into());
+    }
+    object.insert("name".into(), normalize(&item.name).into());
+    object.insert("description".into(), normalize(&item.description).into());
+    if !item.metadata.is_empty() {
+        let metadata: Map<String, Value> = item
+            .metadata
+            .iter()
+            .map(|(k, v)| (normalize(k), normalize(v).into()))
+            .collect();
+        object.insert("metadata".into(), Value::Object(metadata));
+    }
+    if !item.tags.is_empty() {
+        let tags = item.tags.iter().map(|t| normalize(t).into()).collect();
+        object.insert("tags".into(), sorted(tags));
+    }
+    if !item.attachments.is_empty() {
+        let attachments = item.attachments.iter().map(|a| normalize(a).into());
+        object.insert("attachments".into(), Value::Array(attachments.collect()));
+    }
+    if let Some(quantity) = item.quantity {
+        object.insert("quantity".into(), quantity.into());
+    }
+    if let Some(min_quantity) = item.min_quantity {
+        object.insert("min_quantity".into(), min_quantity.into());
+    }
+    if !item.stock_changes.is_empty() {
+        let changes = item
+            .stock_changes
+            .iter()
+            .map(|c| json!({ "at": c.at.to_string(), "delta": c.delta }));
+        object.insert("stock_changes".into(), Value::Array(changes.collect()));
+    }
+    if let Some(category) = &item.category {
+        object.insert("category".into(), normalize(category).into());
+    }
+    if let Some(date) = item.purchase_date {
+        object.insert("purchase_date".into(), date.to_string().into());
+    }
+    if let Some(price) = item.purchase_price {
+        object.insert("purchase_price".into(), price.to_string().into());
+    }
+    if let Some(date) = item.warranty_until {
+        object.insert("warranty_until".into(), date.to_string().into());
+    }
+    if !item.receipts.is_empty() {
+        let receipts = item.receipts.iter().map(|r| normalize(r).into());
+        object.insert("receipts".into(), Value::Array(receipts.collect()));
+    }
+    if !item.links.is_empty() {
+        let links = item
+            .links
+            .iter()
+            .map(|l| json!({ "kind": l.kind.as_str(), "target": l.target.to_string() }));
+        object.insert("links".into(), Value::Array(links.collect()));
+    }
+    if let Some(at) = item.last_verified_at {
+        object.insert("last_verified_at".into(), at.to_string().into());
+    }
+    if let Some(at) = item.missing_at {
+        object.insert("missing_at".into(), at.to_string().into());
+    }
+    if let Some(size) = item.size {
+        object.insert("size".into(), size.to_string().into());
+    }
+    if let Some(weight) = item.weight {
+        object.insert("weight".into(), weight.to_string().into());
+    }
+    extend_display(&mut object, &item.icon, &item.color);
+    Value::Object(object)
+}
+
+pub(crate) fn hash_space(space: &Space) -> ContentHash {
+    ContentHash::of_bytes(space_value(space).to_string().as_bytes())
+}
+
+fn extend_display(object: &mut Map<String, Value>, icon: &Option<String>, color: &Option<String>) {
+    if let Some(icon) = icon {
+        object.insert("icon".into(), normalize(icon).into());
+    }
+    if let Some(color) = color {
+        object.insert("color".into(), normalize(color).into());
+    }
+}
+
+fn extend_extra(object: &mut Map<String, Value>, extra: &Map<String, Value>) {
+    for (key, value) in extra {
+        object.insert(key.clone(), value.clone());
+    }
+}
+
+/// Sort values by their serialized form so that order does not matter.
+fn sorted(values: Vec<Value>) -> Value {
+    let mut keyed: Vec<(String, Value)> = values.into_iter().map(|v| (v.to_string(), v)).collect();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    Value::Array(keyed.into_iter().map(|(_, v)| v).collect())
+}
+
+fn normalize(text: &str) -> String {
+    text.trim().replace("\r\n", "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_ignores_order_whitespace_and_versions() {
+        let a = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("pen").build())
+            .push_item(
+                Item::builder()
+                    .name("cup")
+                    .description("red\r\nmug")
+                    .build(),
+            )
+            .push_space(Space::builder().name("attic").build())
+            .push_space(Space::builder().name("cellar").build())
+            .build();
+        let mut b = Space::builder()
+            .name(" home ")
+            .push_item(Item::builder().name("cup").description("red\nmug").build())
+            .push_space(Space::builder().name("cellar").build())
+            .push_space(Space::builder().name("attic").build())
+            .build();
+        b.add_item(Item::builder().name("pen").build());
+
+        assert_ne!(a.version(), b.version());
+        assert_eq!(a.canonical_hash(), b.canonical_hash());
+
+        b.add_item(Item::builder().name("ink").build());
+        assert_ne!(a.canonical_hash(), b.canonical_hash());
+    }
+
+    #[test]
+    fn hash_displays_as_hex() {
+        let hash = ContentHash::of_bytes(b"");
+        assert_eq!(
+            hash.to_string(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! Capacity planning: how big items are, how much spaces hold, and which
+//! spaces an item would still fit in.
+//!
+//! Sizes are kept in millimetres and weights in grams, but written in
+//! centimetres and kilograms or grams, like `30x20x10` and `2.5kg`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::value::ValueError;
+use crate::{Item, Space};
+
+/// Outer dimensions of an item or inner dimensions of a space, in
+/// millimetres, written in centimetres like `30x20x10` or `30x20.5x10`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "String", into = "String")]
+pub struct Size {
+    pub width: u32,
+    pub depth: u32,
+    pub height: u32,
+}
+
+impl Size {
+    /// Volume in cubic millimetres. Parsed sizes always have one that fits;
+    /// for others it saturates at `u64::MAX`.
+    pub fn volume(&self) -> u64 {
+        self.checked_volume().unwrap_or(u64::MAX)
+    }
+
+    fn checked_volume(&self) -> Option<u64> {
+        u64::from(self.width)
+            .checked_mul(u64::from(self.depth))?
+            .checked_mul(u64::from(self.height))
+    }
+
+    /// Whether something of this size goes into `inside`, turned any way.
+    pub fn fits_in(&self, inside: &Size) -> bool {
+        let (mut a, mut b) = (self.sides(), inside.sides());
+        a.sort_unstable();
+        b.sort_unstable();
+        a.iter().zip(&b).all(|(a, b)| a <= b)
+    }
+
+    fn sides(&self) -> [u32; 3] {
+        [self.width, self.depth, self.height]
+    }
+}
+
+/// Parse `s`, a decimal number with at most `decimals` decimals, into a
+/// whole number of its `decimals`th parts.
+fn scaled(s: &str, decimals: usize) -> Option<u64> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    let digits = |p: &str| p.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() || !digits(whole) || !digits(fraction) || fraction.len() > decimals {
+        return None;
+    }
+    let whole: u64 = whole.parse().ok()?;
+    let fraction: u64 = if decimals == 0 {
+        0
+    } else {
+        format!("{fraction:0<decimals$}").parse().ok()?
+    };
+    whole
+        .checked_mul(10u64.pow(decimals as u32))?
+        .checked_add(fraction)
+}
+
+/// Write `n` parts of `10^decimals` as a decimal number, without trailing
+/// zeros.
+fn unscaled(n: u64, decimals: u32) -> String {
+    let unit = 10u64.pow(decimals);
+    let fraction = n % unit;
+    if fraction == 0 {
+        return (n / unit).to_string();
+    }
+    let fraction = format!("{fraction:0>width$}", width = decimals as usize);
+    format!("{}.{}", n / unit, fraction.trim_end_matches('0'))
+}
+
+impl FromStr for Size {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ValueError {
+            message: format!("invalid size '{s}'; expected centimetres like 30x20x10"),
+        };
+        let cm = s.strip_suffix("cm").unwrap_or(s).trim();
+        let sides: Vec<u32> = cm
+            .split('x')
+            .map(|side| {
+                scaled(side.trim(), 1)
+                    .and_then(|mm| u32::try_from(mm).ok())
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<_, _>>()?;
+        let [width, depth, height] = sides[..] else {
+            return Err(invalid());
+        };
+        let size = Size {
+            width,
+            depth,
+            height,
+        };
+        match size.checked_volume() {
+            Some(_) => Ok(size),
+            None => Err(ValueError {
+                message: format!("size '{s}' is too large"),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cm = |mm: u32| unscaled(u64::from(mm), 1);
+        write!(
+            f,
+            "{}x{}x{}",
+            cm(self.width),
+            cm(self.depth),
+            cm(self.height)
+        )
+    }
+}
+
+impl TryFrom<String> for Size {
+    type Error = ValueError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Size> for String {
+    fn from(size: Size) -> Self {
+        size.to_string()
+    }
+}
+
+/// A weight in grams, written like `300g` or `2.5kg`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "String", into = "String")]
+pub struct Weight(pub u64);
+
+impl FromStr for Weight {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ValueError {
+            message: format!("invalid weight '{s}'; expected e.g. 300g or 2.5kg"),
+        };
+        let grams = if let Some(kg) = s.strip_suffix("kg") {
+            scaled(kg.trim(), 3)
+        } else if let Some(g) = s.strip_suffix('g') {
+            scaled(g.trim(), 0)
+        } else {
+            None
+        };
+        grams.map(Weight).ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Weight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 1000 {
+            write!(f, "{}g", self.0)
+        } else {
+            write!(f, "{}kg", unscaled(self.0, 3))
+        }
+    }
+}
+
+impl TryFrom<String> for Weight {
+    type Error = ValueError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Weight> for String {
+    fn from(weight: Weight) -> Self {
+        weight.to_string()
+    }
+}
+
+impl Item {
+    /// The item's outer dimensions.
+    pub fn size(&self) -> Option<Size> {
+        self.size
+    }
+
+    pub fn weight(&self) -> Option<Weight> {
+        self.weight
+    }
+
+    pub fn set_size(&mut self, size: Option<Size>) {
+        self.size = size;
+        self.version += 1;
+    }
+
+    pub fn set_weight(&mut self, weight: Option<Weight>) {
+        self.weight = weight;
+        self.version += 1;
+    }
+}
+
+impl Space {
+    /// The space's inner dimensions.
+    pub fn capacity(&self) -> Option<Size> {
+        self.capacity
+    }
+
+    /// How much the things in the space may weigh together.
+    pub fn max_weight(&self) -> Option<Weight> {
+        self.max_weight
+    }
+
+    pub fn set_capacity(&mut self, capacity: Option<Size>) {
+        self.capacity = capacity;
+        self.version += 1;
+    }
+
+    pub fn set_max_weight(&mut self, max_weight: Option<Weight>) {
+        self.max_weight = max_weight;
+        self.version += 1;
+    }
+
+    /// Cubic millimetres taken up in the space by items of known size, and
+    /// by the spaces in it: as much as they hold if they have a capacity,
+    /// otherwise what is taken up in them. Saturates at `u64::MAX`.
+    pub fn used_volume(&self) -> u64 {
+        let items = self.items.iter().filter_map(|item| {
+            item.size
+                .map(|s| s.volume().saturating_mul(u64::from(item_count(item))))
+        });
+        let spaces = self.spaces.iter().map(|child| {
+            child
+                .capacity
+                .map_or_else(|| child.used_volume(), |size| size.volume())
+        });
+        items.chain(spaces).fold(0, u64::saturating_add)
+    }
+
+    /// Grams weighed by the items of known weight in and below the space.
+    /// Saturates at `u64::MAX`.
+    pub fn used_weight(&self) -> u64 {
+        let items = self.items.iter().filter_map(|item| {
+            item.weight
+                .map(|w| w.0.saturating_mul(u64::from(item_count(item))))
+        });
+        items
+            .chain(self.spaces.iter().map(Space::used_weight))
+            .fold(0, u64::saturating_add)
+    }
+}
+
+fn item_count(item: &Item) -> u32 {
+    item.quantity.unwrap_or(1)
+}
+
+/// A space an item would fit in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fit {
+    /// Names of the spaces from the root down to this one.
+    pub path: Vec<String>,
+    /// Cubic millimetres still free, if the space has a capacity.
+    pub volume_left: Option<u64>,
+    /// Grams that can still go in, if the space has a maximum weight.
+    pub weight_left: Option<u64>,
+}
+
+/// The spaces under `root` with a capacity or maximum weight that have room
+/// left for `item`, other than the one it is in, in tree order.
+///
+/// A space passes each check it can make: the item's size must fit inside
+/// the capacity and in the volume left, and its weight in the weight left.
+/// `item` must be in the tree under `root` for its own space to be skipped.
+pub fn fits(root: &Space, item: &Item) -> Vec<Fit> {
+    let mut fits = Vec::new();
+    collect(root, item, &mut Vec::new(), &mut fits);
+    fits
+}
+
+fn collect(space: &Space, item: &Item, parents: &mut Vec<String>, fits: &mut Vec<Fit>) {
+    parents.push(space.name.clone());
+    let holds_item = space.items.iter().any(|i| std::ptr::eq(i, item));
+    if !holds_item && (space.capacity.is_some() || space.max_weight.is_some()) {
+        let count = u64::from(item_count(item));
+        let volume_left = space
+            .capacity
+            .map(|c| c.volume().saturating_sub(space.used_volume()));
+        let weight_left = space
+            .max_weight
+            .map(|w| w.0.saturating_sub(space.used_weight()));
+        let size_fits = match (item.size, space.capacity, volume_left) {
+            (Some(size), Some(capacity), Some(left)) => {
+                size.fits_in(&capacity) && size.volume().saturating_mul(count) <= left
+            }
+            _ => true,
+        };
+        let weight_fits = match (item.weight, weight_left) {
+            (Some(weight), Some(left)) => weight.0.saturating_mul(count) <= left,
+            _ => true,
+        };
+        if size_fits && weight_fits {
+            fits.push(Fit {
+                path: parents.clone(),
+                volume_left,
+                weight_left,
+            });
+        }
+    }
+    for child in &space.spaces {
+        collect(child, item, parents, fits);
+    }
+    parents.pop();
+}
+
+/// How many steps up and down the tree it takes to get from the space at
+/// `from` to the one at `to`, both given as names from the root down.
+pub fn distance<S: AsRef<str>, T: AsRef<str>>(from: &[S], to: &[T]) -> usize {
+    let shared = from
+        .iter()
+        .zip(to)
+        .take_while(|(a, b)| a.as_ref() == b.as_ref())
+        .count();
+    from.len() + to.len() - 2 * shared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_and_weights_round_trip() {
+        let size: Size = "30x20.5x10".parse().unwrap();
+        assert_eq!(
+            size,
+            Size {
+                width: 300,
+                depth: 205,
+                height: 100
+            }
+        );
+        assert_eq!(size.to_string(), "30x20.5x10");
+        assert_eq!("30x20x10cm".parse::<Size>().unwrap().volume(), 6_000_000);
+        assert!("30x20".parse::<Size>().is_err());
+        assert!("30x20x1.25".parse::<Size>().is_err());
+
+        assert_eq!("2.5kg".parse(), Ok(Weight(2500)));
+        assert_eq!("300g".parse(), Ok(Weight(300)));
+        assert_eq!(Weight(2500).to_string(), "2.5kg");
+        assert_eq!(Weight(300).to_string(), "300g");
+        assert!("2.5".parse::<Weight>().is_err());
+    }
+
+    #[test]
+    fn volumes_and_weights_at_the_bounds() {
+        let largest: Size = "429496729.5x429496729.5x0.1".parse().unwrap();
+        assert_eq!(largest.volume(), u64::from(u32::MAX).pow(2));
+        assert!("429496729.5x429496729.5x0.2".parse::<Size>().is_err());
+        let unparsed = Size {
+            width: u32::MAX,
+            depth: u32::MAX,
+            height: u32::MAX,
+        };
+        assert_eq!(unparsed.volume(), u64::MAX);
+
+        let item = Item::builder()
+            .name("slab")
+            .size(largest)
+            .weight(Weight(u64::MAX))
+            .quantity(2)
+            .build();
+        let space = Space::builder().name("yard").push_item(item).build();
+        assert_eq!(space.used_volume(), u64::MAX);
+        assert_eq!(space.used_weight(), u64::MAX);
+    }
+
+    #[test]
+    fn sizes_fit_turned_any_way() {
+        let size = |s: &str| s.parse::<Size>().unwrap();
+        assert!(size("10x40x20").fits_in(&size("40x20x10")));
+        assert!(!size("10x41x20").fits_in(&size("40x20x10")));
+    }
+
+    #[test]
+    fn finds_spaces_with_room_left() {
+        let size = |s: &str| s.parse::<Size>().unwrap();
+        let mut small = Space::builder().name("small").build();
+        small.set_capacity(Some(size("10x10x10")));
+        let mut full = Space::builder()
+            .name("full")
+            .push_item(Item::builder().name("box").build())
+            .build();
+        full.set_capacity(Some(size("50x50x50")));
+        full.items[0].set_size(Some(size("50x50x48")));
+        let mut shelf = Space::builder().name("shelf").build();
+        shelf.set_capacity(Some(size("100x40x40")));
+        shelf.set_max_weight(Some(Weight(10_000)));
+        let mut root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("drill").build())
+            .push_space(small)
+            .push_space(full)
+            .push_space(shelf)
+            .build();
+        root.items[0].set_size(Some(size("30x20x10")));
+        root.items[0].set_weight(Some(Weight(2_000)));
+
+        let fits = fits(&root, &root.items()[0]);
+        assert_eq!(fits.len(), 1);
+        assert_eq!(fits[0].path, ["home", "shelf"]);
+        assert_eq!(fits[0].weight_left, Some(10_000));
+        assert_eq!(
+            root.used_volume(),
+            6_000_000 + 1_000_000 + 125_000_000 + 160_000_000
+        );
+        assert_eq!(distance(&["home", "shelf"], &["home", "full"]), 2);
+    }
+}
@@ -0,0 +1,114 @@
+//! Ordering of names for display.
+//!
+//! The default ordering is locale independent: names are compared without
+//! regard to case and accents first, so "Ábel" sorts next to "Abel" rather
+//! than after "Zoltán". With the `collation` feature, names can instead be
+//! ordered following the rules of a particular language.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::MatchOptions;
+
+/// Returned when no ordering is available for a locale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollationError {
+    pub message: String,
+}
+
+impl fmt::Display for CollationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CollationError {}
+
+/// How names are ordered.
+#[derive(Debug, Default)]
+pub struct Collation {
+    #[cfg(feature = "collation")]
+    collator: Option<icu_collator::CollatorBorrowed<'static>>,
+}
+
+impl Collation {
+    /// The locale-independent ordering.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The ordering used by `locale`, a BCP 47 language tag such as `hu` or
+    /// `sv-SE`. Needs the `collation` feature.
+    #[cfg(feature = "collation")]
+    pub fn for_locale(locale: &str) -> Result<Self, CollationError> {
+        let error = |e: &dyn fmt::Display| CollationError {
+            message: format!("locale '{locale}': {e}"),
+        };
+        let locale: icu_locale_core::Locale = locale.parse().map_err(|e| error(&e))?;
+        let collator = icu_collator::CollatorBorrowed::try_new(
+            (&locale).into(),
+            icu_collator::options::CollatorOptions::default(),
+        )
+        .map_err(|e| error(&e))?;
+        Ok(Self {
+            collator: Some(collator),
+        })
+    }
+
+    /// The ordering used by `locale`. Needs the `collation` feature.
+    #[cfg(not(feature = "collation"))]
+    pub fn for_locale(locale: &str) -> Result<Self, CollationError> {
+        Err(CollationError {
+            message: format!("locale '{locale}': sorting by locale needs the `collation` feature"),
+        })
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        #[cfg(feature = "collation")]
+        if let Some(collator) = &self.collator {
+            return collator.compare(a, b);
+        }
+        let folding = MatchOptions::normalized();
+        folding.fold(a).cmp(&folding.fold(b)).then_with(|| a.cmp(b))
+    }
+
+    /// Sort `values` by the name `key` returns for each of them.
+    pub fn sort_by<T>(&self, values: &mut [T], key: impl Fn(&T) -> &str) {
+        values.sort_by(|a, b| self.compare(key(a), key(b)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted<'a>(collation: &Collation, names: &[&'a str]) -> Vec<&'a str> {
+        let mut names = names.to_vec();
+        collation.sort_by(&mut names, |name| name);
+        names
+    }
+
+    #[test]
+    fn accents_and_case_sort_next_to_base_letters() {
+        let names = ["Zoltán", "ábel", "Abel", "Ádám", "bolt"];
+        assert_eq!(
+            sorted(&Collation::new(), &names),
+            vec!["Abel", "ábel", "Ádám", "bolt", "Zoltán"]
+        );
+    }
+
+    #[cfg(feature = "collation")]
+    #[test]
+    fn locales_apply_their_own_rules() {
+        let names = ["zebra", "öl", "ozon"];
+        assert_eq!(
+            sorted(&Collation::for_locale("de").unwrap(), &names),
+            vec!["öl", "ozon", "zebra"]
+        );
+        assert_eq!(
+            sorted(&Collation::for_locale("sv").unwrap(), &names),
+            vec!["ozon", "zebra", "öl"]
+        );
+        assert!(Collation::for_locale("not a locale!").is_err());
+    }
+}
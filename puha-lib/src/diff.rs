@@ -0,0 +1,188 @@
+//! Differences between two trees.
+//!
+//! Spaces and items are matched up by their path. Each one only in the new
+//! tree is added, each one only in the old tree is removed, and each one in
+//! both whose own data differs, in canonical form, is changed. A space
+//! counts as changed for its own fields only, not for what is inside it.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::Space;
+use crate::canonical;
+use crate::search::{self, Target};
+
+/// Whether a difference is about a space or an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Kind {
+    Space,
+    Item,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Space => "space",
+            Kind::Item => "item",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One space or item that differs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Difference {
+    pub change: Change,
+    pub kind: Kind,
+    /// Location as `space/.../name`, starting at the root.
+    pub path: String,
+}
+
+/// Everything that differs between two trees, ordered by path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeDiff {
+    pub differences: Vec<Difference>,
+}
+
+impl TreeDiff {
+    pub fn between(old: &Space, new: &Space) -> Self {
+        let mut old = values(old);
+        let mut differences = Vec::new();
+        for (key, new_values) in values(new) {
+            let old_values = old.remove(&key).unwrap_or_default();
+            // Entries sharing a path are paired up in order.
+            for pair in 0..old_values.len().max(new_values.len()) {
+                let change = match (old_values.get(pair), new_values.get(pair)) {
+                    (Some(a), Some(b)) if a == b => continue,
+                    (Some(_), Some(_)) => Change::Changed,
+                    (None, _) => Change::Added,
+                    (_, None) => Change::Removed,
+                };
+                differences.push(Difference {
+                    change,
+                    kind: key.1,
+                    path: key.0.clone(),
+                });
+            }
+        }
+        for ((path, kind), old_values) in old {
+            differences.extend(old_values.iter().map(|_| Difference {
+                change: Change::Removed,
+                kind,
+                path: path.clone(),
+            }));
+        }
+        differences.sort_by(|a, b| (&a.path, a.kind).cmp(&(&b.path, b.kind)));
+        Self { differences }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// Number of differences of the given kind of change.
+    pub fn count(&self, change: Change) -> usize {
+        self.differences
+            .iter()
+            .filter(|d| d.change == change)
+            .count()
+    }
+}
+
+/// One line per difference: `+` for added, `-` for removed and `~` for
+/// changed, followed by the kind and the path.
+impl fmt::Display for TreeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for difference in &self.differences {
+            let sign = match difference.change {
+                Change::Added => '+',
+                Change::Removed => '-',
+                Change::Changed => '~',
+            };
+            writeln!(f, "{sign} {} {}", difference.kind, difference.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Canonical values of every space and item, by path and kind.
+fn values(root: &Space) -> BTreeMap<(String, Kind), Vec<Value>> {
+    let mut values: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for entry in search::entries(root) {
+        let (kind, value) = match entry.target {
+            Target::Item(item) => (Kind::Item, canonical::item_value(item)),
+            Target::Space(space) => {
+                let mut value = canonical::space_value(space);
+                if let Value::Object(object) = &mut value {
+                    object.remove("items");
+                    object.remove("spaces");
+                }
+                (Kind::Space, value)
+            }
+        };
+        values
+            .entry((entry.path_string(), kind))
+            .or_default()
+            .push(value);
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn reports_added_removed_and_changed() {
+        let old = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("lamp").build())
+            .push_item(Item::builder().name("rug").build())
+            .push_space(Space::builder().name("attic").build())
+            .build();
+        let mut new = old.clone();
+        new.remove_item("rug");
+        new.find_item_mut("lamp")
+            .unwrap()
+            .set_description("desk lamp");
+        new.find_space_mut("attic")
+            .unwrap()
+            .add_item(Item::builder().name("box").build());
+        new.find_space_mut("attic")
+            .unwrap()
+            .set_icon(Some("📦".into()));
+
+        let diff = TreeDiff::between(&old, &new);
+        assert_eq!(
+            diff.to_string(),
+            "~ space home/attic\n+ item home/attic/box\n~ item home/lamp\n- item home/rug\n"
+        );
+        assert_eq!(diff.count(Change::Changed), 2);
+        assert!(TreeDiff::between(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn modification_counters_are_not_changes() {
+        let old = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("lamp").build())
+            .build();
+        let mut new = old.clone();
+        new.find_item_mut("lamp").unwrap().set_name("lamp");
+        assert!(TreeDiff::between(&old, &new).is_empty());
+    }
+}
@@ -0,0 +1,20 @@
+//! The library's error types in one place, for programs that handle them.
+
+pub use crate::VersionConflict;
+pub use crate::cancel::Cancelled;
+pub use crate::collation::CollationError;
+#[cfg(any(feature = "import-grocy", feature = "import-home-assistant"))]
+pub use crate::import::ImportError;
+pub use crate::limits::LimitError;
+pub use crate::links::UnknownLinkKind;
+pub use crate::lock::SpaceLocked;
+pub use crate::naming::{InvalidName, NameProblem};
+pub use crate::patch::PatchError;
+pub use crate::path::PathError;
+pub use crate::query::QueryError;
+pub use crate::quick::QuickAddError;
+pub use crate::redact::UnknownField;
+pub use crate::stock::{NotEnough, TooMany};
+pub use crate::strict::StrictError;
+pub use crate::template::TemplateError;
+pub use crate::value::ValueError;
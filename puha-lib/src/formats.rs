@@ -0,0 +1,146 @@
+//! Writing numbers and dates for people, following the conventions of a
+//! language.
+//!
+//! Saved data always uses the fixed forms, like `1234.50` and `2024-05-01`;
+//! [`Formats`] is for output, such as value totals and reports. Only the
+//! languages in [`Formats::for_locale`] are known, and any other locale gets
+//! the fixed forms.
+
+use crate::value::{Date, Price};
+
+/// How a language writes dates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DateStyle {
+    /// `2024-05-01`
+    Iso,
+    /// `01.05.2024`, with the separator given.
+    DayMonthYear(char),
+    /// `05/01/2024`
+    MonthDayYear,
+    /// `2024. 05. 01.`
+    YearMonthDayDotted,
+}
+
+/// How numbers and dates are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Formats {
+    /// Put between groups of three digits, if anything.
+    pub group: Option<char>,
+    pub decimal: char,
+    pub date: DateStyle,
+}
+
+impl Default for Formats {
+    /// The fixed forms saved data uses.
+    fn default() -> Self {
+        Self {
+            group: None,
+            decimal: '.',
+            date: DateStyle::Iso,
+        }
+    }
+}
+
+impl Formats {
+    /// The conventions of `locale`, a BCP 47 language tag such as `hu` or
+    /// `en-GB` or a POSIX locale such as `en_GB.UTF-8`, or the fixed forms
+    /// for a language not listed here.
+    pub fn for_locale(locale: &str) -> Self {
+        // A POSIX locale may end in `.codeset` and `@modifier`.
+        let locale = locale.split(['.', '@']).next().unwrap_or_default();
+        let mut parts = locale.split(['-', '_']);
+        let language = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let region = parts
+            .find(|part| part.len() == 2)
+            .map(str::to_ascii_uppercase);
+        // A no-break space keeps a number on one line.
+        let (group, decimal, date) = match (language.as_str(), region.as_deref()) {
+            ("en", None | Some("US")) => (',', '.', DateStyle::MonthDayYear),
+            ("en", _) => (',', '.', DateStyle::DayMonthYear('/')),
+            ("de", Some("CH")) => ('\'', '.', DateStyle::DayMonthYear('.')),
+            ("de" | "da" | "tr" | "ro", _) => ('.', ',', DateStyle::DayMonthYear('.')),
+            ("es" | "it" | "pt", _) => ('.', ',', DateStyle::DayMonthYear('/')),
+            ("nl", _) => ('.', ',', DateStyle::DayMonthYear('-')),
+            ("fr", _) => ('\u{a0}', ',', DateStyle::DayMonthYear('/')),
+            ("nb" | "nn" | "no" | "fi" | "cs" | "sk" | "pl" | "ru" | "uk", _) => {
+                ('\u{a0}', ',', DateStyle::DayMonthYear('.'))
+            }
+            ("sv", _) => ('\u{a0}', ',', DateStyle::Iso),
+            ("hu", _) => ('\u{a0}', ',', DateStyle::YearMonthDayDotted),
+            ("ja" | "zh" | "ko", _) => (',', '.', DateStyle::Iso),
+            _ => return Self::default(),
+        };
+        Self {
+            group: Some(group),
+            decimal,
+            date,
+        }
+    }
+
+    /// `n` with its digits grouped.
+    pub fn integer(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let Some(group) = self.group else {
+            return digits;
+        };
+        let mut out = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(group);
+            }
+            out.push(digit);
+        }
+        out
+    }
+
+    pub fn price(&self, price: Price) -> String {
+        format!(
+            "{}{}{:02}",
+            self.integer(price.0 / 100),
+            self.decimal,
+            price.0 % 100
+        )
+    }
+
+    pub fn date(&self, date: Date) -> String {
+        let (year, month, day) = (date.year(), date.month(), date.day());
+        match self.date {
+            DateStyle::Iso => date.to_string(),
+            DateStyle::DayMonthYear(sep) => format!("{day:02}{sep}{month:02}{sep}{year:04}"),
+            DateStyle::MonthDayYear => format!("{month:02}/{day:02}/{year:04}"),
+            DateStyle::YearMonthDayDotted => format!("{year:04}. {month:02}. {day:02}."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_numbers_and_dates_by_language() {
+        let price = Price(123_456_789);
+        let date = Date::new(2024, 5, 1).unwrap();
+        let cases = [
+            ("", "1234567.89", "2024-05-01"),
+            ("en", "1,234,567.89", "05/01/2024"),
+            ("en-GB", "1,234,567.89", "01/05/2024"),
+            ("de_DE.UTF-8", "1.234.567,89", "01.05.2024"),
+            ("de-CH", "1'234'567.89", "01.05.2024"),
+            ("de_CH.UTF-8", "1'234'567.89", "01.05.2024"),
+            ("en_GB.UTF-8", "1,234,567.89", "01/05/2024"),
+            ("en_US.UTF-8@euro", "1,234,567.89", "05/01/2024"),
+            ("hu", "1\u{a0}234\u{a0}567,89", "2024. 05. 01."),
+            ("sv-SE", "1\u{a0}234\u{a0}567,89", "2024-05-01"),
+            ("tlh", "1234567.89", "2024-05-01"),
+        ];
+        for (locale, price_text, date_text) in cases {
+            let formats = Formats::for_locale(locale);
+            assert_eq!(formats.price(price), price_text, "{locale}");
+            assert_eq!(formats.date(date), date_text, "{locale}");
+        }
+        assert_eq!(Formats::for_locale("en").price(Price(5)), "0.05");
+        assert_eq!(Formats::for_locale("en").integer(999), "999");
+    }
+}
@@ -0,0 +1,307 @@
+//! Stable identifiers for spaces and items.
+//!
+//! Names change and repeat, so tools that need to recognize an entity again,
+//! such as importers run a second time over the same export, go by its id.
+//! An [`IdStrategy`] fills in the ids that a tree is missing, and
+//! [`merge_space`] adds a space to a tree, merging it with the space that
+//! already has its id. [`renew_taken`] makes a copy of a space safe to add
+//! to the tree it came from.
+
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::{Item, Space};
+
+/// Namespace for the name-based ids of [`IdStrategy::Deterministic`].
+pub const NAMESPACE: Uuid = Uuid::from_u128(0x6b1f_0c2e_3d4a_4f5b_9c8d_7e6f_5a4b_3c2d);
+
+/// How to give ids to spaces and items that do not have one yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IdStrategy {
+    /// Leave missing ids missing.
+    None,
+    /// Random version 4 UUIDs.
+    Random,
+    /// Version 5 UUIDs derived from the path and name, so the same entity
+    /// at the same place always gets the same id.
+    #[default]
+    Deterministic,
+}
+
+impl IdStrategy {
+    /// Give ids to `space` and everything below it that has none. `parents`
+    /// are the names of the spaces that `space` is, or will be, inside of,
+    /// starting at the root.
+    pub fn assign(self, space: &mut Space, parents: &[&str]) {
+        let mut path: Vec<String> = parents.iter().map(|p| p.to_string()).collect();
+        self.assign_space(space, &mut path);
+    }
+
+    fn assign_space(self, space: &mut Space, path: &mut Vec<String>) {
+        if space.id.is_none() {
+            space.id = self.id("space", path, &space.name);
+        }
+        path.push(space.name.clone());
+        for item in &mut space.items {
+            if item.id.is_none() {
+                item.id = self.id("item", path, &item.name);
+            }
+        }
+        for child in &mut space.spaces {
+            self.assign_space(child, path);
+        }
+        path.pop();
+    }
+
+    fn id(self, kind: &str, path: &[String], name: &str) -> Option<Uuid> {
+        match self {
+            IdStrategy::None => None,
+            IdStrategy::Random => Some(Uuid::new_v4()),
+            IdStrategy::Deterministic => {
+                // Segments are joined with NUL, which valid names cannot
+                // contain, and the kind keeps a space and an item of the same name
+                // apart.
+                let mut key = kind.to_string();
+                for segment in path.iter().map(String::as_str).chain([name]) {
+                    key.push('\0');
+                    key.push_str(segment);
+                }
+                Some(Uuid::new_v5(&NAMESPACE, key.as_bytes()))
+            }
+        }
+    }
+}
+
+/// Add `space` as a child of `parent`. If `parent` already has a child with
+/// the same id, merge into it instead: items with an id already there are
+/// replaced, child spaces are merged the same way, and everything else is
+/// added.
+///
+/// An item edited here since it was last merged, so that its version is
+/// ahead of the incoming one, is kept rather than replaced. The paths of
+/// such items, below `parent`, are returned.
+pub fn merge_space(parent: &mut Space, space: Space) -> Vec<String> {
+    let mut kept = Vec::new();
+    merge_into(parent, space, &mut Vec::new(), &mut kept);
+    kept
+}
+
+fn merge_into(parent: &mut Space, space: Space, path: &mut Vec<String>, kept: &mut Vec<String>) {
+    let existing = space
+        .id
+        .and_then(|id| parent.spaces.iter().position(|s| s.id == Some(id)));
+    let Some(index) = existing else {
+        parent.add_space(space);
+        return;
+    };
+    let target = &mut parent.spaces[index];
+    if target.name != space.name {
+        target.set_name(space.name);
+    }
+    path.push(target.name.clone());
+    for item in space.items {
+        if let Some(name) = merge_item(target, item) {
+            let parents: Vec<&str> = path.iter().map(String::as_str).collect();
+            kept.push(crate::path::join(&parents, &name));
+        }
+    }
+    for child in space.spaces {
+        merge_into(target, child, path, kept);
+    }
+    path.pop();
+}
+
+/// Merge `item` into `space`, returning the name of the item there if it was
+/// kept because of its own edits.
+fn merge_item(space: &mut Space, item: Item) -> Option<String> {
+    let existing = item
+        .id
+        .and_then(|id| space.items.iter().position(|i| i.id == Some(id)));
+    match existing {
+        Some(index) if space.items[index] == item => None,
+        Some(index) if space.items[index].version > item.version => {
+            Some(space.items[index].name.clone())
+        }
+        Some(index) => {
+            space.items[index] = item;
+            space.version += 1;
+            None
+        }
+        None => {
+            space.add_item(item);
+            None
+        }
+    }
+}
+
+/// The ids of `space` and of every space and item below it.
+pub fn ids(space: &Space) -> HashSet<Uuid> {
+    let mut ids = HashSet::new();
+    collect_ids(space, &mut ids);
+    ids
+}
+
+fn collect_ids(space: &Space, ids: &mut HashSet<Uuid>) {
+    ids.extend(space.id);
+    ids.extend(space.items.iter().filter_map(|item| item.id));
+    for child in &space.spaces {
+        collect_ids(child, ids);
+    }
+}
+
+/// Give random ids to the spaces and items of `space` whose ids are in
+/// `taken`, and point the links below `space` that went to those ids at the
+/// new ones. Returns how many ids changed.
+pub fn renew_taken(space: &mut Space, taken: &HashSet<Uuid>) -> usize {
+    let mut renewed = HashMap::new();
+    renew_ids(space, taken, &mut renewed);
+    if !renewed.is_empty() {
+        remap_links(space, &renewed);
+    }
+    renewed.len()
+}
+
+fn renew_ids(space: &mut Space, taken: &HashSet<Uuid>, renewed: &mut HashMap<Uuid, Uuid>) {
+    if let Some(id) = space.id.filter(|id| taken.contains(id)) {
+        let new = Uuid::new_v4();
+        renewed.insert(id, new);
+        space.set_id(Some(new));
+    }
+    for item in &mut space.items {
+        if let Some(id) = item.id.filter(|id| taken.contains(id)) {
+            let new = Uuid::new_v4();
+            renewed.insert(id, new);
+            item.set_id(Some(new));
+        }
+    }
+    for child in &mut space.spaces {
+        renew_ids(child, taken, renewed);
+    }
+}
+
+fn remap_links(space: &mut Space, renewed: &HashMap<Uuid, Uuid>) {
+    for item in &mut space.items {
+        let mut changed = false;
+        for link in &mut item.links {
+            if let Some(&new) = renewed.get(&link.target) {
+                link.target = new;
+                changed = true;
+            }
+        }
+        if changed {
+            item.version += 1;
+        }
+    }
+    for child in &mut space.spaces {
+        remap_links(child, renewed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Space {
+        Space::builder()
+            .name("Kitchen")
+            .push_item(Item::builder().name("kettle").build())
+            .push_space(
+                Space::builder()
+                    .name("Drawer")
+                    .push_item(Item::builder().name("kettle").build())
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn deterministic_ids_depend_on_path_and_name() {
+        let mut a = sample();
+        let mut b = sample();
+        IdStrategy::Deterministic.assign(&mut a, &["home"]);
+        IdStrategy::Deterministic.assign(&mut b, &["home"]);
+        assert_eq!(a, b);
+
+        let kettle = a.items()[0].id().unwrap();
+        let nested = a.spaces()[0].items()[0].id().unwrap();
+        assert_ne!(kettle, nested);
+        assert_ne!(a.id(), a.spaces()[0].id());
+
+        let mut elsewhere = sample();
+        IdStrategy::Deterministic.assign(&mut elsewhere, &["cabin"]);
+        assert_ne!(elsewhere.id(), a.id());
+    }
+
+    #[test]
+    fn assign_keeps_existing_ids() {
+        let id = Uuid::new_v4();
+        let mut space = Space::builder().name("Kitchen").id(id).build();
+        IdStrategy::Random.assign(&mut space, &[]);
+        assert_eq!(space.id(), Some(id));
+
+        let mut space = sample();
+        IdStrategy::None.assign(&mut space, &[]);
+        assert_eq!(space, sample());
+    }
+
+    #[test]
+    fn merging_the_same_import_twice_adds_nothing() {
+        let mut imported = sample();
+        IdStrategy::Deterministic.assign(&mut imported, &["home"]);
+        let mut root = Space::builder().name("home").build();
+        merge_space(&mut root, imported.clone());
+        merge_space(&mut root, imported.clone());
+        assert_eq!(root.spaces().len(), 1);
+        assert_eq!(root.spaces()[0], imported);
+
+        let mut updated = sample();
+        updated.items[0].set_description("boils water");
+        updated.add_item(Item::builder().name("toaster").build());
+        IdStrategy::Deterministic.assign(&mut updated, &["home"]);
+        merge_space(&mut root, updated);
+        let kitchen = &root.spaces()[0];
+        assert_eq!(kitchen.items().len(), 2);
+        assert_eq!(kitchen.items()[0].description(), "boils water");
+        assert_eq!(kitchen.spaces()[0].items().len(), 1);
+    }
+
+    #[test]
+    fn merging_keeps_items_edited_here() {
+        let mut imported = sample();
+        IdStrategy::Deterministic.assign(&mut imported, &["home"]);
+        let mut root = Space::builder().name("home").build();
+        assert!(merge_space(&mut root, imported.clone()).is_empty());
+
+        root.spaces[0].spaces[0].items[0].set_description("descaled");
+        let mut updated = imported.clone();
+        updated.items[0].set_description("boils water");
+        // As an importer makes it, at the first version.
+        updated.spaces[0].items[0].description = "old".into();
+        assert_eq!(merge_space(&mut root, updated), ["Kitchen/Drawer/kettle"]);
+        let kitchen = &root.spaces()[0];
+        assert_eq!(kitchen.items()[0].description(), "boils water");
+        assert_eq!(kitchen.spaces()[0].items()[0].description(), "descaled");
+    }
+
+    #[test]
+    fn a_copy_renewed_against_its_own_tree_has_ids_of_its_own() {
+        let mut root = Space::builder().name("home").push_space(sample()).build();
+        IdStrategy::Deterministic.assign(&mut root, &[]);
+        let kettle = root.spaces[0].items[0].id.unwrap();
+        root.spaces[0].spaces[0].items[0].add_link(crate::LinkKind::AccessoryFor, kettle);
+
+        let mut copy = root.spaces[0].clone();
+        copy.set_name("Kitchen copy");
+        assert_eq!(renew_taken(&mut copy, &ids(&root)), 4);
+        let copied_kettle = copy.items[0].id.unwrap();
+        assert_eq!(copy.spaces[0].items[0].links()[0].target, copied_kettle);
+        assert_eq!(root.spaces[0].spaces[0].items[0].links()[0].target, kettle);
+
+        let before = ids(&root).len();
+        root.add_space(copy);
+        assert_eq!(ids(&root).len(), before + 4);
+        assert_eq!(renew_taken(&mut root.spaces[1].clone(), &HashSet::new()), 0);
+    }
+}
@@ -0,0 +1,90 @@
+//! Importers for data exported from other home inventory tools.
+//!
+//! Every importer produces a space holding one child space per location of
+//! the source, named after the tool. Names are [repaired](naming::repair)
+//! to follow the naming rules and made unique within their space.
+
+#[cfg(feature = "import-grocy")]
+pub mod grocy;
+#[cfg(feature = "import-home-assistant")]
+pub mod home_assistant;
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::cancel::Cancelled;
+use crate::naming;
+
+/// Why an import did not produce a space.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ImportError {
+    /// The input is not what the importer expects.
+    Parse(serde_json::Error),
+    /// The import was cancelled.
+    Cancelled,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Parse(e) => e.fmt(f),
+            ImportError::Cancelled => Cancelled.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(e: serde_json::Error) -> Self {
+        ImportError::Parse(e)
+    }
+}
+
+impl From<Cancelled> for ImportError {
+    fn from(_: Cancelled) -> Self {
+        ImportError::Cancelled
+    }
+}
+
+/// Space and item names already used within one space.
+#[derive(Default)]
+struct Names(HashSet<String>);
+
+impl Names {
+    /// A valid name close to `name` that is not used yet.
+    fn unique(&mut self, name: &str) -> String {
+        let base = naming::repair(name);
+        let mut candidate = base.clone();
+        let mut n = 2;
+        while !self.0.insert(candidate.clone()) {
+            candidate = format!("{base} ({n})");
+            n += 1;
+        }
+        candidate
+    }
+}
+
+/// Id fields are numbers in some exports and strings in others.
+fn id(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_repaired_and_deduplicated() {
+        let mut names = Names::default();
+        assert_eq!(names.unique("Milk"), "Milk");
+        assert_eq!(names.unique("Milk"), "Milk (2)");
+        assert_eq!(names.unique(" Milk "), "Milk (3)");
+        assert_eq!(names.unique("Salt/Pepper"), "Salt-Pepper");
+    }
+}
@@ -0,0 +1,188 @@
+//! Import from [Grocy](https://grocy.info).
+//!
+//! Grocy has no export file of its own, so the input is a JSON object
+//! collecting the responses of its REST API:
+//!
+//! ```json
+//! {
+//!   "locations": [...],       // GET /api/objects/locations
+//!   "products": [...],        // GET /api/objects/products
+//!   "stock": [...],           // GET /api/stock
+//!   "quantity_units": [...]   // GET /api/objects/quantity_units (optional)
+//! }
+//! ```
+//!
+//! Locations become spaces and products become items in the space of their
+//! default location. The amount in stock becomes the item's quantity; a
+//! fractional amount is rounded up and kept exactly in the `amount`
+//! metadata. The stock quantity unit is kept in the `unit` metadata.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{ImportError, Names, id};
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::{Item, Space};
+
+/// Name of the space holding products without a known location.
+const UNASSIGNED: &str = "Unassigned";
+
+#[derive(Deserialize)]
+struct Export {
+    locations: Vec<Value>,
+    products: Vec<Value>,
+    #[serde(default)]
+    stock: Vec<Value>,
+    #[serde(default)]
+    quantity_units: Vec<Value>,
+}
+
+/// Convert a Grocy export into a space named `Grocy`.
+pub fn import(data: &str) -> Result<Space, serde_json::Error> {
+    import_with_progress(data, |_, _| {})
+}
+
+/// Like [`import`], calling `progress` with the number of products
+/// converted so far and the total after each product.
+pub fn import_with_progress(
+    data: &str,
+    progress: impl FnMut(usize, usize),
+) -> Result<Space, serde_json::Error> {
+    let export = serde_json::from_str(data)?;
+    Ok(convert(export, &CancellationToken::new(), progress).expect("token is never cancelled"))
+}
+
+/// Like [`import_with_progress`], stopping with [`ImportError::Cancelled`]
+/// once `cancel` is cancelled.
+pub fn import_cancellable(
+    data: &str,
+    cancel: &CancellationToken,
+    progress: impl FnMut(usize, usize),
+) -> Result<Space, ImportError> {
+    let export = serde_json::from_str(data)?;
+    Ok(convert(export, cancel, progress)?)
+}
+
+fn convert(
+    export: Export,
+    cancel: &CancellationToken,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Space, Cancelled> {
+    let units: HashMap<String, &str> = export
+        .quantity_units
+        .iter()
+        .filter_map(|unit| Some((id(&unit["id"])?, unit["name"].as_str()?)))
+        .collect();
+    let mut amounts: HashMap<String, f64> = HashMap::new();
+    for entry in &export.stock {
+        if let Some(product) = id(&entry["product_id"]) {
+            *amounts.entry(product).or_default() += number(&entry["amount"]).unwrap_or(0.0);
+        }
+    }
+
+    let mut names = Names::default();
+    let mut spaces: Vec<Space> = Vec::new();
+    let mut by_location: HashMap<String, usize> = HashMap::new();
+    for location in &export.locations {
+        let name = names.unique(location["name"].as_str().unwrap_or_default());
+        if let Some(id) = id(&location["id"]) {
+            by_location.insert(id, spaces.len());
+        }
+        spaces.push(Space::builder().name(name).build());
+    }
+    let mut unassigned = None;
+    let mut item_names: HashMap<usize, Names> = HashMap::new();
+
+    let total = export.products.len();
+    for (i, product) in export.products.iter().enumerate() {
+        cancel.check()?;
+        let location = id(&product["location_id"]).and_then(|id| by_location.get(&id));
+        let index = match location {
+            Some(index) => *index,
+            None => *unassigned.get_or_insert_with(|| {
+                let name = names.unique(UNASSIGNED);
+                spaces.push(Space::builder().name(name).build());
+                spaces.len() - 1
+            }),
+        };
+        let name = item_names
+            .entry(index)
+            .or_default()
+            .unique(product["name"].as_str().unwrap_or_default());
+        let mut item = Item::builder()
+            .name(name)
+            .description(product["description"].as_str().unwrap_or_default());
+        let amount = id(&product["id"])
+            .and_then(|id| amounts.get(&id).copied())
+            .unwrap_or(0.0);
+        item = item.quantity(amount.max(0.0).ceil() as u32);
+        if amount.fract() != 0.0 {
+            item = item.metadata("amount", amount.to_string());
+        }
+        if let Some(unit) = id(&product["qu_id_stock"]).and_then(|id| units.get(&id)) {
+            item = item.metadata("unit", *unit);
+        }
+        spaces[index].add_item(item.build());
+        progress(i + 1, total);
+    }
+
+    let mut grocy = Space::builder().name("Grocy");
+    for space in spaces {
+        grocy = grocy.push_space(space);
+    }
+    Ok(grocy.build())
+}
+
+/// Amounts are numbers or numeric strings depending on the Grocy version.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_and_products_become_spaces_and_items() {
+        let data = r#"{
+            "locations": [
+                {"id": 1, "name": "Fridge", "description": ""},
+                {"id": "2", "name": "Pantry"}
+            ],
+            "products": [
+                {"id": 10, "name": "Milk", "location_id": 1, "qu_id_stock": 3},
+                {"id": 11, "name": "Flour", "description": "wheat", "location_id": "2"},
+                {"id": 12, "name": "Rice", "location_id": 2},
+                {"id": 13, "name": "Salt/Pepper", "location_id": 99}
+            ],
+            "stock": [
+                {"product_id": 10, "amount": "2"},
+                {"product_id": "11", "amount": 1.5},
+                {"product_id": 11, "amount": 1}
+            ],
+            "quantity_units": [{"id": 3, "name": "Bottle"}]
+        }"#;
+        let grocy = import(data).unwrap();
+
+        let names: Vec<_> = grocy.spaces().iter().map(|s| s.name()).collect();
+        assert_eq!(names, vec!["Fridge", "Pantry", "Unassigned"]);
+
+        let milk = grocy.find_item("Milk").unwrap();
+        assert_eq!(milk.quantity(), Some(2));
+        assert_eq!(milk.metadata()["unit"], "Bottle");
+
+        let flour = grocy.find_item("Flour").unwrap();
+        assert_eq!(flour.description(), "wheat");
+        assert_eq!(flour.quantity(), Some(3));
+        assert_eq!(flour.metadata()["amount"], "2.5");
+
+        assert_eq!(grocy.find_item("Rice").unwrap().quantity(), Some(0));
+        assert_eq!(grocy.spaces()[2].items()[0].name(), "Salt-Pepper");
+    }
+}
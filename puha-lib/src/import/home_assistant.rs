@@ -0,0 +1,165 @@
+//! Import from [Home Assistant](https://www.home-assistant.io).
+//!
+//! The input is the area and device registries Home Assistant keeps in its
+//! configuration directory, `.storage/core.area_registry` and
+//! `.storage/core.device_registry`. Areas become spaces, keeping their icon,
+//! and devices become items in the space of their area. Manufacturer, model,
+//! serial number and firmware version are kept as metadata.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::{ImportError, Names, id};
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::{Item, Space};
+
+/// Name of the space holding devices without an area.
+const UNASSIGNED: &str = "Unassigned";
+
+/// Device registry fields kept as metadata, with their metadata keys.
+const DETAILS: [(&str, &str); 4] = [
+    ("manufacturer", "manufacturer"),
+    ("model", "model"),
+    ("serial_number", "serial"),
+    ("sw_version", "firmware"),
+];
+
+/// Convert the contents of the area and device registries into a space named
+/// `Home Assistant`.
+pub fn import(areas: &str, devices: &str) -> Result<Space, serde_json::Error> {
+    import_with_progress(areas, devices, |_, _| {})
+}
+
+/// Like [`import`], calling `progress` with the number of devices handled
+/// so far and the total after each device.
+pub fn import_with_progress(
+    areas: &str,
+    devices: &str,
+    progress: impl FnMut(usize, usize),
+) -> Result<Space, serde_json::Error> {
+    let areas = serde_json::from_str(areas)?;
+    let devices = serde_json::from_str(devices)?;
+    Ok(
+        convert(&areas, &devices, &CancellationToken::new(), progress)
+            .expect("token is never cancelled"),
+    )
+}
+
+/// Like [`import_with_progress`], stopping with [`ImportError::Cancelled`]
+/// once `cancel` is cancelled.
+pub fn import_cancellable(
+    areas: &str,
+    devices: &str,
+    cancel: &CancellationToken,
+    progress: impl FnMut(usize, usize),
+) -> Result<Space, ImportError> {
+    let areas = serde_json::from_str(areas)?;
+    let devices = serde_json::from_str(devices)?;
+    Ok(convert(&areas, &devices, cancel, progress)?)
+}
+
+fn convert(
+    areas: &Value,
+    devices: &Value,
+    cancel: &CancellationToken,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Space, Cancelled> {
+    let mut names = Names::default();
+    let mut spaces: Vec<Space> = Vec::new();
+    let mut by_area: HashMap<String, usize> = HashMap::new();
+    for area in list(areas, "areas") {
+        let mut space = Space::builder().name(names.unique(text(&area["name"])));
+        if let Some(icon) = area["icon"].as_str() {
+            space = space.icon(icon);
+        }
+        if let Some(id) = id(&area["id"]) {
+            by_area.insert(id, spaces.len());
+        }
+        spaces.push(space.build());
+    }
+    let mut unassigned = None;
+    let mut item_names: HashMap<usize, Names> = HashMap::new();
+
+    let devices = list(devices, "devices");
+    for (i, device) in devices.iter().enumerate() {
+        cancel.check()?;
+        progress(i + 1, devices.len());
+        // Devices that belong to an integration's internals rather than to
+        // the house, such as services, are not inventory.
+        if device["entry_type"].as_str() == Some("service") {
+            continue;
+        }
+        let area = id(&device["area_id"]).and_then(|id| by_area.get(&id));
+        let index = match area {
+            Some(index) => *index,
+            None => *unassigned.get_or_insert_with(|| {
+                spaces.push(Space::builder().name(names.unique(UNASSIGNED)).build());
+                spaces.len() - 1
+            }),
+        };
+        // The name given in Home Assistant wins over the integration's.
+        let name = device["name_by_user"]
+            .as_str()
+            .or(device["name"].as_str())
+            .unwrap_or_default();
+        let name = item_names.entry(index).or_default().unique(name);
+        let mut item = Item::builder().name(name);
+        for (field, key) in DETAILS {
+            if let Some(value) = device[field].as_str().filter(|v| !v.is_empty()) {
+                item = item.metadata(key, value);
+            }
+        }
+        spaces[index].add_item(item.build());
+    }
+
+    let mut home = Space::builder().name("Home Assistant");
+    for space in spaces {
+        home = home.push_space(space);
+    }
+    Ok(home.build())
+}
+
+/// The `key` array inside the registry's `data` object.
+fn list<'a>(registry: &'a Value, key: &str) -> &'a [Value] {
+    registry["data"][key].as_array().map_or(&[], Vec::as_slice)
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn areas_and_devices_become_spaces_and_items() {
+        let areas = r#"{"version": 1, "key": "core.area_registry", "data": {"areas": [
+            {"id": "kitchen", "name": "Kitchen", "icon": "mdi:fridge"},
+            {"id": "garage", "name": "Garage", "icon": null}
+        ]}}"#;
+        let devices = r#"{"version": 1, "key": "core.device_registry", "data": {"devices": [
+            {"id": "a", "area_id": "kitchen", "name": "Dishwasher", "name_by_user": null,
+             "manufacturer": "Bosch", "model": "SMV4", "serial_number": "X1", "sw_version": ""},
+            {"id": "b", "area_id": "garage", "name": "Plug 3", "name_by_user": "Freezer plug"},
+            {"id": "c", "area_id": null, "name": "Phone"},
+            {"id": "d", "area_id": null, "name": "Sun", "entry_type": "service"}
+        ]}}"#;
+        let home = import(areas, devices).unwrap();
+
+        let names: Vec<_> = home.spaces().iter().map(|s| s.name()).collect();
+        assert_eq!(names, vec!["Kitchen", "Garage", "Unassigned"]);
+        assert_eq!(home.spaces()[0].icon(), Some("mdi:fridge"));
+        assert_eq!(home.spaces()[1].icon(), None);
+
+        let dishwasher = home.find_item("Dishwasher").unwrap();
+        assert_eq!(dishwasher.metadata()["manufacturer"], "Bosch");
+        assert_eq!(dishwasher.metadata()["serial"], "X1");
+        assert!(!dishwasher.metadata().contains_key("firmware"));
+
+        assert_eq!(home.spaces()[1].items()[0].name(), "Freezer plug");
+        assert!(home.find_item("Phone").is_some());
+        assert!(home.find_item("Sun").is_none());
+    }
+}
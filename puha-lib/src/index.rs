@@ -0,0 +1,499 @@
+//! Persistent inverted index for fast search over large trees.
+//!
+//! The index maps lowercase word tokens of names and descriptions to the
+//! items and spaces containing them. It remembers a hash of each space's own
+//! contents so [`SearchIndex::update`] only re-indexes spaces that changed,
+//! and the tree's synonyms, so searching it needs nothing else.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::search::{Folded, MatchKind, Synonyms, tokenize};
+use crate::{ContentHash, Space, canonical};
+
+/// Whether an indexed entry is an item or a space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Item,
+    Space,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Doc {
+    path: Vec<String>,
+    name: String,
+    description: String,
+    kind: EntryKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SpaceEntry {
+    hash: String,
+    docs: Vec<u64>,
+}
+
+/// A result of [`SearchIndex::query`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexHit {
+    /// Names of the spaces leading to the entry, starting at the root.
+    pub path: Vec<String>,
+    pub name: String,
+    /// The item's description; empty for spaces.
+    pub description: String,
+    pub kind: EntryKind,
+    pub match_kind: MatchKind,
+    /// Byte ranges of the matched text within the name, or within the
+    /// description for [`MatchKind::Description`].
+    pub spans: Vec<Range<usize>>,
+}
+
+/// Inverted index over a space tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchIndex {
+    next_id: u64,
+    docs: BTreeMap<u64, Doc>,
+    /// Keyed by the space's path, segments joined with `\u{1f}`. A space
+    /// after the first of its siblings with the same name has its place
+    /// among them appended after `\u{1e}`, so each space has its own key.
+    spaces: BTreeMap<String, SpaceEntry>,
+    postings: BTreeMap<String, BTreeSet<u64>>,
+    /// The root's synonyms as of the last build or update.
+    #[serde(default)]
+    synonyms: Synonyms,
+}
+
+impl SearchIndex {
+    /// Index every item and space of `root`.
+    pub fn build(root: &Space) -> Self {
+        Self::build_with_progress(root, |_, _| {})
+    }
+
+    /// Like [`build`](Self::build), calling `progress` with the number of
+    /// spaces indexed so far and the total after each space.
+    pub fn build_with_progress(root: &Space, progress: impl FnMut(usize, usize)) -> Self {
+        Self::build_cancellable(root, &CancellationToken::new(), progress)
+            .expect("token is never cancelled")
+    }
+
+    /// Like [`build_with_progress`](Self::build_with_progress), stopping
+    /// with [`Cancelled`] once `cancel` is cancelled.
+    pub fn build_cancellable(
+        root: &Space,
+        cancel: &CancellationToken,
+        progress: impl FnMut(usize, usize),
+    ) -> Result<Self, Cancelled> {
+        let mut index = Self::default();
+        index.update_cancellable(root, cancel, progress)?;
+        Ok(index)
+    }
+
+    /// Bring the index in line with `root`, re-indexing only the spaces
+    /// whose names or direct items changed. Returns the number of spaces
+    /// that were re-indexed or dropped.
+    pub fn update(&mut self, root: &Space) -> usize {
+        self.update_with_progress(root, |_, _| {})
+    }
+
+    /// Like [`update`](Self::update), calling `progress` with the number of
+    /// spaces checked so far and the total after each space.
+    pub fn update_with_progress(
+        &mut self,
+        root: &Space,
+        progress: impl FnMut(usize, usize),
+    ) -> usize {
+        self.update_cancellable(root, &CancellationToken::new(), progress)
+            .expect("token is never cancelled")
+    }
+
+    /// Like [`update_with_progress`](Self::update_with_progress), stopping
+    /// with [`Cancelled`] once `cancel` is cancelled. The spaces re-indexed
+    /// until then stay updated and a later update picks up the rest.
+    pub fn update_cancellable(
+        &mut self,
+        root: &Space,
+        cancel: &CancellationToken,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<usize, Cancelled> {
+        self.synonyms = root.settings().synonyms.clone();
+        let total = count_spaces(root);
+        let mut seen = BTreeSet::new();
+        let mut changed = 0;
+        let mut path = vec![(root.name().to_string(), 0)];
+        let mut report = |done| progress(done, total);
+        self.update_space(
+            root,
+            &mut path,
+            &mut seen,
+            &mut changed,
+            cancel,
+            &mut report,
+        )?;
+
+        let stale: Vec<String> = self
+            .spaces
+            .keys()
+            .filter(|key| !seen.contains(*key))
+            .cloned()
+            .collect();
+        for key in stale {
+            let entry = self.spaces.remove(&key).expect("key was just listed");
+            self.remove_docs(&entry.docs);
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    /// Index `space`, whose path ends in its own name and its place among
+    /// the siblings with that name.
+    fn update_space(
+        &mut self,
+        space: &Space,
+        path: &mut Vec<(String, usize)>,
+        seen: &mut BTreeSet<String>,
+        changed: &mut usize,
+        cancel: &CancellationToken,
+        report: &mut dyn FnMut(usize),
+    ) -> Result<(), Cancelled> {
+        cancel.check()?;
+        let key = path
+            .iter()
+            .map(|(name, n)| match n {
+                0 => name.clone(),
+                n => format!("{name}\u{1e}{n}"),
+            })
+            .collect::<Vec<_>>()
+            .join("\u{1f}");
+        let names: Vec<String> = path.iter().map(|(name, _)| name.clone()).collect();
+        let hash = shallow_hash(space).to_string();
+        let unchanged = self.spaces.get(&key).is_some_and(|e| e.hash == hash);
+        if !unchanged {
+            if let Some(old) = self.spaces.remove(&key) {
+                self.remove_docs(&old.docs);
+            }
+            let parent = names[..names.len() - 1].to_vec();
+            let mut docs = vec![self.add_doc(Doc {
+                path: parent,
+                name: space.name().to_string(),
+                description: String::new(),
+                kind: EntryKind::Space,
+            })];
+            for item in space.items() {
+                docs.push(self.add_doc(Doc {
+                    path: names.clone(),
+                    name: item.name().to_string(),
+                    description: item.description().to_string(),
+                    kind: EntryKind::Item,
+                }));
+            }
+            self.spaces.insert(key.clone(), SpaceEntry { hash, docs });
+            *changed += 1;
+        }
+        seen.insert(key);
+        report(seen.len());
+        let mut named: BTreeMap<&str, usize> = BTreeMap::new();
+        for child in space.spaces() {
+            let n = named.entry(child.name()).or_default();
+            path.push((child.name().to_string(), *n));
+            *n += 1;
+            self.update_space(child, path, seen, changed, cancel, report)?;
+            path.pop();
+        }
+        Ok(())
+    }
+
+    fn add_doc(&mut self, doc: Doc) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        for token in tokens(&doc.name).chain(tokens(&doc.description)) {
+            self.postings.entry(token).or_default().insert(id);
+        }
+        self.docs.insert(id, doc);
+        id
+    }
+
+    fn remove_docs(&mut self, ids: &[u64]) {
+        for id in ids {
+            let Some(doc) = self.docs.remove(id) else {
+                continue;
+            };
+            for token in tokens(&doc.name).chain(tokens(&doc.description)) {
+                if let Some(set) = self.postings.get_mut(&token) {
+                    set.remove(id);
+                    if set.is_empty() {
+                        self.postings.remove(&token);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Find entries whose name or description contains `query`, ignoring
+    /// case, as [`search`](crate::search::search) does, and rank them the
+    /// same way. Only entries with a word containing each word of `query`
+    /// are looked at.
+    pub fn query(&self, query: &str) -> Vec<IndexHit> {
+        let needle = query.trim().to_lowercase();
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        let mut candidates: Option<BTreeSet<u64>> = None;
+        for word in tokens(query) {
+            let ids: BTreeSet<u64> = self
+                .postings
+                .iter()
+                .filter(|(token, _)| token.contains(word.as_str()))
+                .flat_map(|(_, ids)| ids.iter().copied())
+                .collect();
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+        let candidates = candidates.unwrap_or_else(|| self.docs.keys().copied().collect());
+        let folded = Folded::new(query.trim());
+
+        let mut hits: Vec<IndexHit> = candidates
+            .into_iter()
+            .filter(|id| {
+                let doc = &self.docs[id];
+                doc.name.to_lowercase().contains(&needle)
+                    || doc.description.to_lowercase().contains(&needle)
+            })
+            .map(|id| {
+                let doc = &self.docs[&id];
+                let match_kind = rank(&doc.name.to_lowercase(), &needle);
+                let matched = match match_kind {
+                    MatchKind::Description => &doc.description,
+                    _ => &doc.name,
+                };
+                IndexHit {
+                    path: doc.path.clone(),
+                    name: doc.name.clone(),
+                    description: doc.description.clone(),
+                    kind: doc.kind,
+                    match_kind,
+                    spans: folded.find_in(matched),
+                }
+            })
+            .collect();
+        sort_hits(&mut hits);
+        hits
+    }
+
+    /// Like [`query`](Self::query), but also find entries matching any
+    /// synonym variant of `query`, each reported once with its best match.
+    pub fn query_with(&self, query: &str, synonyms: &Synonyms) -> Vec<IndexHit> {
+        let mut hits: Vec<IndexHit> = Vec::new();
+        for variant in synonyms.expand(query) {
+            for hit in self.query(&variant) {
+                match hits
+                    .iter_mut()
+                    .find(|h| h.path == hit.path && h.name == hit.name && h.kind == hit.kind)
+                {
+                    Some(existing) if existing.match_kind <= hit.match_kind => {}
+                    Some(existing) => *existing = hit,
+                    None => hits.push(hit),
+                }
+            }
+        }
+        sort_hits(&mut hits);
+        hits
+    }
+
+    /// The synonyms of the tree as last indexed, for
+    /// [`query_with`](Self::query_with).
+    pub fn synonyms(&self) -> &Synonyms {
+        &self.synonyms
+    }
+
+    /// Load an index saved with [`save_to_file`](Self::save_to_file).
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    /// Save the index to `path`, replacing the file only once it is
+    /// written completely.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let data = serde_json::to_vec(self)?;
+        crate::storage::write_atomically(path.as_ref(), |w| w.write_all(&data))?;
+        Ok(())
+    }
+}
+
+fn sort_hits(hits: &mut [IndexHit]) {
+    hits.sort_by(|a, b| {
+        a.match_kind
+            .cmp(&b.match_kind)
+            .then(a.path.len().cmp(&b.path.len()))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+fn rank(name: &str, needle: &str) -> MatchKind {
+    if name == needle {
+        MatchKind::Exact
+    } else if name.starts_with(needle) {
+        MatchKind::Prefix
+    } else if name.contains(needle) {
+        MatchKind::Substring
+    } else {
+        MatchKind::Description
+    }
+}
+
+/// Hash of a space's own name and direct items, ignoring child spaces.
+fn shallow_hash(space: &Space) -> ContentHash {
+    let items: Vec<_> = space.items().iter().map(canonical::item_value).collect();
+    let value = serde_json::json!({ "name": space.name(), "items": items });
+    ContentHash::of_bytes(value.to_string().as_bytes())
+}
+
+fn count_spaces(space: &Space) -> usize {
+    1 + space.spaces().iter().map(count_spaces).sum::<usize>()
+}
+
+fn tokens(text: &str) -> impl Iterator<Item = String> {
+    tokenize(text).into_iter().map(|token| token.text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    fn tree() -> Space {
+        let shelf = Space::builder()
+            .name("shelf")
+            .push_item(
+                Item::builder()
+                    .name("Allen key set")
+                    .description("metric hex keys")
+                    .build(),
+            )
+            .build();
+        Space::builder()
+            .name("garage")
+            .root(true)
+            .push_item(Item::builder().name("keyboard").build())
+            .push_space(shelf)
+            .build()
+    }
+
+    fn sorted_docs(index: &SearchIndex) -> Vec<Doc> {
+        let mut docs: Vec<Doc> = index.docs.values().cloned().collect();
+        docs.sort_by(|a, b| (&a.path, &a.name).cmp(&(&b.path, &b.name)));
+        docs
+    }
+
+    #[test]
+    fn build_reports_progress_per_space() {
+        let mut calls = Vec::new();
+        SearchIndex::build_with_progress(&tree(), |done, total| calls.push((done, total)));
+        assert_eq!(calls, vec![(1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn cancelled_build_stops() {
+        let cancel = CancellationToken::new();
+        let result = SearchIndex::build_cancellable(&tree(), &cancel, |_, _| cancel.cancel());
+        assert_eq!(result, Err(Cancelled));
+    }
+
+    #[test]
+    fn query_matches_like_a_search() {
+        let root = tree();
+        let index = SearchIndex::build(&root);
+
+        let names: Vec<String> = index.query("key").into_iter().map(|h| h.name).collect();
+        assert_eq!(names, vec!["keyboard", "Allen key set"]);
+
+        let hits = index.query("hex KEY");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].path, vec!["garage", "shelf"]);
+        assert_eq!(hits[0].match_kind, MatchKind::Description);
+
+        for query in ["eyboar", "len ke", "c hex"] {
+            let found: Vec<(String, Vec<Range<usize>>)> = index
+                .query(query)
+                .into_iter()
+                .map(|h| (h.name, h.spans))
+                .collect();
+            let searched: Vec<(String, Vec<Range<usize>>)> = crate::search::search(&root, query)
+                .into_iter()
+                .map(|h| (h.target.name().to_string(), h.spans))
+                .collect();
+            assert_eq!(found, searched, "{query}");
+            assert_eq!(found.len(), 1, "{query}");
+        }
+        assert!(index.query("drill").is_empty());
+        assert!(index.query("key set metric").is_empty());
+    }
+
+    #[test]
+    fn same_named_siblings_are_indexed_apart() {
+        let bin = |item: &str| {
+            Space::builder()
+                .name("bin")
+                .push_item(Item::builder().name(item).build())
+                .build()
+        };
+        let mut root = Space::builder()
+            .name("garage")
+            .push_space(bin("hammer"))
+            .push_space(bin("saw"))
+            .build();
+        let mut index = SearchIndex::build(&root);
+        assert_eq!(index.query("hammer").len(), 1);
+        assert_eq!(index.query("saw").len(), 1);
+
+        root.spaces[1].add_item(Item::builder().name("drill").build());
+        assert_eq!(index.update(&root), 1);
+        assert_eq!(index.query("hammer").len(), 1);
+        assert_eq!(index.query("bin").len(), 2);
+        assert_eq!(sorted_docs(&index), sorted_docs(&SearchIndex::build(&root)));
+    }
+
+    #[test]
+    fn update_only_reindexes_changed_spaces() {
+        let mut root = tree();
+        let mut index = SearchIndex::build(&root);
+
+        root.find_space_mut("shelf")
+            .unwrap()
+            .add_item(Item::builder().name("drill").build());
+        assert_eq!(index.update(&root), 1);
+        assert_eq!(index.query("drill").len(), 1);
+
+        root.remove_space("shelf").unwrap();
+        assert_eq!(index.update(&root), 1);
+        assert!(index.query("drill").is_empty());
+        assert_eq!(sorted_docs(&index), sorted_docs(&SearchIndex::build(&root)));
+    }
+
+    #[test]
+    fn index_keeps_the_synonyms() {
+        let mut root = tree();
+        root.settings_mut().synonyms.add_line("drill = driver");
+        let mut index = SearchIndex::build(&root);
+        assert_eq!(index.synonyms(), &root.settings().synonyms);
+
+        root.settings_mut().synonyms.remove_word("drill");
+        index.update(&root);
+        assert!(index.synonyms().is_empty());
+    }
+
+    #[test]
+    fn index_round_trips_through_file() {
+        let index = SearchIndex::build(&tree());
+        let file = tempfile::NamedTempFile::new().unwrap();
+        index.save_to_file(file.path()).unwrap();
+        assert_eq!(SearchIndex::from_file(file.path()).unwrap(), index);
+    }
+}
@@ -0,0 +1,136 @@
+//! Kits: named sets of items that belong together, such as a camping kit,
+//! wherever in the tree each item is kept.
+//!
+//! Kits are stored in the root's [`Settings`](crate::Settings) and refer to
+//! their members by item id, like [links](crate::links) do.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Space;
+use crate::links;
+
+/// Kits by name, each with the ids of its members in the order added.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct Kits {
+    kits: BTreeMap<String, Vec<Uuid>>,
+}
+
+impl Kits {
+    pub fn is_empty(&self) -> bool {
+        self.kits.is_empty()
+    }
+
+    /// The kits' names, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.kits.keys().map(String::as_str)
+    }
+
+    /// The members of the kit called `name`.
+    pub fn get(&self, name: &str) -> Option<&[Uuid]> {
+        self.kits.get(name).map(Vec::as_slice)
+    }
+
+    /// Create an empty kit, returning whether there was none by that name.
+    pub fn create(&mut self, name: &str) -> bool {
+        if self.kits.contains_key(name) {
+            return false;
+        }
+        self.kits.insert(name.to_string(), Vec::new());
+        true
+    }
+
+    /// Delete a kit, returning whether there was one by that name.
+    pub fn delete(&mut self, name: &str) -> bool {
+        self.kits.remove(name).is_some()
+    }
+
+    /// Add the item with id `member` to the kit called `name`. `None` if
+    /// there is no such kit, otherwise whether the item was not in it yet.
+    pub fn add(&mut self, name: &str, member: Uuid) -> Option<bool> {
+        let members = self.kits.get_mut(name)?;
+        if members.contains(&member) {
+            return Some(false);
+        }
+        members.push(member);
+        Some(true)
+    }
+
+    /// Remove the item with id `member` from the kit called `name`. `None`
+    /// if there is no such kit, otherwise whether the item was in it.
+    pub fn remove(&mut self, name: &str, member: Uuid) -> Option<bool> {
+        let members = self.kits.get_mut(name)?;
+        let before = members.len();
+        members.retain(|m| *m != member);
+        Some(members.len() < before)
+    }
+}
+
+/// Where a kit's member is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Member {
+    /// In the tree, at this path.
+    Present(String),
+    /// No longer in the tree.
+    Missing(Uuid),
+}
+
+/// Look up the `members` of a kit under `root`, in the kit's order.
+pub fn check(root: &Space, members: &[Uuid]) -> Vec<Member> {
+    members
+        .iter()
+        .map(|&id| match links::path_of(root, id) {
+            Some(path) => Member::Present(path),
+            None => Member::Missing(id),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn kits_hold_members_once() {
+        let mut kits = Kits::default();
+        assert!(kits.create("camping"));
+        assert!(!kits.create("camping"));
+        assert_eq!(kits.add("camping", Uuid::from_u128(1)), Some(true));
+        assert_eq!(kits.add("camping", Uuid::from_u128(1)), Some(false));
+        assert_eq!(kits.add("diving", Uuid::from_u128(1)), None);
+        assert_eq!(kits.get("camping"), Some(&[Uuid::from_u128(1)][..]));
+        assert_eq!(kits.remove("camping", Uuid::from_u128(1)), Some(true));
+        assert_eq!(kits.remove("camping", Uuid::from_u128(1)), Some(false));
+        assert_eq!(kits.names().collect::<Vec<_>>(), ["camping"]);
+        assert!(kits.delete("camping"));
+        assert!(kits.is_empty());
+    }
+
+    #[test]
+    fn check_finds_members_anywhere() {
+        let tent = Uuid::from_u128(1);
+        let stove = Uuid::from_u128(2);
+        let root = Space::builder()
+            .name("home")
+            .push_space(
+                Space::builder()
+                    .name("attic")
+                    .push_item(Item::builder().name("tent").id(tent).build())
+                    .build(),
+            )
+            .build();
+
+        assert_eq!(
+            check(&root, &[tent, stove]),
+            [
+                Member::Present("home/attic/tent".into()),
+                Member::Missing(stove)
+            ]
+        );
+    }
+}
@@ -1,20 +1,179 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub mod assets;
+pub mod audit;
+pub mod cancel;
+pub mod canonical;
+pub mod capacity;
+pub mod collation;
+pub mod diff;
+pub mod error;
+pub mod formats;
+pub mod ids;
+#[cfg(any(feature = "import-grocy", feature = "import-home-assistant"))]
+pub mod import;
+#[cfg(feature = "search-index")]
+pub mod index;
+pub mod kits;
+pub mod limits;
+pub mod links;
+pub mod lock;
+pub mod matching;
+pub mod naming;
+pub mod patch;
+pub mod path;
+pub mod prelude;
+pub mod query;
+pub mod quick;
+pub mod redact;
+pub mod sample;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod search;
+pub mod settings;
+pub mod shared;
+pub mod shopping;
+pub mod sniff;
+pub mod stats;
+pub mod stock;
+pub mod storage;
+pub mod strict;
+pub mod suggest;
+pub mod tags;
+pub mod template;
+pub mod transfer;
+pub mod value;
+pub mod views;
+pub mod warranty;
+
+pub use assets::Assets;
+pub use cancel::{CancellationToken, Cancelled};
+pub use canonical::ContentHash;
+pub use capacity::{Size, Weight};
+pub use collation::Collation;
+pub use diff::TreeDiff;
+pub use formats::Formats;
+pub use ids::IdStrategy;
+pub use kits::Kits;
+pub use limits::{Limits, TreeLimits};
+pub use links::{Link, LinkKind};
+pub use lock::SpaceLocked;
+pub use matching::MatchOptions;
+pub use naming::InvalidName;
+pub use patch::{Patch, PatchError};
+pub use query::Query;
+pub use redact::Redaction;
+pub use search::{MatchKind, SearchHit, Synonyms};
+pub use settings::Settings;
+pub use shared::SharedSpace;
+pub use shopping::ShoppingList;
+pub use stock::{StockChange, Timestamp};
+pub use storage::{Backend, SaveOptions, Storage};
+pub use strict::StrictError;
+pub use template::Template;
+pub use value::{Date, Depreciation, Price, Valuation};
+pub use views::Views;
 
 /// Returns a greeting string from `puha-lib`.
 pub fn greet() -> &'static str {
     "Hello from puha-lib!"
 }
 
+/// Returned by `update_if_version` when the entity was changed by someone
+/// else since the caller last looked at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionConflict {
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl std::fmt::Display for VersionConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "version conflict: expected version {}, found {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for VersionConflict {}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Item {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<Uuid>,
     name: String,
     description: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quantity: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_quantity: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stock_changes: Vec<StockChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    purchase_date: Option<Date>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    purchase_price: Option<Price>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    warranty_until: Option<Date>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    receipts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    links: Vec<Link>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_verified_at: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    missing_at: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<Size>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weight: Option<Weight>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default)]
+    version: u64,
+    /// Fields this version of puha does not know about, kept so they survive
+    /// a load/save round trip.
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Default)]
 pub struct ItemBuilder {
+    id: Option<Uuid>,
     name: String,
     description: String,
+    metadata: BTreeMap<String, String>,
+    tags: BTreeSet<String>,
+    attachments: Vec<String>,
+    quantity: Option<u32>,
+    min_quantity: Option<u32>,
+    category: Option<String>,
+    purchase_date: Option<Date>,
+    purchase_price: Option<Price>,
+    warranty_until: Option<Date>,
+    receipts: Vec<String>,
+    links: Vec<Link>,
+    size: Option<Size>,
+    weight: Option<Weight>,
+    icon: Option<String>,
+    color: Option<String>,
 }
 
 impl ItemBuilder {
@@ -22,6 +181,11 @@ impl ItemBuilder {
         Self::default()
     }
 
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.id = Some(id);
+        self
+    }
+
     pub fn name(mut self, name: impl Into<String>) -> Self {
         self.name = name.into();
         self
@@ -32,12 +196,115 @@ impl ItemBuilder {
         self
     }
 
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    pub fn attachment(mut self, path: impl Into<String>) -> Self {
+        self.attachments.push(path.into());
+        self
+    }
+
+    pub fn quantity(mut self, quantity: u32) -> Self {
+        self.quantity = Some(quantity);
+        self
+    }
+
+    pub fn min_quantity(mut self, min_quantity: u32) -> Self {
+        self.min_quantity = Some(min_quantity);
+        self
+    }
+
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    pub fn purchase_date(mut self, date: Date) -> Self {
+        self.purchase_date = Some(date);
+        self
+    }
+
+    pub fn purchase_price(mut self, price: Price) -> Self {
+        self.purchase_price = Some(price);
+        self
+    }
+
+    pub fn warranty_until(mut self, date: Date) -> Self {
+        self.warranty_until = Some(date);
+        self
+    }
+
+    pub fn receipt(mut self, receipt: impl Into<String>) -> Self {
+        self.receipts.push(receipt.into());
+        self
+    }
+
+    pub fn link(mut self, kind: LinkKind, target: Uuid) -> Self {
+        self.links.push(Link { kind, target });
+        self
+    }
+
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn weight(mut self, weight: Weight) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
     pub fn build(self) -> Item {
         Item {
+            id: self.id,
             name: self.name,
             description: self.description,
+            metadata: self.metadata,
+            tags: self.tags,
+            attachments: self.attachments,
+            quantity: self.quantity,
+            min_quantity: self.min_quantity,
+            stock_changes: Vec::new(),
+            category: self.category,
+            purchase_date: self.purchase_date,
+            purchase_price: self.purchase_price,
+            warranty_until: self.warranty_until,
+            receipts: self.receipts,
+            links: self.links,
+            last_verified_at: None,
+            missing_at: None,
+            size: self.size,
+            weight: self.weight,
+            icon: self.icon,
+            color: self.color,
+            version: 0,
+            extra: serde_json::Map::new(),
         }
     }
+
+    /// Like [`build`](Self::build), but reject names that break the
+    /// [naming rules](naming).
+    pub fn try_build(self) -> Result<Item, InvalidName> {
+        naming::validate(&self.name)?;
+        Ok(self.build())
+    }
 }
 
 impl Item {
@@ -45,6 +312,11 @@ impl Item {
         ItemBuilder::new()
     }
 
+    /// Stable identifier, if the item has one; see [`IdStrategy`].
+    pub fn id(&self) -> Option<Uuid> {
+        self.id
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -53,29 +325,262 @@ impl Item {
         &self.description
     }
 
+    /// Free-form key/value details such as serial numbers.
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Labels for grouping items across spaces, such as `consumables`.
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    /// Files attached to the item, such as photos of it: names of files in
+    /// the [`Assets`] store, or absolute paths.
+    pub fn attachments(&self) -> &[String] {
+        &self.attachments
+    }
+
+    /// How many of this item there are, if counted.
+    pub fn quantity(&self) -> Option<u32> {
+        self.quantity
+    }
+
+    /// How many of this item to keep in stock; below it, the item goes on
+    /// the [shopping list](shopping).
+    pub fn min_quantity(&self) -> Option<u32> {
+        self.min_quantity
+    }
+
+    /// Kind of item, such as `electronics`, which decides how it
+    /// [depreciates](value).
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    pub fn purchase_date(&self) -> Option<Date> {
+        self.purchase_date
+    }
+
+    pub fn purchase_price(&self) -> Option<Price> {
+        self.purchase_price
+    }
+
+    /// Last day the item is under warranty.
+    pub fn warranty_until(&self) -> Option<Date> {
+        self.warranty_until
+    }
+
+    /// Receipts and other documents for the item, referred to like
+    /// [`attachments`](Self::attachments).
+    pub fn receipts(&self) -> &[String] {
+        &self.receipts
+    }
+
+    /// Emoji or glyph name shown next to the item's name.
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
+    }
+
+    /// Colour the item's name is shown in, as a colour name or `#rrggbb`.
+    pub fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    /// Unrecognized fields read from the data file.
+    pub fn extra(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.extra
+    }
+
+    /// Number of modifications made to this item so far.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn set_id(&mut self, id: Option<Uuid>) {
+        self.id = id;
+        self.version += 1;
+    }
+
     pub fn set_name(&mut self, name: impl Into<String>) {
         self.name = name.into();
+        self.version += 1;
     }
 
     pub fn set_description(&mut self, description: impl Into<String>) {
         self.description = description.into();
+        self.version += 1;
+    }
+
+    pub fn set_quantity(&mut self, quantity: Option<u32>) {
+        self.quantity = quantity;
+        self.version += 1;
+    }
+
+    pub fn set_min_quantity(&mut self, min_quantity: Option<u32>) {
+        self.min_quantity = min_quantity;
+        self.version += 1;
+    }
+
+    pub fn set_category(&mut self, category: Option<String>) {
+        self.category = category;
+        self.version += 1;
+    }
+
+    pub fn set_purchase_date(&mut self, date: Option<Date>) {
+        self.purchase_date = date;
+        self.version += 1;
+    }
+
+    pub fn set_purchase_price(&mut self, price: Option<Price>) {
+        self.purchase_price = price;
+        self.version += 1;
+    }
+
+    pub fn set_warranty_until(&mut self, date: Option<Date>) {
+        self.warranty_until = date;
+        self.version += 1;
+    }
+
+    pub fn add_receipt(&mut self, receipt: impl Into<String>) {
+        self.receipts.push(receipt.into());
+        self.version += 1;
+    }
+
+    pub fn set_icon(&mut self, icon: Option<String>) {
+        self.icon = icon;
+        self.version += 1;
+    }
+
+    pub fn set_color(&mut self, color: Option<String>) {
+        self.color = color;
+        self.version += 1;
+    }
+
+    /// Set a metadata value, returning the previous one.
+    pub fn set_metadata(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Option<String> {
+        self.version += 1;
+        self.metadata.insert(key.into(), value.into())
+    }
+
+    /// Add a tag, returning whether the item did not have it yet.
+    pub fn add_tag(&mut self, tag: impl Into<String>) -> bool {
+        let added = self.tags.insert(tag.into());
+        if added {
+            self.version += 1;
+        }
+        added
+    }
+
+    /// Remove a tag, returning whether the item had it.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let removed = self.tags.remove(tag);
+        if removed {
+            self.version += 1;
+        }
+        removed
+    }
+
+    pub fn add_attachment(&mut self, path: impl Into<String>) {
+        self.attachments.push(path.into());
+        self.version += 1;
+    }
+
+    /// Remove a metadata value, returning it if it was present.
+    pub fn remove_metadata(&mut self, key: &str) -> Option<String> {
+        let removed = self.metadata.remove(key);
+        if removed.is_some() {
+            self.version += 1;
+        }
+        removed
+    }
+
+    /// Apply `f` only if the item is still at version `expected`.
+    ///
+    /// On success the item ends up at version `expected + 1`, however many
+    /// setters `f` called.
+    pub fn update_if_version<R>(
+        &mut self,
+        expected: u64,
+        f: impl FnOnce(&mut Item) -> R,
+    ) -> Result<R, VersionConflict> {
+        if self.version != expected {
+            return Err(VersionConflict {
+                expected,
+                actual: self.version,
+            });
+        }
+        let result = f(self);
+        self.version = expected + 1;
+        Ok(result)
+    }
+}
+
+/// A window into a longer list of results.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<'a, T> {
+    /// The entries in this page.
+    pub entries: Vec<&'a T>,
+    /// Position of the first entry within all matching results.
+    pub offset: usize,
+    /// Number of matching results across all pages.
+    pub total: usize,
+}
+
+impl<T> Page<'_, T> {
+    /// Whether further results follow this page.
+    pub fn has_more(&self) -> bool {
+        self.offset + self.entries.len() < self.total
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Space {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<Uuid>,
     name: String,
     items: Vec<Item>,
     spaces: Vec<Space>,
     root: bool,
+    /// Guards the space and everything below it against changes.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    locked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    capacity: Option<Size>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_weight: Option<Weight>,
+    #[serde(default)]
+    version: u64,
+    /// Only meaningful on the root space.
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    settings: Settings,
+    /// Fields this version of puha does not know about, kept so they survive
+    /// a load/save round trip.
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Default)]
 pub struct SpaceBuilder {
+    id: Option<Uuid>,
     name: String,
     items: Vec<Item>,
     spaces: Vec<Space>,
     root: bool,
+    locked: bool,
+    icon: Option<String>,
+    color: Option<String>,
+    capacity: Option<Size>,
+    max_weight: Option<Weight>,
 }
 
 impl SpaceBuilder {
@@ -83,6 +588,11 @@ impl SpaceBuilder {
         Self::default()
     }
 
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.id = Some(id);
+        self
+    }
+
     pub fn name(mut self, name: impl Into<String>) -> Self {
         self.name = name.into();
         self
@@ -93,6 +603,11 @@ impl SpaceBuilder {
         self
     }
 
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
     pub fn items(mut self, items: Vec<Item>) -> Self {
         self.items = items;
         self
@@ -113,14 +628,50 @@ impl SpaceBuilder {
         self
     }
 
+    pub fn icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn capacity(mut self, capacity: Size) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    pub fn max_weight(mut self, max_weight: Weight) -> Self {
+        self.max_weight = Some(max_weight);
+        self
+    }
+
     pub fn build(self) -> Space {
         Space {
+            id: self.id,
             name: self.name,
             items: self.items,
             spaces: self.spaces,
             root: self.root,
+            locked: self.locked,
+            icon: self.icon,
+            color: self.color,
+            capacity: self.capacity,
+            max_weight: self.max_weight,
+            version: 0,
+            settings: Settings::default(),
+            extra: serde_json::Map::new(),
         }
     }
+
+    /// Like [`build`](Self::build), but reject names that break the
+    /// [naming rules](naming).
+    pub fn try_build(self) -> Result<Space, InvalidName> {
+        naming::validate(&self.name)?;
+        Ok(self.build())
+    }
 }
 
 impl Space {
@@ -128,6 +679,11 @@ impl Space {
         SpaceBuilder::new()
     }
 
+    /// Stable identifier, if the space has one; see [`IdStrategy`].
+    pub fn id(&self) -> Option<Uuid> {
+        self.id
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -140,95 +696,437 @@ impl Space {
         &self.spaces
     }
 
+    /// Return at most `limit` items of this space starting at `offset`.
+    pub fn items_page(&self, offset: usize, limit: usize) -> Page<'_, Item> {
+        self.items_page_filtered(offset, limit, |_| true)
+    }
+
+    /// Like [`items_page`](Self::items_page), but only counts and returns the
+    /// items for which `filter` returns `true`.
+    pub fn items_page_filtered(
+        &self,
+        offset: usize,
+        limit: usize,
+        mut filter: impl FnMut(&Item) -> bool,
+    ) -> Page<'_, Item> {
+        let mut total = 0;
+        let mut entries = Vec::new();
+        for item in &self.items {
+            if !filter(item) {
+                continue;
+            }
+            if total >= offset && entries.len() < limit {
+                entries.push(item);
+            }
+            total += 1;
+        }
+        Page {
+            entries,
+            offset,
+            total,
+        }
+    }
+
+    /// Like [`items_page_filtered`](Self::items_page_filtered), with the
+    /// matching items ordered by name using `collation` before paging.
+    pub fn items_page_sorted(
+        &self,
+        offset: usize,
+        limit: usize,
+        mut filter: impl FnMut(&Item) -> bool,
+        collation: &Collation,
+    ) -> Page<'_, Item> {
+        let mut matching: Vec<&Item> = self.items.iter().filter(|i| filter(i)).collect();
+        collation.sort_by(&mut matching, |item| &item.name);
+        let total = matching.len();
+        Page {
+            entries: matching.into_iter().skip(offset).take(limit).collect(),
+            offset,
+            total,
+        }
+    }
+
     pub fn root(&self) -> bool {
         self.root
     }
 
+    /// Whether the space and everything below it are guarded against
+    /// changes; see [`lock`].
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Emoji or glyph name shown next to the space's name.
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
+    }
+
+    /// Colour the space's name is shown in, as a colour name or `#rrggbb`.
+    pub fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    /// Unrecognized fields read from the data file.
+    pub fn extra(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.extra
+    }
+
+    /// Tree-wide settings. Only the root space's settings are used.
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Mutable access to the settings; counts as a modification.
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        self.version += 1;
+        &mut self.settings
+    }
+
+    /// Number of modifications made to this space so far.
+    ///
+    /// Only changes to the space itself count: renaming it or adding and
+    /// removing its direct items and child spaces.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn set_id(&mut self, id: Option<Uuid>) {
+        self.id = id;
+        self.version += 1;
+    }
+
     pub fn set_name(&mut self, name: impl Into<String>) {
         self.name = name.into();
+        self.version += 1;
     }
 
     pub fn set_root(&mut self, root: bool) {
         self.root = root;
+        self.version += 1;
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+        self.version += 1;
+    }
+
+    pub fn set_icon(&mut self, icon: Option<String>) {
+        self.icon = icon;
+        self.version += 1;
+    }
+
+    pub fn set_color(&mut self, color: Option<String>) {
+        self.color = color;
+        self.version += 1;
     }
 
     pub fn add_item(&mut self, item: Item) {
         self.items.push(item);
+        self.version += 1;
     }
 
     pub fn add_space(&mut self, space: Space) {
         self.spaces.push(space);
+        self.version += 1;
+    }
+
+    /// Apply `f` only if the space is still at version `expected`.
+    ///
+    /// On success the space ends up at version `expected + 1`, however many
+    /// modifications `f` made.
+    pub fn update_if_version<R>(
+        &mut self,
+        expected: u64,
+        f: impl FnOnce(&mut Space) -> R,
+    ) -> Result<R, VersionConflict> {
+        if self.version != expected {
+            return Err(VersionConflict {
+                expected,
+                actual: self.version,
+            });
+        }
+        let result = f(self);
+        self.version = expected + 1;
+        Ok(result)
     }
 
     /// Recursively search for a space and return a mutable reference if found.
     pub fn find_space_mut<'a>(&'a mut self, name: &str) -> Option<&'a mut Space> {
-        if self.name == name {
-            return Some(self);
-        }
-        for space in &mut self.spaces {
-            if let Some(found) = space.find_space_mut(name) {
-                return Some(found);
-            }
-        }
-        None
+        self.find_space_mut_with(name, &MatchOptions::exact())
+    }
+
+    /// Like [`find_space_mut`](Self::find_space_mut), comparing names with `options`.
+    pub fn find_space_mut_with<'a>(
+        &'a mut self,
+        name: &str,
+        options: &MatchOptions,
+    ) -> Option<&'a mut Space> {
+        let path = self.path_to(|space| options.matches(&space.name, name))?;
+        Some(self.descend_mut(&path))
+    }
+
+    /// Recursively search for an item by name.
+    pub fn find_item(&self, name: &str) -> Option<&Item> {
+        self.find_item_with(name, &MatchOptions::exact())
+    }
+
+    /// Like [`find_item`](Self::find_item), comparing names with `options`.
+    pub fn find_item_with(&self, name: &str, options: &MatchOptions) -> Option<&Item> {
+        let matches = |item: &Item| options.matches(&item.name, name);
+        let path = self.path_to(|space| space.items.iter().any(matches))?;
+        self.descend(&path).items.iter().find(|item| matches(item))
+    }
+
+    /// Recursively search for an item and return a mutable reference if found.
+    pub fn find_item_mut(&mut self, name: &str) -> Option<&mut Item> {
+        self.find_item_mut_with(name, &MatchOptions::exact())
+    }
+
+    /// Like [`find_item_mut`](Self::find_item_mut), comparing names with `options`.
+    pub fn find_item_mut_with(&mut self, name: &str, options: &MatchOptions) -> Option<&mut Item> {
+        let matches = |item: &Item| options.matches(&item.name, name);
+        let path = self.path_to(|space| space.items.iter().any(matches))?;
+        self.descend_mut(&path)
+            .items
+            .iter_mut()
+            .find(|item| matches(item))
     }
 
     /// Remove an item by name from this space or any child space.
     pub fn remove_item(&mut self, name: &str) -> Option<Item> {
-        if let Some(pos) = self.items.iter().position(|i| i.name == name) {
-            return Some(self.items.remove(pos));
-        }
-        for space in &mut self.spaces {
-            if let Some(item) = space.remove_item(name) {
-                return Some(item);
-            }
-        }
-        None
+        self.remove_item_with(name, &MatchOptions::exact())
+    }
+
+    /// Like [`remove_item`](Self::remove_item), comparing names with `options`.
+    pub fn remove_item_with(&mut self, name: &str, options: &MatchOptions) -> Option<Item> {
+        let matches = |item: &Item| options.matches(&item.name, name);
+        let path = self.path_to(|space| space.items.iter().any(matches))?;
+        let space = self.descend_mut(&path);
+        let pos = space.items.iter().position(matches)?;
+        space.version += 1;
+        Some(space.items.remove(pos))
+    }
+
+    /// The item with id `id` in this space or any child space.
+    pub fn find_item_by_id_mut(&mut self, id: Uuid) -> Option<&mut Item> {
+        let matches = |item: &Item| item.id == Some(id);
+        let path = self.path_to(|space| space.items.iter().any(matches))?;
+        self.descend_mut(&path)
+            .items
+            .iter_mut()
+            .find(|item| matches(item))
+    }
+
+    /// Remove the item with id `id` from this space or any child space.
+    pub fn remove_item_by_id(&mut self, id: Uuid) -> Option<Item> {
+        let matches = |item: &Item| item.id == Some(id);
+        let path = self.path_to(|space| space.items.iter().any(matches))?;
+        let space = self.descend_mut(&path);
+        let pos = space.items.iter().position(matches)?;
+        space.version += 1;
+        Some(space.items.remove(pos))
     }
 
     /// Remove a child space by name and return it if found.
     pub fn remove_space(&mut self, name: &str) -> Option<Space> {
-        if let Some(pos) = self.spaces.iter().position(|s| s.name == name) {
-            return Some(self.spaces.remove(pos));
+        self.remove_space_with(name, &MatchOptions::exact())
+    }
+
+    /// Like [`remove_space`](Self::remove_space), comparing names with `options`.
+    pub fn remove_space_with(&mut self, name: &str, options: &MatchOptions) -> Option<Space> {
+        let matches = |space: &Space| options.matches(&space.name, name);
+        let path = self.path_to(|space| space.spaces.iter().any(matches))?;
+        let parent = self.descend_mut(&path);
+        let pos = parent.spaces.iter().position(matches)?;
+        parent.version += 1;
+        Some(parent.spaces.remove(pos))
+    }
+
+    /// How many levels of spaces there are below this one.
+    pub fn depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut stack = vec![(0, self)];
+        while let Some((depth, space)) = stack.pop() {
+            deepest = deepest.max(depth);
+            stack.extend(space.spaces.iter().map(|child| (depth + 1, child)));
         }
-        for space in &mut self.spaces {
-            if let Some(found) = space.remove_space(name) {
-                return Some(found);
+        deepest
+    }
+
+    /// Indices of the spaces leading from this one to the first space below
+    /// and including it, in depth-first order, for which `found` holds.
+    ///
+    /// Lookups walk the tree with an explicit stack rather than recursion,
+    /// so trees nested deeper than the call stack allows cannot crash them.
+    fn path_to(&self, mut found: impl FnMut(&Space) -> bool) -> Option<Vec<usize>> {
+        let mut path = Vec::new();
+        let mut stack = vec![(0, 0, self)];
+        while let Some((depth, index, space)) = stack.pop() {
+            if depth > 0 {
+                path.truncate(depth - 1);
+                path.push(index);
             }
+            if found(space) {
+                return Some(path);
+            }
+            let children = space.spaces.iter().enumerate().rev();
+            stack.extend(children.map(|(i, child)| (depth + 1, i, child)));
         }
         None
     }
 
+    fn descend(&self, path: &[usize]) -> &Space {
+        path.iter().fold(self, |space, &i| &space.spaces[i])
+    }
+
+    fn descend_mut(&mut self, path: &[usize]) -> &mut Space {
+        path.iter().fold(self, |space, &i| &mut space.spaces[i])
+    }
+
+    /// Hash of the tree's canonical form.
+    ///
+    /// Equal hashes mean the trees hold the same data, ignoring the order of
+    /// items and spaces, surrounding whitespace and version counters. This is
+    /// much cheaper to compare and store than the trees themselves.
+    pub fn canonical_hash(&self) -> ContentHash {
+        canonical::hash_space(self)
+    }
+
+    /// Search this space and everything below it; see [`search::search`].
+    pub fn search(&self, query: &str) -> Vec<SearchHit<'_>> {
+        search::search(self, query)
+    }
+
     pub fn find_space<'a>(&'a self, name: &str) -> Option<&'a Space> {
-        if self.name == name {
-            return Some(self);
-        }
-        for space in &self.spaces {
-            if let Some(found) = space.find_space(name) {
-                return Some(found);
-            }
+        self.find_space_with(name, &MatchOptions::exact())
+    }
+
+    /// Like [`find_space`](Self::find_space), comparing names with `options`.
+    pub fn find_space_with<'a>(&'a self, name: &str, options: &MatchOptions) -> Option<&'a Space> {
+        let path = self.path_to(|space| options.matches(&space.name, name))?;
+        Some(self.descend(&path))
+    }
+
+    /// Like [`find_space_with`](Self::find_space_with), but also return the
+    /// names of the spaces leading to the found space, starting at `self`.
+    pub fn locate_space_with<'a>(
+        &'a self,
+        name: &str,
+        options: &MatchOptions,
+    ) -> Option<(Vec<&'a str>, &'a Space)> {
+        let path = self.path_to(|space| options.matches(&space.name, name))?;
+        let mut parents = Vec::with_capacity(path.len());
+        let mut space = self;
+        for &i in &path {
+            parents.push(space.name.as_str());
+            space = &space.spaces[i];
         }
-        None
+        Some((parents, space))
     }
 
     pub fn save_to_file<P: AsRef<std::path::Path>>(
         &self,
         path: P,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_string_pretty(self)?;
-        std::fs::write(path, json)?;
+        self.save_to_file_with(path, &SaveOptions::default())
+    }
+
+    /// Save the tree, formatting the JSON according to `options`.
+    pub fn save_to_file_with<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        options: &SaveOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        limits::check_depth(self)?;
+        storage::write_atomically(path.as_ref(), |writer| {
+            self.save_to_writer(writer, options)
+                .map_err(std::io::Error::from)
+        })?;
         Ok(())
     }
 
+    /// Write the tree as JSON formatted according to `options`. Small
+    /// writes are not buffered, so wrap unbuffered writers in a
+    /// [`BufWriter`](std::io::BufWriter).
+    pub fn save_to_writer<W: Write>(
+        &self,
+        writer: W,
+        options: &SaveOptions,
+    ) -> serde_json::Result<()> {
+        options.to_writer(writer, self)
+    }
+
+    /// Load a tree saved with [`save_to_file`](Self::save_to_file). A file
+    /// that is not puha data at all, such as an empty file or a CSV export,
+    /// fails with advice instead of the JSON parser's complaint.
     pub fn from_file<P: AsRef<std::path::Path>>(
         path: P,
     ) -> Result<Self, Box<dyn std::error::Error>> {
+        use std::io::BufRead;
+
+        let path = path.as_ref();
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        if reader.fill_buf()?.starts_with(sniff::BOM.as_bytes()) {
+            reader.consume(sniff::BOM.len());
+        }
+        Self::from_reader(reader).map_err(|e| sniff::explain(path, e))
+    }
+
+    /// Read a tree from JSON as it arrives, without holding all of the text
+    /// in memory. Like writers, unbuffered readers are best wrapped in a
+    /// [`BufReader`](std::io::BufReader).
+    pub fn from_reader<R: std::io::Read>(reader: R) -> serde_json::Result<Self> {
+        serde_json::from_reader(reader)
+    }
+
+    /// Load a tree like [`from_file`](Self::from_file), but reject unknown
+    /// fields, duplicate names and misplaced root flags.
+    pub fn from_file_strict<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
         let data = std::fs::read_to_string(path)?;
-        let space = serde_json::from_str(&data)?;
+        let space = strict::parse(sniff::strip_bom(&data)).map_err(|e| {
+            let problem = sniff::mistake(&data).map_or_else(|| e.to_string(), str::to_string);
+            format!("{}: {problem}", path.display())
+        })?;
+        strict::check_structure(&space)?;
         Ok(space)
     }
 }
 
+/// An item from its name and description.
+impl From<(&str, &str)> for Item {
+    fn from((name, description): (&str, &str)) -> Self {
+        Item::builder().name(name).description(description).build()
+    }
+}
+
+/// Adds the items like [`Space::add_item`], bumping the version once.
+impl Extend<Item> for Space {
+    fn extend<I: IntoIterator<Item = Item>>(&mut self, items: I) {
+        let before = self.items.len();
+        self.items.extend(items);
+        if self.items.len() > before {
+            self.version += 1;
+        }
+    }
+}
+
+/// A builder holding the items, to be given a name and built.
+impl FromIterator<Item> for SpaceBuilder {
+    fn from_iter<I: IntoIterator<Item = Item>>(items: I) -> Self {
+        Space::builder().items(items.into_iter().collect())
+    }
+}
+
+/// A builder holding the spaces, to be given a name and built.
+impl FromIterator<Space> for SpaceBuilder {
+    fn from_iter<I: IntoIterator<Item = Space>>(spaces: I) -> Self {
+        Space::builder().spaces(spaces.into_iter().collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -286,4 +1184,210 @@ mod tests {
         let loaded = Space::from_file(file.path()).unwrap();
         assert_eq!(loaded, root);
     }
+
+    #[test]
+    fn save_to_writer_and_load_from_reader() {
+        let root = Space::builder()
+            .name("root")
+            .root(true)
+            .push_item(Item::builder().name("lamp").build())
+            .build();
+        let options = SaveOptions::compact();
+        let mut out = Vec::new();
+        root.save_to_writer(&mut out, &options).unwrap();
+        assert_eq!(out, options.to_vec(&root).unwrap());
+        assert_eq!(Space::from_reader(out.as_slice()).unwrap(), root);
+    }
+
+    #[test]
+    fn from_file_skips_a_bom_and_explains_other_formats() {
+        let root = Space::builder().name("root").root(true).build();
+        let mut data = sniff::BOM.as_bytes().to_vec();
+        data.extend(SaveOptions::compact().to_vec(&root).unwrap());
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), data).unwrap();
+        assert_eq!(Space::from_file(file.path()).unwrap(), root);
+
+        std::fs::write(file.path(), "name,description\nlamp,desk\n").unwrap();
+        let error = Space::from_file(file.path()).unwrap_err().to_string();
+        assert!(error.contains("CSV"), "{error}");
+        std::fs::write(file.path(), "{\"name\": ").unwrap();
+        let error = Space::from_file(file.path()).unwrap_err().to_string();
+        assert!(error.contains("EOF"), "{error}");
+    }
+
+    #[test]
+    fn unknown_fields_survive_round_trip() {
+        let json = r#"{
+            "name": "root",
+            "items": [{"name": "pen", "description": "", "colour": "blue"}],
+            "spaces": [],
+            "root": true,
+            "owner": {"name": "Ada"}
+        }"#;
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+
+        let root = Space::from_file(file.path()).unwrap();
+        assert_eq!(root.extra()["owner"]["name"], "Ada");
+        assert_eq!(root.items()[0].extra()["colour"], "blue");
+
+        root.save_to_file(file.path()).unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(file.path()).unwrap()).unwrap();
+        assert_eq!(saved["owner"]["name"], "Ada");
+        assert_eq!(saved["items"][0]["colour"], "blue");
+    }
+
+    #[test]
+    fn trees_collect_from_iterators() {
+        let rooms = ["kitchen", "garage"].map(|name| {
+            [("lamp", "by the door"), ("rug", "")]
+                .into_iter()
+                .map(Item::from)
+                .collect::<SpaceBuilder>()
+                .name(name)
+                .build()
+        });
+        let mut root = rooms
+            .into_iter()
+            .collect::<SpaceBuilder>()
+            .name("home")
+            .build();
+        assert_eq!(root.spaces()[1].name(), "garage");
+        assert_eq!(root.spaces()[1].items()[0].description(), "by the door");
+
+        root.extend([Item::from(("drill", "18V")), Item::from(("saw", ""))]);
+        assert_eq!(root.items().len(), 2);
+        assert_eq!(root.version(), 1);
+        root.extend(Vec::new());
+        assert_eq!(root.version(), 1);
+    }
+
+    #[test]
+    fn mutations_bump_versions() {
+        let mut root = Space::builder().name("root").build();
+        root.add_space(Space::builder().name("box").build());
+        assert_eq!(root.version(), 1);
+
+        let target = root.find_space_mut("box").unwrap();
+        target.add_item(Item::builder().name("pen").build());
+        assert_eq!(target.version(), 1);
+
+        let mut pen = root.remove_item("pen").unwrap();
+        assert_eq!(root.find_space("box").unwrap().version(), 2);
+        assert_eq!(root.version(), 1);
+
+        pen.set_description("blue");
+        assert_eq!(pen.version(), 1);
+    }
+
+    #[test]
+    fn update_if_version_rejects_stale_versions() {
+        let mut item = Item::builder().name("pen").build();
+        item.update_if_version(0, |i| {
+            i.set_name("pencil");
+            i.set_description("HB");
+        })
+        .unwrap();
+        assert_eq!(item.version(), 1);
+
+        let err = item
+            .update_if_version(0, |i| i.set_name("pen"))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            VersionConflict {
+                expected: 0,
+                actual: 1
+            }
+        );
+        assert_eq!(item.name(), "pencil");
+    }
+
+    #[test]
+    fn find_and_remove_respect_match_options() {
+        let mut root = Space::builder()
+            .name("Ház")
+            .push_space(
+                Space::builder()
+                    .name("Kamra")
+                    .push_item(Item::builder().name("Létra").build())
+                    .build(),
+            )
+            .build();
+        let normalized = MatchOptions::normalized();
+
+        assert!(root.find_space("kamra").is_none());
+        assert!(root.find_space_with("kamra", &normalized).is_some());
+        assert!(root.find_space_with("haz", &normalized).is_some());
+        assert!(root.find_item_with("LETRA", &normalized).is_some());
+        assert!(root.find_item_mut_with("letra", &normalized).is_some());
+
+        let (path, kamra) = root.locate_space_with("KAMRA", &normalized).unwrap();
+        assert_eq!(path, vec!["Ház"]);
+        assert_eq!(kamra.name(), "Kamra");
+
+        assert!(root.remove_item_with("letra", &normalized).is_some());
+        assert!(root.remove_space_with("KAMRA", &normalized).is_some());
+        assert!(root.spaces().is_empty());
+    }
+
+    #[test]
+    fn lookups_do_not_recurse_on_deep_trees() {
+        let levels = 20_000;
+        let mut root = Space::builder()
+            .name("bottom")
+            .push_item(Item::builder().name("coin").build())
+            .build();
+        for level in 1..=levels {
+            root = Space::builder()
+                .name(format!("level{level}"))
+                .push_space(root)
+                .build();
+        }
+
+        assert_eq!(root.depth(), levels);
+        let (parents, bottom) = root
+            .locate_space_with("bottom", &MatchOptions::exact())
+            .unwrap();
+        assert_eq!(parents.len(), levels);
+        assert_eq!(bottom.items().len(), 1);
+        assert!(root.find_item("coin").is_some());
+        assert!(root.remove_item("coin").is_some());
+        assert!(root.remove_space("bottom").is_some());
+        assert!(root.find_space("bottom").is_none());
+
+        // Dropping the tree recurses, so take it apart level by level.
+        while let Some(child) = root.spaces.pop() {
+            root = child;
+        }
+    }
+
+    #[test]
+    fn items_are_paged_after_filtering() {
+        let mut space = Space::builder().name("box").build();
+        for i in 0..10 {
+            space.add_item(Item::builder().name(format!("item{i}")).build());
+        }
+
+        let page = space.items_page(8, 5);
+        assert_eq!(page.entries.len(), 2);
+        assert_eq!(page.total, 10);
+        assert!(!page.has_more());
+
+        let even = space.items_page_filtered(1, 2, |item| {
+            item.name().ends_with(['0', '2', '4', '6', '8'])
+        });
+        let names: Vec<&str> = even.entries.iter().map(|i| i.name()).collect();
+        assert_eq!(names, vec!["item2", "item4"]);
+        assert_eq!(even.total, 5);
+        assert!(even.has_more());
+
+        space.add_item(Item::builder().name("Item1").build());
+        let sorted = space.items_page_sorted(0, 3, |_| true, &Collation::new());
+        let names: Vec<&str> = sorted.entries.iter().map(|i| i.name()).collect();
+        assert_eq!(names, vec!["item0", "Item1", "item1"]);
+        assert_eq!(sorted.total, 11);
+    }
 }
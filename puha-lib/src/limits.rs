@@ -0,0 +1,408 @@
+//! Guards against loading trees too large to handle comfortably.
+//!
+//! A whole tree is held in memory, so a data file grown far beyond what a
+//! home inventory needs, or written by a runaway script, can exhaust it.
+//! [`Limits`] turns that into an error explaining what to do instead.
+//!
+//! [`TreeLimits`] instead bound the shape of a tree as it is added to, and
+//! are kept in the root's [`Settings`](crate::Settings) so they hold for
+//! everyone changing the tree.
+
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::stats::TreeStats;
+use crate::{Item, Space};
+
+/// Deepest nesting of spaces below the root that can be saved. Each level
+/// nests the JSON two deep, and serde_json refuses to read JSON nested over
+/// 128 deep, so a deeper tree could be written but not loaded back.
+pub const MAX_DEPTH: usize = 60;
+
+/// Deepest nesting of groups in a view that can be saved, for the same
+/// reason: each group nests the JSON two deep too.
+pub const MAX_GROUP_DEPTH: usize = 60;
+
+/// Upper bounds checked when loading. Unset bounds are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// Largest data size in bytes; for the dir backend, of all files
+    /// together.
+    pub max_file_size: Option<u64>,
+    /// Largest number of spaces and items together.
+    pub max_nodes: Option<usize>,
+}
+
+/// A limit that a tree or data file is over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LimitError {
+    FileSize {
+        size: u64,
+        limit: u64,
+    },
+    Nodes {
+        nodes: usize,
+        limit: usize,
+    },
+    Depth {
+        depth: usize,
+        limit: usize,
+    },
+    GroupDepth {
+        view: String,
+        depth: usize,
+        limit: usize,
+    },
+    Children {
+        space: String,
+        limit: usize,
+    },
+    NameLength {
+        name: String,
+        limit: usize,
+    },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::FileSize { size, limit } => write!(
+                f,
+                "data is {size} bytes, over the limit of {limit} bytes; \
+                 split it up with the dir backend or raise max_file_size"
+            ),
+            LimitError::Nodes { nodes, limit } => write!(
+                f,
+                "tree has {nodes} spaces and items, over the limit of {limit}; \
+                 split it up into several files or raise max_nodes"
+            ),
+            LimitError::Depth { depth, limit } => write!(
+                f,
+                "spaces are nested {depth} levels deep, over the limit of {limit}; \
+                 move some of them further up"
+            ),
+            LimitError::GroupDepth { view, depth, limit } => write!(
+                f,
+                "groups of view '{view}' are nested {depth} levels deep, \
+                 over the limit of {limit}"
+            ),
+            LimitError::Children { space, limit } => write!(
+                f,
+                "'{space}' would hold more than {limit} items and spaces; \
+                 put some of them in a new space inside it"
+            ),
+            LimitError::NameLength { name, limit } => {
+                write!(f, "'{name}' is longer than {limit} characters")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// Check that `root` is not nested deeper than [`MAX_DEPTH`], nor the
+/// groups of its views deeper than [`MAX_GROUP_DEPTH`], before saving it.
+pub fn check_depth(root: &Space) -> Result<(), LimitError> {
+    let depth = root.depth();
+    if depth > MAX_DEPTH {
+        return Err(LimitError::Depth {
+            depth,
+            limit: MAX_DEPTH,
+        });
+    }
+    let views = &root.settings().views;
+    for view in views.names() {
+        let depth = views.get(view).map_or(0, |group| group.depth());
+        if depth > MAX_GROUP_DEPTH {
+            return Err(LimitError::GroupDepth {
+                view: view.to_string(),
+                depth,
+                limit: MAX_GROUP_DEPTH,
+            });
+        }
+    }
+    Ok(())
+}
+
+impl Limits {
+    /// Check the size of the data at `path`, a file or a directory, before
+    /// loading it. Missing data is not over any limit.
+    pub fn check_size(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(limit) = self.max_file_size else {
+            return Ok(());
+        };
+        if !path.exists() {
+            return Ok(());
+        }
+        let size = size_on_disk(path)?;
+        if size > limit {
+            return Err(LimitError::FileSize { size, limit }.into());
+        }
+        Ok(())
+    }
+
+    /// Check the number of nodes in a loaded tree.
+    pub fn check_tree(&self, root: &Space) -> Result<(), LimitError> {
+        let Some(limit) = self.max_nodes else {
+            return Ok(());
+        };
+        let nodes = TreeStats::of(root).nodes();
+        if nodes > limit {
+            return Err(LimitError::Nodes { nodes, limit });
+        }
+        Ok(())
+    }
+}
+
+/// Bytes taken by the file at `path`, or by all files below it.
+fn size_on_disk(path: &Path) -> std::io::Result<u64> {
+    let meta = std::fs::metadata(path)?;
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        total += size_on_disk(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+/// Bounds on the shape of a tree, checked when adding to it. Unset bounds
+/// are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct TreeLimits {
+    /// Deepest nesting of spaces below the root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    /// Most items and spaces together directly in one space.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_children: Option<usize>,
+    /// Longest name, in characters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_name_length: Option<usize>,
+}
+
+impl TreeLimits {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check a whole tree against the limits.
+    pub fn check(&self, root: &Space) -> Result<(), LimitError> {
+        self.check_below(root, 0)
+    }
+
+    /// Check `space`, `depth` levels below the root, and everything in it.
+    fn check_below(&self, space: &Space, depth: usize) -> Result<(), LimitError> {
+        let mut stack = vec![(depth, space)];
+        while let Some((depth, space)) = stack.pop() {
+            if let Some(limit) = self.max_depth.filter(|limit| depth > *limit) {
+                return Err(LimitError::Depth { depth, limit });
+            }
+            self.check_name(&space.name)?;
+            self.check_children(space, 0)?;
+            for item in &space.items {
+                self.check_name(&item.name)?;
+            }
+            stack.extend(space.spaces.iter().map(|child| (depth + 1, child)));
+        }
+        Ok(())
+    }
+
+    fn check_name(&self, name: &str) -> Result<(), LimitError> {
+        match self.max_name_length {
+            Some(limit) if name.chars().count() > limit => Err(LimitError::NameLength {
+                name: name.to_string(),
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that `space` can take `adding` more items or spaces.
+    fn check_children(&self, space: &Space, adding: usize) -> Result<(), LimitError> {
+        match self.max_children {
+            Some(limit) if space.items.len() + space.spaces.len() + adding > limit => {
+                Err(LimitError::Children {
+                    space: space.name.clone(),
+                    limit,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Space {
+    /// Like [`add_item`](Self::add_item), unless that would break `limits`.
+    pub fn try_add_item(&mut self, item: Item, limits: &TreeLimits) -> Result<(), LimitError> {
+        limits.check_children(self, 1)?;
+        limits.check_name(&item.name)?;
+        self.add_item(item);
+        Ok(())
+    }
+
+    /// Like [`add_space`](Self::add_space), unless `space` or anything in it
+    /// would break `limits`. `depth` is how many levels below the root this
+    /// space is.
+    pub fn try_add_space(
+        &mut self,
+        space: Space,
+        depth: usize,
+        limits: &TreeLimits,
+    ) -> Result<(), LimitError> {
+        limits.check_children(self, 1)?;
+        limits.check_below(&space, depth + 1)?;
+        self.add_space(space);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn limits_reject_large_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir(dir.join("child")).unwrap();
+        std::fs::write(dir.join("space.json"), [b' '; 60]).unwrap();
+        std::fs::write(dir.join("child/space.json"), [b' '; 60]).unwrap();
+
+        let limits = Limits {
+            max_file_size: Some(100),
+            max_nodes: Some(2),
+        };
+        assert!(limits.check_size(&dir.join("space.json")).is_ok());
+        let err = limits.check_size(dir).unwrap_err();
+        assert!(err.to_string().contains("120 bytes"), "{err}");
+        assert!(limits.check_size(&dir.join("missing")).is_ok());
+
+        let root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("a").build())
+            .push_item(Item::builder().name("b").build())
+            .build();
+        assert_eq!(
+            limits.check_tree(&root),
+            Err(LimitError::Nodes { nodes: 3, limit: 2 })
+        );
+        assert!(Limits::default().check_tree(&root).is_ok());
+    }
+
+    #[test]
+    fn trees_too_deep_to_load_back_are_not_saved() {
+        let nest = |levels: usize| {
+            let mut space = Space::builder().name("box").build();
+            for _ in 0..levels {
+                space = Space::builder().name("box").push_space(space).build();
+            }
+            space
+        };
+        assert!(check_depth(&nest(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            check_depth(&nest(MAX_DEPTH + 1)),
+            Err(LimitError::Depth {
+                depth: MAX_DEPTH + 1,
+                limit: MAX_DEPTH
+            })
+        );
+
+        // The deepest tree that can be saved also loads back.
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut root = nest(MAX_DEPTH);
+        let mut leaf = &mut root;
+        while !leaf.spaces.is_empty() {
+            leaf = &mut leaf.spaces[0];
+        }
+        leaf.add_item(
+            Item::builder()
+                .name("pen")
+                .metadata("colour", "blue")
+                .link(crate::LinkKind::PartOf, uuid::Uuid::nil())
+                .build(),
+        );
+        root.save_to_file(file.path()).unwrap();
+        assert_eq!(Space::from_file(file.path()).unwrap(), root);
+        assert!(nest(MAX_DEPTH + 1).save_to_file(file.path()).is_err());
+
+        // The same holds for the groups of a view.
+        let mut root = nest(0);
+        let views = &mut root.settings_mut().views;
+        views.create("deep");
+        views.add("deep", &["group"; MAX_GROUP_DEPTH], uuid::Uuid::nil());
+        root.save_to_file(file.path()).unwrap();
+        assert_eq!(Space::from_file(file.path()).unwrap(), root);
+        let views = &mut root.settings_mut().views;
+        views.add("deep", &["group"; MAX_GROUP_DEPTH + 1], uuid::Uuid::nil());
+        assert_eq!(
+            check_depth(&root),
+            Err(LimitError::GroupDepth {
+                view: "deep".into(),
+                depth: MAX_GROUP_DEPTH + 1,
+                limit: MAX_GROUP_DEPTH
+            })
+        );
+    }
+
+    #[test]
+    fn tree_limits_are_checked_when_adding() {
+        let limits = TreeLimits {
+            max_depth: Some(2),
+            max_children: Some(2),
+            max_name_length: Some(6),
+        };
+        let mut root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("lamp").build())
+            .build();
+        let item = |name: &str| Item::builder().name(name).build();
+
+        assert_eq!(
+            root.try_add_item(item("toaster"), &limits),
+            Err(LimitError::NameLength {
+                name: "toaster".into(),
+                limit: 6
+            })
+        );
+        let shed = Space::builder()
+            .name("shed")
+            .push_space(
+                Space::builder()
+                    .name("shelf")
+                    .push_space(Space::builder().name("box").build())
+                    .build(),
+            )
+            .build();
+        assert_eq!(
+            root.try_add_space(shed, 0, &limits),
+            Err(LimitError::Depth { depth: 3, limit: 2 })
+        );
+        assert!(root.try_add_item(item("kettle"), &limits).is_ok());
+        assert_eq!(
+            root.try_add_space(Space::builder().name("shed").build(), 0, &limits),
+            Err(LimitError::Children {
+                space: "home".into(),
+                limit: 2
+            })
+        );
+        assert_eq!(root.items().len(), 2);
+        assert!(limits.check(&root).is_ok());
+        assert!(
+            TreeLimits {
+                max_children: Some(1),
+                ..TreeLimits::default()
+            }
+            .check(&root)
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Typed links between items, such as a charger being an accessory for a
+//! camera kept in another box.
+//!
+//! Links go by item id, so they keep pointing at an item when it is renamed
+//! or moved. A link whose item was deleted is left dangling until removed
+//! with [`remove_dangling`].
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::search::{self, Target};
+use crate::{Item, Space};
+
+/// How an item relates to the item it links to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum LinkKind {
+    PartOf,
+    AccessoryFor,
+    ReplacementFor,
+}
+
+impl LinkKind {
+    pub const ALL: [LinkKind; 3] = [
+        LinkKind::PartOf,
+        LinkKind::AccessoryFor,
+        LinkKind::ReplacementFor,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkKind::PartOf => "part-of",
+            LinkKind::AccessoryFor => "accessory-for",
+            LinkKind::ReplacementFor => "replacement-for",
+        }
+    }
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Text that is not a [`LinkKind`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownLinkKind(pub String);
+
+impl fmt::Display for UnknownLinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown link kind '{}'; expected part-of, accessory-for or replacement-for",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownLinkKind {}
+
+impl FromStr for LinkKind {
+    type Err = UnknownLinkKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LinkKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| UnknownLinkKind(s.to_string()))
+    }
+}
+
+/// A link from an item to the item with id `target`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Link {
+    pub kind: LinkKind,
+    pub target: Uuid,
+}
+
+impl Item {
+    /// The item's links to other items.
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    /// The item's id, giving it a random one first if it has none, so it
+    /// can be linked to.
+    pub fn ensure_id(&mut self) -> Uuid {
+        match self.id {
+            Some(id) => id,
+            None => {
+                let id = Uuid::new_v4();
+                self.set_id(Some(id));
+                id
+            }
+        }
+    }
+
+    /// Link the item to the item with id `target`, returning whether the
+    /// link is new.
+    pub fn add_link(&mut self, kind: LinkKind, target: Uuid) -> bool {
+        let link = Link { kind, target };
+        if self.links.contains(&link) {
+            return false;
+        }
+        self.links.push(link);
+        self.version += 1;
+        true
+    }
+
+    /// Remove the item's links to `target`, returning how many there were.
+    pub fn remove_links_to(&mut self, target: Uuid) -> usize {
+        let before = self.links.len();
+        self.links.retain(|link| link.target != target);
+        let removed = before - self.links.len();
+        if removed > 0 {
+            self.version += 1;
+        }
+        removed
+    }
+}
+
+/// The path of the item with `id` under `root`.
+pub fn path_of(root: &Space, id: Uuid) -> Option<String> {
+    search::entries(root)
+        .into_iter()
+        .find_map(|entry| match entry.target {
+            Target::Item(item) if item.id == Some(id) => Some(entry.path_string()),
+            _ => None,
+        })
+}
+
+/// The links to the item with `id` from items under `root`, with the paths
+/// of the items they come from.
+pub fn incoming(root: &Space, id: Uuid) -> Vec<(String, LinkKind)> {
+    let mut incoming = Vec::new();
+    for entry in search::entries(root) {
+        if let Target::Item(item) = entry.target {
+            for link in item.links.iter().filter(|link| link.target == id) {
+                incoming.push((entry.path_string(), link.kind));
+            }
+        }
+    }
+    incoming
+}
+
+/// The links under `root` to items that are not under it, with the paths
+/// of the items they come from.
+pub fn dangling(root: &Space) -> Vec<(String, Link)> {
+    let ids = item_ids(root);
+    let mut dangling = Vec::new();
+    for entry in search::entries(root) {
+        if let Target::Item(item) = entry.target {
+            for link in item.links.iter().filter(|link| !ids.contains(&link.target)) {
+                dangling.push((entry.path_string(), *link));
+            }
+        }
+    }
+    dangling
+}
+
+/// Remove the links that [`dangling`] finds, returning how many there were.
+pub fn remove_dangling(root: &mut Space) -> usize {
+    let ids = item_ids(root);
+    prune(root, &ids)
+}
+
+fn prune(space: &mut Space, ids: &BTreeSet<Uuid>) -> usize {
+    let mut removed = 0;
+    for item in &mut space.items {
+        let before = item.links.len();
+        item.links.retain(|link| ids.contains(&link.target));
+        if item.links.len() < before {
+            removed += before - item.links.len();
+            item.version += 1;
+        }
+    }
+    for child in &mut space.spaces {
+        removed += prune(child, ids);
+    }
+    removed
+}
+
+fn item_ids(root: &Space) -> BTreeSet<Uuid> {
+    search::entries(root)
+        .into_iter()
+        .filter_map(|entry| match entry.target {
+            Target::Item(item) => item.id,
+            Target::Space(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_parse_and_serialize_kebab_case() {
+        for kind in LinkKind::ALL {
+            assert_eq!(kind.as_str().parse(), Ok(kind));
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                serde_json::Value::from(kind.as_str())
+            );
+        }
+        assert!("parent".parse::<LinkKind>().is_err());
+    }
+
+    #[test]
+    fn finds_incoming_and_dangling_links() {
+        let camera = Uuid::from_u128(1);
+        let gone = Uuid::from_u128(2);
+        let mut root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("camera").id(camera).build())
+            .push_space(
+                Space::builder()
+                    .name("box")
+                    .push_item(
+                        Item::builder()
+                            .name("charger")
+                            .link(LinkKind::AccessoryFor, camera)
+                            .link(LinkKind::PartOf, gone)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        assert_eq!(path_of(&root, camera).as_deref(), Some("home/camera"));
+        assert_eq!(
+            incoming(&root, camera),
+            [("home/box/charger".to_string(), LinkKind::AccessoryFor)]
+        );
+        assert_eq!(
+            dangling(&root),
+            [(
+                "home/box/charger".to_string(),
+                Link {
+                    kind: LinkKind::PartOf,
+                    target: gone
+                }
+            )]
+        );
+        assert_eq!(remove_dangling(&mut root), 1);
+        assert!(dangling(&root).is_empty());
+        assert_eq!(root.spaces()[0].items()[0].links().len(), 1);
+    }
+
+    #[test]
+    fn links_are_added_once_and_removed_by_target() {
+        let mut charger = Item::builder().name("charger").build();
+        let mut camera = Item::builder().name("camera").build();
+        let id = camera.ensure_id();
+        assert_eq!(camera.ensure_id(), id);
+        assert!(charger.add_link(LinkKind::AccessoryFor, id));
+        assert!(!charger.add_link(LinkKind::AccessoryFor, id));
+        assert!(charger.add_link(LinkKind::PartOf, id));
+        assert_eq!(charger.remove_links_to(id), 2);
+        assert!(charger.links().is_empty());
+    }
+}
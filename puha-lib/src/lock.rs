@@ -0,0 +1,155 @@
+//! Guarding spaces against accidental changes.
+//!
+//! A locked space and everything below it should not change. Rather than
+//! have every operation check for locks, [`check_changes`] compares a tree
+//! before and after a change and rejects changes under a locked space,
+//! whatever made them.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::Space;
+use crate::diff::TreeDiff;
+use crate::path::SEPARATOR;
+use crate::search::{self, Target};
+
+/// A change inside a locked space.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpaceLocked {
+    /// Path of the locked space.
+    pub space: String,
+    /// Path of what was changed inside it.
+    pub changed: String,
+}
+
+impl fmt::Display for SpaceLocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.space == self.changed {
+            write!(f, "space '{}' is locked", self.space)
+        } else {
+            write!(
+                f,
+                "'{}' is inside locked space '{}'",
+                self.changed, self.space
+            )
+        }
+    }
+}
+
+impl std::error::Error for SpaceLocked {}
+
+/// Check that going from `old` to `new` changes nothing inside a space that
+/// is locked in either tree. Locking and unlocking a space are not changes
+/// to it.
+pub fn check_changes(old: &Space, new: &Space) -> Result<(), SpaceLocked> {
+    let mut locked = locked_paths(old);
+    locked.extend(locked_paths(new));
+    if locked.is_empty() {
+        return Ok(());
+    }
+    let diff = TreeDiff::between(&unlocked(old), &unlocked(new));
+    for difference in diff.differences {
+        let inside = locked.iter().find(|space| {
+            difference
+                .path
+                .strip_prefix(space.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(SEPARATOR))
+        });
+        if let Some(space) = inside {
+            return Err(SpaceLocked {
+                space: space.clone(),
+                changed: difference.path,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Whether `root` or any space below it is locked, so that changes to it
+/// need checking with [`check_changes`].
+pub fn any_locked(root: &Space) -> bool {
+    root.locked || root.spaces.iter().any(any_locked)
+}
+
+fn locked_paths(root: &Space) -> BTreeSet<String> {
+    search::entries(root)
+        .into_iter()
+        .filter(|entry| matches!(entry.target, Target::Space(space) if space.locked))
+        .map(|entry| entry.path_string())
+        .collect()
+}
+
+/// A copy of `space` with every lock removed.
+fn unlocked(space: &Space) -> Space {
+    let mut space = space.clone();
+    unlock_all(&mut space);
+    space
+}
+
+fn unlock_all(space: &mut Space) {
+    space.locked = false;
+    for child in &mut space.spaces {
+        unlock_all(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    fn tree() -> Space {
+        Space::builder()
+            .name("home")
+            .push_space(
+                Space::builder()
+                    .name("archive 2019")
+                    .locked(true)
+                    .push_item(Item::builder().name("tax papers").build())
+                    .build(),
+            )
+            .push_space(Space::builder().name("garage").build())
+            .build()
+    }
+
+    #[test]
+    fn changes_inside_locked_spaces_are_rejected() {
+        let old = tree();
+        let mut new = old.clone();
+        new.find_space_mut("archive 2019")
+            .unwrap()
+            .add_item(Item::builder().name("receipts").build());
+        assert_eq!(
+            check_changes(&old, &new),
+            Err(SpaceLocked {
+                space: "home/archive 2019".into(),
+                changed: "home/archive 2019/receipts".into(),
+            })
+        );
+
+        let mut moved = old.clone();
+        let archive = moved.remove_space("archive 2019").unwrap();
+        moved.find_space_mut("garage").unwrap().add_space(archive);
+        assert!(check_changes(&old, &moved).is_err());
+    }
+
+    #[test]
+    fn other_changes_and_locking_itself_are_allowed() {
+        let old = tree();
+        assert!(any_locked(&old));
+        assert!(!any_locked(&unlocked(&old)));
+        let mut new = old.clone();
+        new.find_space_mut("garage")
+            .unwrap()
+            .add_item(Item::builder().name("drill").build());
+        assert_eq!(check_changes(&old, &new), Ok(()));
+
+        let mut unlocked = old.clone();
+        unlocked
+            .find_space_mut("archive 2019")
+            .unwrap()
+            .set_locked(false);
+        assert_eq!(check_changes(&old, &unlocked), Ok(()));
+        assert_eq!(check_changes(&unlocked, &old), Ok(()));
+    }
+}
@@ -0,0 +1,123 @@
+//! How names given by the user are compared with names in the tree.
+
+use std::borrow::Cow;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Options for comparing names in the `find_*` and `remove_*` APIs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchOptions {
+    /// Treat upper and lower case letters as equal.
+    pub ignore_case: bool,
+    /// Treat letters with and without accents as equal, so "Szekrény"
+    /// matches "Szekreny".
+    pub ignore_accents: bool,
+}
+
+impl MatchOptions {
+    /// Names must be identical.
+    pub fn exact() -> Self {
+        Self::default()
+    }
+
+    /// Names must be equal apart from case.
+    pub fn ignore_case() -> Self {
+        Self {
+            ignore_case: true,
+            ..Self::default()
+        }
+    }
+
+    /// Names must be equal apart from case, accents and Unicode
+    /// normalization form.
+    pub fn normalized() -> Self {
+        Self {
+            ignore_case: true,
+            ignore_accents: true,
+        }
+    }
+
+    /// Whether `a` and `b` are the same name under these options.
+    pub fn matches(&self, a: &str, b: &str) -> bool {
+        if *self == Self::exact() {
+            return a == b;
+        }
+        self.fold(a) == self.fold(b)
+    }
+
+    /// Reduce `name` to the form in which names are compared.
+    pub fn fold<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let mut folded = Cow::Borrowed(name);
+        if self.ignore_accents {
+            folded = Cow::Owned(
+                name.nfd()
+                    .filter(|c| !is_combining_mark(*c))
+                    .nfc()
+                    .collect(),
+            );
+        }
+        if self.ignore_case {
+            folded = Cow::Owned(folded.to_lowercase());
+        }
+        folded
+    }
+}
+
+/// How alike two names are, from 0 for nothing in common to 1 for the same
+/// name apart from case and accents, by how many letters have to be added,
+/// removed or changed to turn one into the other.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let folding = MatchOptions::normalized();
+    let a: Vec<char> = folding.fold(a).chars().collect();
+    let b: Vec<char> = folding.fold(b).chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    // Levenshtein distance, keeping one row of the table.
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, &x) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &y) in b.iter().enumerate() {
+            let changed = diagonal + usize::from(x != y);
+            diagonal = row[j + 1];
+            row[j + 1] = changed.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    1.0 - row[b.len()] as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_control_equality() {
+        assert!(MatchOptions::exact().matches("Kamra", "Kamra"));
+        assert!(!MatchOptions::exact().matches("Kamra", "kamra"));
+        assert!(MatchOptions::ignore_case().matches("Kamra", "kamra"));
+        assert!(!MatchOptions::ignore_case().matches("Fiók", "Fiok"));
+        assert!(MatchOptions::normalized().matches("Fiók", "fiok"));
+        assert!(MatchOptions::normalized().matches("Szekrény", "SZEKRENY"));
+        // Precomposed and decomposed spellings of the same name.
+        assert!(MatchOptions::normalized().matches("\u{e9}", "e\u{301}"));
+
+        let accents_only = MatchOptions {
+            ignore_accents: true,
+            ..MatchOptions::default()
+        };
+        assert!(accents_only.matches("Gyűjtő", "Gyujto"));
+        assert!(!accents_only.matches("Gyűjtő", "gyujto"));
+    }
+
+    #[test]
+    fn similarity_counts_edits() {
+        assert_eq!(similarity("Fiók", "fiok"), 1.0);
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("abc", ""), 0.0);
+        assert_eq!(similarity("cable", "table"), 0.8);
+        assert_eq!(similarity("kitten", "sitting"), 1.0 - 3.0 / 7.0);
+    }
+}
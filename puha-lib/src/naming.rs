@@ -0,0 +1,212 @@
+//! Rules for the names of items and spaces.
+//!
+//! A valid name is not empty, has no leading or trailing whitespace and does
+//! not contain the path separator or NUL, which separates names in
+//! [`IdStrategy::Deterministic`](crate::IdStrategy::Deterministic) ids. Trees written before these rules existed
+//! may break them; [`Space::invalid_names`] finds such names and
+//! [`Space::repair_names`] renames them.
+
+use std::fmt;
+
+use crate::path::SEPARATOR;
+use crate::{Item, Space};
+
+/// What is wrong with a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NameProblem {
+    Empty,
+    SurroundingWhitespace,
+    Separator,
+    Nul,
+}
+
+/// A name that does not follow the naming rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidName {
+    pub name: String,
+    pub problem: NameProblem,
+}
+
+impl fmt::Display for InvalidName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.problem {
+            NameProblem::Empty => write!(f, "name must not be empty"),
+            NameProblem::SurroundingWhitespace => write!(
+                f,
+                "name '{}' must not start or end with whitespace",
+                self.name
+            ),
+            NameProblem::Separator => {
+                write!(f, "name '{}' must not contain '{SEPARATOR}'", self.name)
+            }
+            NameProblem::Nul => write!(f, "name {:?} must not contain NUL", self.name),
+        }
+    }
+}
+
+impl std::error::Error for InvalidName {}
+
+/// Check `name` against the naming rules.
+pub fn validate(name: &str) -> Result<(), InvalidName> {
+    let problem = if name.is_empty() {
+        NameProblem::Empty
+    } else if name.trim() != name {
+        NameProblem::SurroundingWhitespace
+    } else if name.contains(SEPARATOR) {
+        NameProblem::Separator
+    } else if name.contains('\0') {
+        NameProblem::Nul
+    } else {
+        return Ok(());
+    };
+    Err(InvalidName {
+        name: name.to_string(),
+        problem,
+    })
+}
+
+/// The closest valid name to `name`: surrounding whitespace is trimmed,
+/// separators become `-`, NULs are dropped and an empty name becomes
+/// `unnamed`.
+pub fn repair(name: &str) -> String {
+    let repaired = name.replace('\0', "");
+    let repaired = repaired.trim().replace(SEPARATOR, "-");
+    if repaired.is_empty() {
+        "unnamed".to_string()
+    } else {
+        repaired
+    }
+}
+
+/// An invalid name found in a tree, with the path of its entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameIssue {
+    pub path: String,
+    pub error: InvalidName,
+}
+
+/// A rename done by [`Space::repair_names`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+}
+
+impl Space {
+    /// Every item and space at or below this space whose name breaks the
+    /// naming rules.
+    pub fn invalid_names(&self) -> Vec<NameIssue> {
+        let mut issues = Vec::new();
+        collect_issues(self, &mut Vec::new(), &mut issues);
+        issues
+    }
+
+    /// Rename every item and space at or below this space whose name breaks
+    /// the naming rules to its [`repair`]ed form.
+    pub fn repair_names(&mut self) -> Vec<Rename> {
+        let mut renames = Vec::new();
+        repair_space(self, &mut renames);
+        renames
+    }
+}
+
+fn collect_issues<'a>(space: &'a Space, parents: &mut Vec<&'a str>, issues: &mut Vec<NameIssue>) {
+    if let Err(error) = validate(&space.name) {
+        issues.push(NameIssue {
+            path: crate::path::join(parents, &space.name),
+            error,
+        });
+    }
+    parents.push(&space.name);
+    for item in &space.items {
+        if let Err(error) = validate(&item.name) {
+            issues.push(NameIssue {
+                path: crate::path::join(parents, &item.name),
+                error,
+            });
+        }
+    }
+    for child in &space.spaces {
+        collect_issues(child, parents, issues);
+    }
+    parents.pop();
+}
+
+fn repair_space(space: &mut Space, renames: &mut Vec<Rename>) {
+    if validate(&space.name).is_err() {
+        let to = repair(&space.name);
+        renames.push(Rename {
+            from: space.name.clone(),
+            to: to.clone(),
+        });
+        space.set_name(to);
+    }
+    for item in &mut space.items {
+        repair_item(item, renames);
+    }
+    for child in &mut space.spaces {
+        repair_space(child, renames);
+    }
+}
+
+fn repair_item(item: &mut Item, renames: &mut Vec<Rename>) {
+    if validate(&item.name).is_err() {
+        let to = repair(&item.name);
+        renames.push(Rename {
+            from: item.name.clone(),
+            to: to.clone(),
+        });
+        item.set_name(to);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_validated() {
+        assert!(validate("drill").is_ok());
+        assert!(validate("allen key").is_ok());
+        assert_eq!(validate("").unwrap_err().problem, NameProblem::Empty);
+        assert_eq!(
+            validate(" box").unwrap_err().problem,
+            NameProblem::SurroundingWhitespace
+        );
+        assert_eq!(
+            validate("A/B closet").unwrap_err().problem,
+            NameProblem::Separator
+        );
+        assert_eq!(validate("a\0b").unwrap_err().problem, NameProblem::Nul);
+        assert_eq!(repair("a\0b \0"), "ab");
+    }
+
+    #[test]
+    fn invalid_names_are_found_and_repaired() {
+        let mut root = Space::builder()
+            .name("home")
+            .push_space(
+                Space::builder()
+                    .name("A/B closet")
+                    .push_item(Item::builder().name(" pen ").build())
+                    .push_item(Item::builder().name("").build())
+                    .build(),
+            )
+            .build();
+
+        let paths: Vec<_> = root.invalid_names().into_iter().map(|i| i.path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "home/A/B closet",
+                "home/A/B closet/ pen ",
+                "home/A/B closet/"
+            ]
+        );
+
+        let renamed: Vec<_> = root.repair_names().into_iter().map(|r| r.to).collect();
+        assert_eq!(renamed, vec!["A-B closet", "pen", "unnamed"]);
+        assert!(root.invalid_names().is_empty());
+    }
+}
@@ -0,0 +1,343 @@
+//! Applying JSON Patch documents (RFC 6902), so other tools can describe
+//! precise edits to a tree in a standard format.
+//!
+//! A patch is applied to the tree's JSON form, as saved, and the result is
+//! read back as a tree. Either every operation applies and the result is a
+//! valid tree, or the tree is left as it was.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::limits::{self, LimitError};
+use crate::{Space, StrictError, strict};
+
+/// A JSON Patch: operations applied in order.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Patch(pub Vec<Operation>);
+
+/// One operation of a [`Patch`]. `path` and `from` are JSON Pointers
+/// (RFC 6901), such as `/spaces/0/items/-`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// Why a patch was not applied.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PatchError {
+    /// Operation `index` of the patch points at `path`, which does not
+    /// exist or cannot be written.
+    Path { index: usize, path: String },
+    /// The `test` at `index` found a different value at `path`.
+    Test { index: usize, path: String },
+    /// The patched document is not a tree.
+    Invalid(serde_json::Error),
+    /// The patched tree has invalid or duplicate names, or misplaced root
+    /// flags, that the tree did not have before.
+    Structure(StrictError),
+    /// The patched tree breaks a limit the tree kept.
+    Limit(LimitError),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Path { index, path } => {
+                write!(f, "operation {index}: nothing at '{path}'")
+            }
+            PatchError::Test { index, path } => {
+                write!(f, "operation {index}: test of '{path}' failed")
+            }
+            PatchError::Invalid(e) => write!(f, "the patched data is not a tree: {e}"),
+            PatchError::Structure(e) => e.fmt(f),
+            PatchError::Limit(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl Space {
+    /// Apply `patch` to the tree's JSON form, as saved. Either every
+    /// operation applies and the result is a valid tree, or the tree is
+    /// left unchanged. Problems the tree already had, such as duplicate
+    /// names or being over a limit, do not stop a patch.
+    ///
+    /// Only the root's version is bumped; versions below it change only if
+    /// the patch sets them.
+    pub fn apply_json_patch(&mut self, patch: &Patch) -> Result<(), PatchError> {
+        let mut doc = serde_json::to_value(&*self).map_err(PatchError::Invalid)?;
+        for (index, operation) in patch.0.iter().enumerate() {
+            operation.apply(&mut doc, index)?;
+        }
+        let mut patched: Space = serde_json::from_value(doc).map_err(PatchError::Invalid)?;
+
+        if let Err(mut error) = strict::check_structure(&patched) {
+            let before: HashSet<String> = match strict::check_structure(self) {
+                Ok(()) => HashSet::new(),
+                Err(before) => before.problems.into_iter().collect(),
+            };
+            error.problems.retain(|problem| !before.contains(problem));
+            if !error.problems.is_empty() {
+                return Err(PatchError::Structure(error));
+            }
+        }
+        let check = |root: &Space| {
+            limits::check_depth(root)?;
+            patched.settings.limits.check(root)
+        };
+        if let Err(error) = check(&patched)
+            && check(self).is_ok()
+        {
+            return Err(PatchError::Limit(error));
+        }
+
+        patched.version += 1;
+        *self = patched;
+        Ok(())
+    }
+}
+
+impl Operation {
+    /// Apply the operation, the `index`th of its patch, to `doc`.
+    fn apply(&self, doc: &mut Value, index: usize) -> Result<(), PatchError> {
+        let missing = |path: &str| PatchError::Path {
+            index,
+            path: path.to_string(),
+        };
+        let tokens = |pointer: &str| tokens(pointer).ok_or_else(|| missing(pointer));
+        match self {
+            Operation::Add { path, value } => {
+                add(doc, &tokens(path)?, value.clone()).ok_or_else(|| missing(path))
+            }
+            Operation::Remove { path } => remove(doc, &tokens(path)?)
+                .map(drop)
+                .ok_or_else(|| missing(path)),
+            Operation::Replace { path, value } => {
+                let target = find(doc, &tokens(path)?).ok_or_else(|| missing(path))?;
+                *target = value.clone();
+                Ok(())
+            }
+            Operation::Move { from, path } => {
+                let (from_tokens, to) = (tokens(from)?, tokens(path)?);
+                // Nothing can be moved into itself.
+                if to.len() > from_tokens.len() && to.starts_with(&from_tokens) {
+                    return Err(missing(path));
+                }
+                let value = remove(doc, &from_tokens).ok_or_else(|| missing(from))?;
+                add(doc, &to, value).ok_or_else(|| missing(path))
+            }
+            Operation::Copy { from, path } => {
+                let value = find(doc, &tokens(from)?)
+                    .ok_or_else(|| missing(from))?
+                    .clone();
+                add(doc, &tokens(path)?, value).ok_or_else(|| missing(path))
+            }
+            Operation::Test { path, value } => {
+                let actual = find(doc, &tokens(path)?).ok_or_else(|| missing(path))?;
+                if actual == value {
+                    Ok(())
+                } else {
+                    Err(PatchError::Test {
+                        index,
+                        path: path.clone(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// The reference tokens of a JSON Pointer, unescaped.
+fn tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let rest = pointer.strip_prefix('/')?;
+    Some(
+        rest.split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+/// The array index `token` stands for in an array of `len` elements. With
+/// `end`, `-` and `len` itself stand for the end of the array.
+fn index(token: &str, len: usize, end: bool) -> Option<usize> {
+    if end && token == "-" {
+        return Some(len);
+    }
+    if token.is_empty()
+        || (token.len() > 1 && token.starts_with('0'))
+        || !token.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let index = token.parse().ok()?;
+    (index < len || (end && index == len)).then_some(index)
+}
+
+/// The value `tokens` point at.
+fn find<'a>(doc: &'a mut Value, tokens: &[String]) -> Option<&'a mut Value> {
+    tokens.iter().try_fold(doc, |value, token| match value {
+        Value::Object(map) => map.get_mut(token),
+        Value::Array(list) => {
+            let index = index(token, list.len(), false)?;
+            list.get_mut(index)
+        }
+        _ => None,
+    })
+}
+
+/// Put `value` where `tokens` point, replacing an object member or
+/// inserting into an array.
+fn add(doc: &mut Value, tokens: &[String], value: Value) -> Option<()> {
+    let Some((last, parents)) = tokens.split_last() else {
+        *doc = value;
+        return Some(());
+    };
+    match find(doc, parents)? {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+        }
+        Value::Array(list) => {
+            let index = index(last, list.len(), true)?;
+            list.insert(index, value);
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Take out the value `tokens` point at. The whole document cannot be
+/// removed.
+fn remove(doc: &mut Value, tokens: &[String]) -> Option<Value> {
+    let (last, parents) = tokens.split_last()?;
+    match find(doc, parents)? {
+        Value::Object(map) => map.remove(last),
+        Value::Array(list) => {
+            let index = index(last, list.len(), false)?;
+            Some(list.remove(index))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Item, TreeLimits};
+
+    fn home() -> Space {
+        Space::builder()
+            .name("home")
+            .root(true)
+            .push_item(Item::builder().name("lamp").build())
+            .push_space(
+                Space::builder()
+                    .name("shed")
+                    .push_item(Item::builder().name("drill").build())
+                    .build(),
+            )
+            .build()
+    }
+
+    fn patch(json: &str) -> Patch {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn applies_every_kind_of_operation() {
+        let mut root = home();
+        root.apply_json_patch(&patch(
+            r#"[
+                {"op": "test", "path": "/spaces/0/name", "value": "shed"},
+                {"op": "add", "path": "/items/-", "value": {"name": "rug", "description": ""}},
+                {"op": "replace", "path": "/items/0/description", "value": "by the bed"},
+                {"op": "move", "from": "/spaces/0/items/0", "path": "/items/0"},
+                {"op": "copy", "from": "/items/1", "path": "/spaces/0/items/-"},
+                {"op": "remove", "path": "/items/2"}
+            ]"#,
+        ))
+        .unwrap();
+        let names = |space: &Space| -> Vec<String> {
+            space.items().iter().map(|i| i.name().to_string()).collect()
+        };
+        assert_eq!(names(&root), ["drill", "lamp"]);
+        assert_eq!(root.items()[1].description(), "by the bed");
+        assert_eq!(names(&root.spaces()[0]), ["lamp"]);
+        assert_eq!(root.version(), 1);
+    }
+
+    #[test]
+    fn failing_patches_change_nothing() {
+        let mut root = home();
+        let failures = [
+            r#"[{"op": "remove", "path": "/items/0"}, {"op": "remove", "path": "/items/0"}]"#,
+            r#"[{"op": "remove", "path": "/items/0"}, {"op": "test", "path": "/name", "value": "x"}]"#,
+            r#"[{"op": "move", "from": "/spaces/0", "path": "/spaces/0/spaces/0"}]"#,
+            r#"[{"op": "remove", "path": "/name"}]"#,
+            r#"[{"op": "replace", "path": "/spaces/0/name", "value": "bad/name"}]"#,
+            r#"[{"op": "copy", "from": "/items/0", "path": "/items/-"}]"#,
+            r#"[{"op": "replace", "path": "/items/01", "value": {}}]"#,
+        ];
+        for failure in failures {
+            assert!(root.apply_json_patch(&patch(failure)).is_err(), "{failure}");
+            assert_eq!(root, home());
+        }
+    }
+
+    #[test]
+    fn reports_failures_by_operation() {
+        let mut root = home();
+        let error = root
+            .apply_json_patch(&patch(
+                r#"[{"op": "test", "path": "/name", "value": "home"},
+                    {"op": "test", "path": "/name", "value": "shed"}]"#,
+            ))
+            .unwrap_err();
+        assert!(matches!(error, PatchError::Test { index: 1, .. }));
+        let error = root
+            .apply_json_patch(&patch(r#"[{"op": "remove", "path": "/items/7"}]"#))
+            .unwrap_err();
+        assert!(matches!(error, PatchError::Path { index: 0, .. }));
+    }
+
+    #[test]
+    fn keeps_to_the_tree_limits() {
+        let mut root = home();
+        root.settings_mut().limits = TreeLimits {
+            max_children: Some(2),
+            ..TreeLimits::default()
+        };
+        let add_item = patch(
+            r#"[{"op": "add", "path": "/items/-", "value": {"name": "rug", "description": ""}}]"#,
+        );
+        assert!(matches!(
+            root.apply_json_patch(&add_item),
+            Err(PatchError::Limit(_))
+        ));
+        let unlimited = patch(r#"[{"op": "remove", "path": "/settings"}]"#);
+        root.apply_json_patch(&unlimited).unwrap();
+        root.apply_json_patch(&add_item).unwrap();
+    }
+
+    #[test]
+    fn unescapes_pointers() {
+        let mut doc = serde_json::json!({"a/b": {"~": 1}});
+        let tokens = tokens("/a~1b/~0").unwrap();
+        assert_eq!(find(&mut doc, &tokens), Some(&mut Value::from(1)));
+        assert_eq!(super::tokens("no-slash"), None);
+    }
+}
@@ -0,0 +1,428 @@
+//! Paths of items and spaces within a tree.
+//!
+//! A path lists the names of the spaces leading to an entity, starting at the
+//! root, followed by the entity's own name, separated by `/`.
+//!
+//! Names written before the [naming rules](crate::naming) existed may contain
+//! `/` themselves. In a path such a segment is either quoted,
+//! `home/"A/B closet"/box`, or has its separators escaped with a backslash,
+//! `home/A\/B closet/box`. A backslash also escapes `"` and itself.
+//!
+//! [`resolve`] also accepts paths relative to a space: `./drawer/2` names a
+//! space below it and `../shelf` one next to it.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::{Item, MatchOptions, Space};
+
+/// Separator between the segments of a path.
+pub const SEPARATOR: char = '/';
+
+/// Join the names of the spaces in `parents` and `name` into a path.
+pub fn join(parents: &[&str], name: &str) -> String {
+    let mut out = String::new();
+    write_joined(&mut out, parents, name).expect("writing to a String");
+    out
+}
+
+/// Like [`join`], writing the path to `out` instead of a new string.
+pub fn write_joined(out: &mut impl fmt::Write, parents: &[&str], name: &str) -> fmt::Result {
+    for segment in parents {
+        out.write_str(segment)?;
+        out.write_char(SEPARATOR)?;
+    }
+    out.write_str(name)
+}
+
+/// Returned when a path cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathError {
+    pub message: String,
+    /// Byte offset into the path where the problem was found.
+    pub position: usize,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Split a path into its segments, resolving quotes and escapes.
+pub fn parse(text: &str) -> Result<Vec<String>, PathError> {
+    Ok(split(text)?.into_iter().map(|s| s.name).collect())
+}
+
+/// Split a path into its segments like [`parse`], resolving a path that
+/// starts with `.` or `..` against `base`, the path of the space it is
+/// relative to. `.` stands for the space itself and `..` for its parent,
+/// anywhere in the path; quote them, `".."`, to name a space called that.
+pub fn resolve<S: AsRef<str>>(text: &str, base: &[S]) -> Result<Vec<String>, PathError> {
+    let segments = split(text)?;
+    let relative = segments.first().is_some_and(Segment::is_dot);
+    let mut out: Vec<String> = if relative {
+        base.iter().map(|s| s.as_ref().to_string()).collect()
+    } else {
+        Vec::new()
+    };
+    for segment in segments {
+        if !segment.literal && segment.name == "." {
+            continue;
+        }
+        if !segment.literal && segment.name == ".." {
+            // The root has no parent.
+            if out.len() < 2 {
+                return Err(PathError {
+                    message: "path leads above the root".to_string(),
+                    position: segment.start,
+                });
+            }
+            out.pop();
+            continue;
+        }
+        out.push(segment.name);
+    }
+    Ok(out)
+}
+
+/// A segment of a path as written.
+struct Segment {
+    name: String,
+    /// Byte offset of the segment in the path.
+    start: usize,
+    /// Whether the segment used quotes or escapes.
+    literal: bool,
+}
+
+impl Segment {
+    fn is_dot(&self) -> bool {
+        !self.literal && (self.name == "." || self.name == "..")
+    }
+}
+
+fn split(text: &str) -> Result<Vec<Segment>, PathError> {
+    let error = |message: &str, position| PathError {
+        message: message.to_string(),
+        position,
+    };
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    // Whether the current segment used quotes, which makes `""` a valid
+    // (empty) segment.
+    let mut had_quotes = false;
+    let mut literal = false;
+    let mut quote_start = None;
+    let mut segment_start = 0;
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) => {
+                    literal = true;
+                    current.push(escaped);
+                }
+                None => return Err(error("dangling escape", i)),
+            },
+            '"' => {
+                had_quotes = true;
+                literal = true;
+                quote_start = match quote_start {
+                    Some(_) => None,
+                    None => Some(i),
+                };
+            }
+            SEPARATOR if quote_start.is_none() => {
+                if current.is_empty() && !had_quotes {
+                    return Err(error("empty segment", segment_start));
+                }
+                segments.push(Segment {
+                    name: std::mem::take(&mut current),
+                    start: segment_start,
+                    literal,
+                });
+                had_quotes = false;
+                literal = false;
+                segment_start = i + c.len_utf8();
+            }
+            c => current.push(c),
+        }
+    }
+    if let Some(start) = quote_start {
+        return Err(error("unterminated quote", start));
+    }
+    if current.is_empty() && !had_quotes {
+        return Err(error("empty segment", segment_start));
+    }
+    segments.push(Segment {
+        name: current,
+        start: segment_start,
+        literal,
+    });
+    Ok(segments)
+}
+
+/// `name` as a path segment, quoted if it would not parse back as itself.
+pub fn quote(name: &str) -> Cow<'_, str> {
+    if !needs_quotes(name) {
+        return Cow::Borrowed(name);
+    }
+    let mut out = String::with_capacity(name.len() + 2);
+    write_quoted(&mut out, name).expect("writing to a String");
+    Cow::Owned(out)
+}
+
+/// Names that are empty, contain special characters or would be taken for
+/// dot segments by [`resolve`].
+fn needs_quotes(name: &str) -> bool {
+    matches!(name, "" | "." | "..") || name.contains([SEPARATOR, '"', '\\'])
+}
+
+/// Like [`quote`], writing the segment to `out`.
+fn write_quoted(out: &mut impl fmt::Write, name: &str) -> fmt::Result {
+    if !needs_quotes(name) {
+        return out.write_str(name);
+    }
+    out.write_char('"')?;
+    for c in name.chars() {
+        if c == '"' || c == '\\' {
+            out.write_char('\\')?;
+        }
+        out.write_char(c)?;
+    }
+    out.write_char('"')
+}
+
+/// Join `segments` into a path that [`parse`] splits back into them.
+pub fn format(segments: &[&str]) -> String {
+    let mut out = String::new();
+    write_formatted(&mut out, segments).expect("writing to a String");
+    out
+}
+
+/// Like [`format`], writing the path to `out` instead of a new string.
+pub fn write_formatted(out: &mut impl fmt::Write, segments: &[&str]) -> fmt::Result {
+    for (i, segment) in segments.iter().enumerate() {
+        if i > 0 {
+            out.write_char(SEPARATOR)?;
+        }
+        write_quoted(out, segment)?;
+    }
+    Ok(())
+}
+
+impl Space {
+    /// The space at `path`, whose first segment names this space, together
+    /// with the names of the spaces leading to it.
+    pub fn locate_at<'a, S: AsRef<str>>(
+        &'a self,
+        path: &[S],
+        options: &MatchOptions,
+    ) -> Option<(Vec<&'a str>, &'a Space)> {
+        let (first, rest) = path.split_first()?;
+        if !options.matches(&self.name, first.as_ref()) {
+            return None;
+        }
+        let mut parents = Vec::new();
+        let mut space = self;
+        for segment in rest {
+            parents.push(space.name.as_str());
+            space = space
+                .spaces
+                .iter()
+                .find(|s| options.matches(&s.name, segment.as_ref()))?;
+        }
+        Some((parents, space))
+    }
+
+    /// The space at `path`, whose first segment names this space.
+    pub fn space_at<S: AsRef<str>>(&self, path: &[S], options: &MatchOptions) -> Option<&Space> {
+        self.locate_at(path, options).map(|(_, space)| space)
+    }
+
+    /// Like [`space_at`](Self::space_at), returning a mutable reference.
+    pub fn space_at_mut<S: AsRef<str>>(
+        &mut self,
+        path: &[S],
+        options: &MatchOptions,
+    ) -> Option<&mut Space> {
+        let (first, rest) = path.split_first()?;
+        if !options.matches(&self.name, first.as_ref()) {
+            return None;
+        }
+        let mut space = self;
+        for segment in rest {
+            space = space
+                .spaces
+                .iter_mut()
+                .find(|s| options.matches(&s.name, segment.as_ref()))?;
+        }
+        Some(space)
+    }
+
+    /// The item at `path`: the last segment names an item directly in the
+    /// space named by the others.
+    pub fn item_at_mut<S: AsRef<str>>(
+        &mut self,
+        path: &[S],
+        options: &MatchOptions,
+    ) -> Option<&mut Item> {
+        let (name, parents) = path.split_last()?;
+        self.space_at_mut(parents, options)?
+            .items
+            .iter_mut()
+            .find(|i| options.matches(&i.name, name.as_ref()))
+    }
+
+    /// Remove the space at `path` from its parent and return it.
+    pub fn remove_space_at<S: AsRef<str>>(
+        &mut self,
+        path: &[S],
+        options: &MatchOptions,
+    ) -> Option<Space> {
+        let (name, parents) = path.split_last()?;
+        let parent = self.space_at_mut(parents, options)?;
+        let pos = parent
+            .spaces
+            .iter()
+            .position(|s| options.matches(&s.name, name.as_ref()))?;
+        parent.version += 1;
+        Some(parent.spaces.remove(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> Vec<String> {
+        parse(text).unwrap()
+    }
+
+    #[test]
+    fn join_separates_segments() {
+        assert_eq!(join(&["home", "kitchen"], "fork"), "home/kitchen/fork");
+        assert_eq!(join(&[], "home"), "home");
+    }
+
+    #[test]
+    fn writes_append_to_a_buffer() {
+        let mut out = String::from("> ");
+        write_joined(&mut out, &["home", "A/B"], "fork").unwrap();
+        out.push(' ');
+        write_formatted(&mut out, &["home", "A/B", "fork"]).unwrap();
+        assert_eq!(out, r#"> home/A/B/fork home/"A/B"/fork"#);
+    }
+
+    #[test]
+    fn parse_splits_plain_paths() {
+        assert_eq!(parsed("home"), vec!["home"]);
+        assert_eq!(parsed("home/kitchen/fork"), vec!["home", "kitchen", "fork"]);
+        assert_eq!(parsed("home/allen key"), vec!["home", "allen key"]);
+        assert_eq!(parsed("ház/fiók"), vec!["ház", "fiók"]);
+    }
+
+    #[test]
+    fn parse_resolves_quotes_and_escapes() {
+        assert_eq!(
+            parsed(r#"root/"A/B closet"/box"#),
+            vec!["root", "A/B closet", "box"]
+        );
+        assert_eq!(
+            parsed(r"root/A\/B closet/box"),
+            vec!["root", "A/B closet", "box"]
+        );
+        assert_eq!(parsed(r#"A"/"B"#), vec!["A/B"]);
+        assert_eq!(parsed(r#""say \"hi\"""#), vec![r#"say "hi""#]);
+        assert_eq!(parsed(r"back\\slash/x"), vec![r"back\slash", "x"]);
+        assert_eq!(parsed(r#"home/"""#), vec!["home", ""]);
+        assert_eq!(parsed(r#"" padded ""#), vec![" padded "]);
+    }
+
+    #[test]
+    fn parse_reports_errors_with_position() {
+        let err = |text| parse(text).unwrap_err();
+        assert_eq!(err("").message, "empty segment");
+        assert_eq!(err("home//box").position, 5);
+        assert_eq!(err("home/").position, 5);
+        assert_eq!(err("/home").position, 0);
+        assert_eq!(err(r#"home/"A/B"#).message, "unterminated quote");
+        assert_eq!(err(r#"home/"A/B"#).position, 5);
+        assert_eq!(err(r"home\").message, "dangling escape");
+    }
+
+    #[test]
+    fn resolve_follows_dot_segments() {
+        let base = ["home", "kitchen"];
+        let resolved = |text| resolve(text, &base).unwrap();
+        assert_eq!(
+            resolved("./drawer/2"),
+            vec!["home", "kitchen", "drawer", "2"]
+        );
+        assert_eq!(resolved("."), vec!["home", "kitchen"]);
+        assert_eq!(resolved("../garage"), vec!["home", "garage"]);
+        assert_eq!(resolved(".."), vec!["home"]);
+        assert_eq!(resolved("home/garage/../attic"), vec!["home", "attic"]);
+        assert_eq!(resolved("drawer"), vec!["drawer"]);
+        assert_eq!(resolved(r#""..""#), vec![".."]);
+        assert_eq!(resolved(r#"./"..""#), vec!["home", "kitchen", ".."]);
+
+        let err = resolve("../..", &base).unwrap_err();
+        assert_eq!(err.message, "path leads above the root");
+        assert_eq!(err.position, 3);
+        assert!(resolve("home/..", &base).is_err());
+    }
+
+    #[test]
+    fn format_round_trips() {
+        for segments in [
+            vec!["home", "kitchen"],
+            vec!["home", "A/B closet", "box"],
+            vec![r#"say "hi""#, r"back\slash", ""],
+        ] {
+            assert_eq!(parsed(&format(&segments)), segments);
+        }
+        assert_eq!(format(&["home", "A/B"]), r#"home/"A/B""#);
+
+        let dots = ["home", ".", ".."];
+        assert_eq!(format(&dots), r#"home/"."/"..""#);
+        assert_eq!(resolve(&format(&dots), &["home"]).unwrap(), dots);
+    }
+
+    #[test]
+    fn lookups_follow_paths() {
+        let mut root = Space::builder()
+            .name("home")
+            .push_space(
+                Space::builder()
+                    .name("A/B closet")
+                    .push_space(
+                        Space::builder()
+                            .name("box")
+                            .push_item(Item::builder().name("pen").build())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let path = parsed(r#"home/"A/B closet"/box"#);
+        let exact = MatchOptions::exact();
+
+        let (parents, found) = root.locate_at(&path, &exact).unwrap();
+        assert_eq!(parents, vec!["home", "A/B closet"]);
+        assert_eq!(found.name(), "box");
+        assert!(root.space_at(&["home", "box"], &exact).is_none());
+        assert!(
+            root.space_at(&["HOME"], &MatchOptions::ignore_case())
+                .is_some()
+        );
+
+        let pen = parsed(r#"home/"A/B closet"/box/pen"#);
+        assert!(root.item_at_mut(&pen, &exact).is_some());
+        let removed = root.remove_space_at(&path, &exact).unwrap();
+        assert_eq!(removed.name(), "box");
+        assert!(root.space_at(&path, &exact).is_none());
+    }
+}
@@ -0,0 +1,13 @@
+//! The types most programs using the library need, for
+//! `use puha_lib::prelude::*;`.
+
+pub use crate::matching::MatchOptions;
+pub use crate::patch::Patch;
+pub use crate::query::Query;
+pub use crate::search::SearchHit;
+pub use crate::settings::Settings;
+pub use crate::shared::SharedSpace;
+pub use crate::stock::Timestamp;
+pub use crate::storage::{Backend, SaveOptions, Storage};
+pub use crate::value::{Date, Price};
+pub use crate::{Item, ItemBuilder, Space, SpaceBuilder};
@@ -0,0 +1,307 @@
+//! A small query language for search and filtering.
+//!
+//! A query is a list of whitespace-separated terms. Bare words and quoted
+//! phrases are free text; terms of the form `field:value` only match that
+//! field:
+//!
+//! - `name:drill` matches item and space names,
+//! - `desc:"allen key"` (or `description:`) matches item descriptions,
+//! - `meta.serial:ABC123` matches the item's `serial` metadata value,
+//! - `tag:fragile` matches items tagged `fragile`.
+//!
+//! Other words with a colon, such as `12:30`, are free text.
+//!
+//! Tags must match exactly, as adding and removing them does; all other
+//! matching is case-insensitive substring matching.
+//! Every term must match.
+
+use std::fmt;
+
+use crate::{Item, Space};
+
+/// The part of an entity a term applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Field {
+    /// Free text.
+    Any,
+    Name,
+    Description,
+    /// A metadata value, by key.
+    Meta(String),
+    /// One of the item's tags, matched exactly.
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    pub field: Field,
+    pub value: String,
+}
+
+/// Returned when a query string cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub message: String,
+    /// Byte offset into the query where the problem was found.
+    pub position: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// A parsed query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let mut terms = Vec::new();
+        let mut rest = input;
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            let start = input.len() - rest.len();
+            let (word, after) = read_value(rest, start)?;
+            rest = after;
+
+            // `field:value`, where the value may itself be quoted. Words
+            // like `12:30`, whose prefix is no field, are free text.
+            let field = word
+                .split_once(':')
+                .filter(|_| !word.quoted)
+                .and_then(|(field, value)| Some((parse_field(field)?, value)));
+            let term = match field {
+                Some((field, value)) => {
+                    let value = if value.is_empty() && rest.starts_with('"') {
+                        let (quoted, after) = read_value(rest, input.len() - rest.len())?;
+                        rest = after;
+                        quoted.text
+                    } else {
+                        value.to_string()
+                    };
+                    if value.is_empty() {
+                        return Err(QueryError {
+                            message: "missing value after ':'".into(),
+                            position: start,
+                        });
+                    }
+                    Term { field, value }
+                }
+                None => Term {
+                    field: Field::Any,
+                    value: word.text,
+                },
+            };
+            terms.push(term);
+        }
+        Ok(Self { terms })
+    }
+
+    /// The free-text terms joined into a single phrase, if there are any.
+    pub fn text(&self) -> Option<String> {
+        let words: Vec<&str> = self
+            .terms
+            .iter()
+            .filter(|t| t.field == Field::Any)
+            .map(|t| t.value.as_str())
+            .collect();
+        (!words.is_empty()).then(|| words.join(" "))
+    }
+
+    /// Terms restricted to a specific field.
+    pub fn field_terms(&self) -> impl Iterator<Item = &Term> {
+        self.terms.iter().filter(|t| t.field != Field::Any)
+    }
+
+    /// Whether `item` satisfies every field term. Free text is ignored.
+    pub fn matches_item_fields(&self, item: &Item) -> bool {
+        self.field_terms().all(|term| {
+            let text = match &term.field {
+                Field::Any => return true,
+                Field::Name => item.name(),
+                Field::Description => item.description(),
+                Field::Meta(key) => match item.metadata().get(key) {
+                    Some(value) => value,
+                    None => return false,
+                },
+                Field::Tag => return item.tags().contains(&term.value),
+            };
+            contains(text, &term.value)
+        })
+    }
+
+    /// Whether `space` satisfies every field term. Spaces have no
+    /// description, metadata or tags, so such terms never match.
+    pub fn matches_space_fields(&self, space: &Space) -> bool {
+        self.field_terms().all(|term| match &term.field {
+            Field::Any => true,
+            Field::Name => contains(space.name(), &term.value),
+            Field::Description | Field::Meta(_) | Field::Tag => false,
+        })
+    }
+
+    /// Whether `item` satisfies every term, free text matching its name or
+    /// description.
+    pub fn matches_item(&self, item: &Item) -> bool {
+        let text = self.text();
+        self.matches_item_fields(item)
+            && text.is_none_or(|t| contains(item.name(), &t) || contains(item.description(), &t))
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+struct Word {
+    text: String,
+    quoted: bool,
+}
+
+impl Word {
+    fn split_once(&self, c: char) -> Option<(&str, &str)> {
+        self.text.split_once(c)
+    }
+}
+
+/// Read a bare word or a quoted phrase from the start of `input`, which
+/// begins at byte `offset` of the whole query.
+fn read_value(input: &str, offset: usize) -> Result<(Word, &str), QueryError> {
+    if let Some(quoted) = input.strip_prefix('"') {
+        let mut text = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    let word = Word { text, quoted: true };
+                    return Ok((word, &quoted[i + 1..]));
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                c => text.push(c),
+            }
+        }
+        return Err(QueryError {
+            message: "unterminated quote".into(),
+            position: offset,
+        });
+    }
+    let mut end = input.len();
+    for (i, c) in input.char_indices() {
+        if c.is_whitespace() {
+            end = i;
+            break;
+        }
+        // Leave a quoted field value to be read separately.
+        if c == ':' && input[i + 1..].starts_with('"') {
+            end = i + 1;
+            break;
+        }
+    }
+    let word = Word {
+        text: input[..end].to_string(),
+        quoted: false,
+    };
+    Ok((word, &input[end..]))
+}
+
+fn parse_field(name: &str) -> Option<Field> {
+    match name {
+        "name" => Some(Field::Name),
+        "desc" | "description" => Some(Field::Description),
+        "tag" => Some(Field::Tag),
+        _ => {
+            let key = name.strip_prefix("meta.")?;
+            (!key.is_empty()).then(|| Field::Meta(key.to_string()))
+        }
+    }
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fields_and_quoted_phrases() {
+        let query = Query::parse(r#"wrench desc:"allen key" meta.serial:ABC123 "a:b""#).unwrap();
+        assert_eq!(
+            query.terms,
+            vec![
+                Term {
+                    field: Field::Any,
+                    value: "wrench".into()
+                },
+                Term {
+                    field: Field::Description,
+                    value: "allen key".into()
+                },
+                Term {
+                    field: Field::Meta("serial".into()),
+                    value: "ABC123".into()
+                },
+                Term {
+                    field: Field::Any,
+                    value: "a:b".into()
+                },
+            ]
+        );
+        assert_eq!(query.text().as_deref(), Some("wrench a:b"));
+
+        let query = Query::parse("alarm 12:30 colour:red").unwrap();
+        assert_eq!(query.text().as_deref(), Some("alarm 12:30 colour:red"));
+        assert_eq!(query.field_terms().count(), 0);
+    }
+
+    #[test]
+    fn reports_errors_with_position() {
+        let err = Query::parse(r#"drill desc:"open"#).unwrap_err();
+        assert_eq!(err.message, "unterminated quote");
+        assert_eq!(err.position, 11);
+
+        let err = Query::parse("name:").unwrap_err();
+        assert_eq!(err.message, "missing value after ':'");
+    }
+
+    #[test]
+    fn matches_item_fields() {
+        let item = Item::builder()
+            .name("Hex key set")
+            .description("Allen keys, metric")
+            .metadata("serial", "abc123")
+            .tag("tools")
+            .build();
+
+        assert!(
+            Query::parse(r#"desc:"allen key""#)
+                .unwrap()
+                .matches_item(&item)
+        );
+        assert!(Query::parse("meta.serial:ABC").unwrap().matches_item(&item));
+        assert!(Query::parse("metric name:hex").unwrap().matches_item(&item));
+        assert!(!Query::parse("meta.colour:red").unwrap().matches_item(&item));
+        assert!(!Query::parse("name:allen").unwrap().matches_item(&item));
+        assert!(Query::parse("tag:tools").unwrap().matches_item(&item));
+        assert!(!Query::parse("tag:Tools").unwrap().matches_item(&item));
+        assert!(!Query::parse("tag:tool").unwrap().matches_item(&item));
+    }
+}
@@ -0,0 +1,166 @@
+//! A one-line shorthand for adding an item.
+//!
+//! `3x AA batteries -> garage/shelf #consumables` reads as a quantity, a
+//! name, the path of the space to add it to and tags. Only the name is
+//! needed; what is left out is for the caller to fill in.
+
+use std::fmt;
+
+/// An item described by a quick-add line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuickAdd {
+    pub quantity: Option<u32>,
+    /// Empty when the line gives no name.
+    pub name: String,
+    /// The path after `->`, unresolved.
+    pub destination: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// An error in a quick-add line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickAddError {
+    pub message: String,
+    /// Byte offset into the line where the problem was found.
+    pub position: usize,
+}
+
+impl fmt::Display for QuickAddError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QuickAddError {}
+
+/// Parse a quick-add line.
+///
+/// Words starting with `#` are tags wherever they appear. A leading `3x` or
+/// `3 x` is the quantity. Everything after the first `->` is the
+/// destination.
+pub fn parse(text: &str) -> Result<QuickAdd, QuickAddError> {
+    let (head, tail) = match text.find("->") {
+        Some(i) => (&text[..i], Some((i + 2, &text[i + 2..]))),
+        None => (text, None),
+    };
+    let mut quick = QuickAdd::default();
+
+    let mut words = words(head, 0, &mut quick.tags);
+    if let Some(&(start, first)) = words.first() {
+        let count = match first.strip_suffix(['x', '×']) {
+            Some(digits) if is_number(digits) => Some((digits, 1)),
+            _ if is_number(first) && words.get(1).is_some_and(|w| matches!(w.1, "x" | "×")) => {
+                Some((first, 2))
+            }
+            _ => None,
+        };
+        if let Some((digits, used)) = count {
+            let quantity = digits
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or(QuickAddError {
+                    message: format!("invalid quantity '{digits}'"),
+                    position: start,
+                })?;
+            quick.quantity = Some(quantity);
+            words.drain(..used);
+        }
+    }
+    quick.name = join(&words);
+
+    if let Some((offset, tail)) = tail {
+        let destination = join(&self::words(tail, offset, &mut quick.tags));
+        if destination.is_empty() {
+            return Err(QuickAddError {
+                message: "missing destination after '->'".to_string(),
+                position: offset,
+            });
+        }
+        quick.destination = Some(destination);
+    }
+    Ok(quick)
+}
+
+/// The words of `text` with their byte offsets, moving tags into `tags`.
+fn words<'a>(text: &'a str, offset: usize, tags: &mut Vec<String>) -> Vec<(usize, &'a str)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                let word = &text[s..i];
+                match word.strip_prefix('#') {
+                    Some(tag) if !tag.is_empty() => tags.push(tag.to_string()),
+                    _ => out.push((offset + s, word)),
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn join(words: &[(usize, &str)]) -> String {
+    let words: Vec<&str> = words.iter().map(|&(_, word)| word).collect();
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_part() {
+        assert_eq!(
+            parse("3x AA batteries -> garage/shelf #consumables").unwrap(),
+            QuickAdd {
+                quantity: Some(3),
+                name: "AA batteries".into(),
+                destination: Some("garage/shelf".into()),
+                tags: vec!["consumables".into()],
+            }
+        );
+        assert_eq!(
+            parse("  12 x  screws #diy -> garage/top shelf #small ").unwrap(),
+            QuickAdd {
+                quantity: Some(12),
+                name: "screws".into(),
+                destination: Some("garage/top shelf".into()),
+                tags: vec!["diy".into(), "small".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn parts_can_be_left_out() {
+        assert_eq!(
+            parse("drill").unwrap(),
+            QuickAdd {
+                name: "drill".into(),
+                ..QuickAdd::default()
+            }
+        );
+        assert_eq!(parse("2x").unwrap().name, "");
+        assert_eq!(parse("3xl shirt").unwrap().quantity, None);
+        assert_eq!(parse("x-ray -> office").unwrap().name, "x-ray");
+    }
+
+    #[test]
+    fn reports_errors_with_positions() {
+        assert_eq!(
+            parse("drill -> #tools"),
+            Err(QuickAddError {
+                message: "missing destination after '->'".into(),
+                position: 8,
+            })
+        );
+        assert_eq!(parse(" 0x drill").unwrap_err().position, 1);
+    }
+}
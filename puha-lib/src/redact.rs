@@ -0,0 +1,219 @@
+//! Removing sensitive fields from a tree before it is exported.
+//!
+//! A [`Redaction`] is parsed from a comma-separated list of field names such
+//! as `description,metadata.serial` and applied to a copy of the tree, so
+//! exporters never see the removed data.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Space;
+
+/// An item field to remove.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field {
+    /// The ids of items and spaces, and the links that refer to items by
+    /// them.
+    Id,
+    Description,
+    Quantity,
+    Tags,
+    Attachments,
+    Receipts,
+    Category,
+    /// The purchase date and price, and so the item's value.
+    Purchase,
+    /// The last day of the warranty.
+    Warranty,
+    /// When an audit last found the item, or found it missing.
+    Verification,
+    /// All metadata, or with a key, the entry with that key.
+    Metadata(Option<String>),
+    /// Fields of items and spaces this version does not know, kept from the
+    /// file they were loaded from.
+    Extra,
+}
+
+/// Fields to remove from every item in a tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redaction {
+    fields: Vec<Field>,
+}
+
+/// A field name [`Redaction`] does not know.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownField(pub String);
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown field '{}'; expected id, description, quantity, tags, \
+             attachments, receipts, category, purchase, value, warranty, \
+             verification, metadata, metadata.<key> or extra",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownField {}
+
+impl FromStr for Redaction {
+    type Err = UnknownField;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Vec::new();
+        for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            fields.push(match name {
+                "id" => Field::Id,
+                "description" => Field::Description,
+                "quantity" => Field::Quantity,
+                "tags" => Field::Tags,
+                "attachments" => Field::Attachments,
+                "receipts" => Field::Receipts,
+                "category" => Field::Category,
+                "purchase" | "value" => Field::Purchase,
+                "warranty" => Field::Warranty,
+                "verification" => Field::Verification,
+                "metadata" => Field::Metadata(None),
+                "extra" => Field::Extra,
+                _ => match name.strip_prefix("metadata.") {
+                    Some(key) if !key.is_empty() => Field::Metadata(Some(key.to_string())),
+                    _ => return Err(UnknownField(name.to_string())),
+                },
+            });
+        }
+        Ok(Self { fields })
+    }
+}
+
+impl Redaction {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Remove the fields from every item in `root` and below it, and
+    /// those that spaces have too from the spaces.
+    pub fn apply(&self, root: &mut Space) {
+        if self.is_empty() {
+            return;
+        }
+        for field in &self.fields {
+            match field {
+                Field::Id => root.id = None,
+                Field::Extra => root.extra.clear(),
+                _ => {}
+            }
+        }
+        for item in &mut root.items {
+            for field in &self.fields {
+                match field {
+                    Field::Id => {
+                        item.id = None;
+                        item.links.clear();
+                    }
+                    Field::Description => item.description.clear(),
+                    Field::Quantity => {
+                        item.quantity = None;
+                        item.min_quantity = None;
+                        item.stock_changes.clear();
+                    }
+                    Field::Tags => item.tags.clear(),
+                    Field::Attachments => item.attachments.clear(),
+                    Field::Receipts => item.receipts.clear(),
+                    Field::Category => item.category = None,
+                    Field::Purchase => {
+                        item.purchase_date = None;
+                        item.purchase_price = None;
+                    }
+                    Field::Warranty => item.warranty_until = None,
+                    Field::Verification => {
+                        item.last_verified_at = None;
+                        item.missing_at = None;
+                    }
+                    Field::Metadata(None) => item.metadata.clear(),
+                    Field::Metadata(Some(key)) => {
+                        item.metadata.remove(key);
+                    }
+                    Field::Extra => item.extra.clear(),
+                }
+            }
+        }
+        for child in &mut root.spaces {
+            self.apply(child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn removes_listed_fields_throughout_the_tree() {
+        let item = Item::builder()
+            .name("camera")
+            .description("bought 2021")
+            .metadata("serial", "X123")
+            .metadata("brand", "Acme")
+            .quantity(1)
+            .purchase_price(crate::Price(49_900))
+            .build();
+        let mut root = Space::builder()
+            .name("home")
+            .push_space(Space::builder().name("office").push_item(item).build())
+            .build();
+
+        let redaction: Redaction = "description, metadata.serial, value".parse().unwrap();
+        redaction.apply(&mut root);
+        let camera = &root.spaces()[0].items()[0];
+        assert_eq!(camera.description(), "");
+        assert_eq!(camera.metadata().len(), 1);
+        assert_eq!(camera.metadata().get("brand").unwrap(), "Acme");
+        assert_eq!(camera.quantity(), Some(1));
+        assert_eq!(camera.purchase_price(), None);
+    }
+
+    #[test]
+    fn ids_go_with_the_links_that_use_them() {
+        let mut charger = Item::builder()
+            .name("charger")
+            .id(uuid::Uuid::new_v4())
+            .link(crate::LinkKind::AccessoryFor, uuid::Uuid::new_v4())
+            .warranty_until("2030-01-01".parse().unwrap())
+            .build();
+        charger.verify(crate::Timestamp(1));
+        charger.extra.insert("colour".into(), "black".into());
+        let mut root = Space::builder()
+            .name("home")
+            .id(uuid::Uuid::new_v4())
+            .push_item(charger)
+            .build();
+        root.extra.insert("owner".into(), "Ada".into());
+
+        let redaction: Redaction = "id,warranty,verification,extra".parse().unwrap();
+        redaction.apply(&mut root);
+        let charger = &root.items()[0];
+        assert_eq!(root.id(), None);
+        assert!(root.extra().is_empty());
+        assert_eq!(charger.id(), None);
+        assert!(charger.links().is_empty());
+        assert_eq!(charger.warranty_until(), None);
+        assert_eq!(charger.last_verified_at(), None);
+        assert!(charger.extra().is_empty());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert_eq!(
+            "quantity,serial".parse::<Redaction>(),
+            Err(UnknownField("serial".into()))
+        );
+        assert_eq!(
+            "metadata.".parse::<Redaction>(),
+            Err(UnknownField("metadata.".into()))
+        );
+        assert!("".parse::<Redaction>().unwrap().is_empty());
+    }
+}
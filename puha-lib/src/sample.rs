@@ -0,0 +1,200 @@
+//! Random but realistic-looking trees for demos, benchmarks and bug
+//! reports.
+//!
+//! Generation only depends on the [`SampleOptions`], so the same seed gives
+//! the same tree on every machine and with every version that keeps the
+//! word lists below unchanged.
+
+use std::collections::HashMap;
+
+use crate::{Item, Space};
+
+const ROOMS: &[&str] = &[
+    "Kitchen",
+    "Living room",
+    "Bedroom",
+    "Bathroom",
+    "Hallway",
+    "Office",
+    "Garage",
+    "Attic",
+    "Basement",
+    "Shed",
+    "Laundry room",
+    "Guest room",
+];
+
+const CONTAINERS: &[&str] = &[
+    "Cupboard", "Drawer", "Shelf", "Box", "Wardrobe", "Cabinet", "Crate", "Basket", "Chest", "Bin",
+    "Toolbox", "Suitcase",
+];
+
+const ADJECTIVES: &[&str] = &[
+    "Red", "Blue", "Green", "Black", "White", "Old", "Small", "Large", "Spare", "Wooden", "Metal",
+    "Plastic",
+];
+
+const THINGS: &[&str] = &[
+    "hammer",
+    "screwdriver",
+    "kettle",
+    "blanket",
+    "torch",
+    "charger",
+    "scissors",
+    "candle",
+    "mug",
+    "towel",
+    "extension cord",
+    "tape measure",
+    "umbrella",
+    "headphones",
+    "notebook",
+    "frying pan",
+    "pillow",
+    "glue",
+    "batteries",
+    "light bulb",
+];
+
+const BRANDS: &[&str] = &["Acme", "Nordic", "Fiskars", "Bosch", "Ikea", "Philips"];
+
+/// Size of the tree to generate and the seed to generate it from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleOptions {
+    /// Spaces below the root.
+    pub spaces: usize,
+    pub items: usize,
+    pub seed: u64,
+}
+
+impl Default for SampleOptions {
+    fn default() -> Self {
+        Self {
+            spaces: 50,
+            items: 2000,
+            seed: 0,
+        }
+    }
+}
+
+/// A tree with a root named "Home", `options.spaces` spaces below it and
+/// `options.items` items spread over them, or put in the root if there
+/// are no other spaces.
+///
+/// Rooms come first, directly below the root; the other spaces are put into
+/// random earlier spaces. Space names are unique in the whole tree, since
+/// spaces are found by name, and item names within their space.
+pub fn generate(options: &SampleOptions) -> Space {
+    let mut rng = SplitMix64(options.seed);
+    // Spaces are kept flat while generating, each with its parent's index.
+    let mut spaces: Vec<(Option<usize>, Space)> =
+        vec![(None, Space::builder().name("Home").root(true).build())];
+    // How often each name has been used, to number the repeats.
+    let mut used: HashMap<String, usize> = HashMap::new();
+    let rooms = options.spaces.min(ROOMS.len().max(options.spaces / 8));
+    for n in 0..options.spaces {
+        let (parent, name) = if n < rooms {
+            (0, ROOMS[n % ROOMS.len()].to_string())
+        } else {
+            let parent = 1 + rng.below(n);
+            let name = rng.pick(CONTAINERS).to_string();
+            (parent, name)
+        };
+        let count = used.entry(name.clone()).or_insert(0);
+        *count += 1;
+        let name = match *count {
+            1 => name,
+            n => format!("{name} {n}"),
+        };
+        spaces.push((Some(parent), Space::builder().name(name).build()));
+    }
+    for _ in 0..options.items {
+        let index = match options.spaces {
+            0 => 0,
+            n => 1 + rng.below(n),
+        };
+        let space = &mut spaces[index].1;
+        let thing = rng.pick(THINGS);
+        let mut name = format!("{} {thing}", rng.pick(ADJECTIVES));
+        let mut n = 1;
+        while space.items().iter().any(|i| i.name() == name) {
+            n += 1;
+            name = format!("{} {thing} {n}", rng.pick(ADJECTIVES));
+        }
+        let mut item = Item::builder().name(name);
+        if rng.below(3) == 0 {
+            item = item.metadata("brand", *rng.pick(BRANDS));
+        }
+        if rng.below(4) == 0 {
+            item = item.quantity(2 + rng.below(10) as u32);
+        }
+        space.add_item(item.build());
+    }
+    // Children always come after their parents, so attaching from the back
+    // moves every space into place before its parent is itself moved.
+    while spaces.len() > 1 {
+        let (parent, space) = spaces.pop().expect("more than one space");
+        let parent = parent.expect("only the root has no parent");
+        spaces[parent].1.spaces.insert(0, space);
+    }
+    spaces.pop().expect("root").1
+}
+
+/// Small, portable pseudo-random generator, so that a seed gives the same
+/// tree everywhere.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `n`, which must not be zero.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, words: &'a [&'a str]) -> &'a &'a str {
+        &words[self.below(words.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::TreeStats;
+    use crate::strict;
+
+    #[test]
+    fn generates_the_requested_size_reproducibly() {
+        let options = SampleOptions {
+            spaces: 40,
+            items: 500,
+            seed: 42,
+        };
+        let tree = generate(&options);
+        let stats = TreeStats::of(&tree);
+        assert_eq!(stats.spaces, 41);
+        assert_eq!(stats.items, 500);
+        assert_eq!(tree, generate(&options));
+        assert_ne!(tree, generate(&SampleOptions { seed: 7, ..options }));
+        assert!(strict::check_structure(&tree).is_ok());
+    }
+
+    #[test]
+    fn empty_sample_is_just_the_root() {
+        let options = SampleOptions {
+            spaces: 0,
+            items: 3,
+            seed: 1,
+        };
+        let tree = generate(&options);
+        assert!(tree.spaces().is_empty());
+        assert_eq!(tree.items().len(), 3);
+    }
+}
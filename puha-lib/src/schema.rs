@@ -0,0 +1,30 @@
+//! The JSON Schema of the data file format, for other tools that read or
+//! write puha data.
+
+use crate::Space;
+
+/// The JSON Schema that a saved tree follows.
+pub fn schema() -> serde_json::Value {
+    schemars::schema_for!(Space).to_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_the_saved_form() {
+        let schema = schema();
+        assert_eq!(
+            schema["required"],
+            serde_json::json!(["name", "items", "spaces", "root"])
+        );
+        assert_eq!(
+            schema["$defs"]["Item"]["required"],
+            serde_json::json!(["name", "description"])
+        );
+        // Values with a written form are strings, not their fields.
+        assert_eq!(schema["$defs"]["Size"]["type"], "string");
+        assert_eq!(schema["$defs"]["Timestamp"]["type"], "string");
+    }
+}
@@ -0,0 +1,723 @@
+//! Ranked search over item and space names and item descriptions.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::query::{Field, Query};
+use crate::{Item, Space, matching, path};
+
+/// Upper bound on the number of query variants a synonym list may expand a
+/// single query into.
+const MAX_VARIANTS: usize = 32;
+
+/// How a hit matched the query, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum MatchKind {
+    /// The whole name equals the query.
+    Exact,
+    /// The name starts with the query.
+    Prefix,
+    /// The query occurs somewhere in the name.
+    Substring,
+    /// The query occurs in the item's description.
+    Description,
+    /// Only field terms of a [`Query`] matched, such as metadata values.
+    Field,
+}
+
+/// The entity a search hit refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<'a> {
+    Item(&'a Item),
+    Space(&'a Space),
+}
+
+impl<'a> Target<'a> {
+    pub fn name(&self) -> &'a str {
+        match self {
+            Target::Item(item) => item.name(),
+            Target::Space(space) => space.name(),
+        }
+    }
+}
+
+/// A single search result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit<'a> {
+    /// Names of the spaces leading to the hit, starting at the searched space.
+    pub path: Vec<&'a str>,
+    pub target: Target<'a>,
+    pub kind: MatchKind,
+    /// Byte ranges of the matched text within the name, or within the
+    /// description for [`MatchKind::Description`].
+    pub spans: Vec<Range<usize>>,
+}
+
+impl SearchHit<'_> {
+    /// The hit's location as `space/.../name`.
+    pub fn path_string(&self) -> String {
+        path::join(&self.path, self.target.name())
+    }
+
+    /// Like [`path_string`](Self::path_string), writing to `out`.
+    pub fn write_path(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        path::write_joined(out, &self.path, self.target.name())
+    }
+}
+
+/// An item or space together with the spaces leading to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry<'a> {
+    /// Names of the spaces leading to the entry, starting at the walked space.
+    pub path: Vec<&'a str>,
+    pub target: Target<'a>,
+}
+
+impl Entry<'_> {
+    /// The entry's location as `space/.../name`.
+    pub fn path_string(&self) -> String {
+        path::join(&self.path, self.target.name())
+    }
+
+    /// Like [`path_string`](Self::path_string), writing to `out`.
+    pub fn write_path(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        path::write_joined(out, &self.path, self.target.name())
+    }
+}
+
+/// Every space and item in the tree below and including `root`, in
+/// depth-first order with each space's items before its child spaces.
+pub fn entries(root: &Space) -> Vec<Entry<'_>> {
+    let mut entries = Vec::new();
+    let never = CancellationToken::new();
+    visit(root, &mut Vec::new(), &never, &mut |target, path| {
+        entries.push(Entry {
+            path: path.to_vec(),
+            target,
+        });
+    })
+    .expect("token is never cancelled");
+    entries
+}
+
+/// Entries whose name matches `pattern`.
+#[cfg(feature = "regex")]
+pub fn find_regex<'a>(root: &'a Space, pattern: &regex::Regex) -> Vec<Entry<'a>> {
+    let mut entries = entries(root);
+    entries.retain(|entry| pattern.is_match(entry.target.name()));
+    entries
+}
+
+/// Items under and including `root` whose names are more than 80% similar
+/// to `name`, by [`similarity`](crate::matching::similarity), most similar
+/// first.
+pub fn similar_items<'a>(root: &'a Space, name: &str) -> Vec<Entry<'a>> {
+    let mut similar: Vec<(f64, Entry<'a>)> = entries(root)
+        .into_iter()
+        .filter(|entry| matches!(entry.target, Target::Item(_)))
+        .map(|entry| (matching::similarity(entry.target.name(), name), entry))
+        .filter(|(similarity, _)| *similarity > 0.8)
+        .collect();
+    similar.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    similar.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// A word of a text, lowercased, together with its byte range in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub range: Range<usize>,
+}
+
+/// Split `text` into lowercase words at every non-alphanumeric character.
+///
+/// This is the tokenizer shared by synonym expansion and the search index.
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, c.is_alphanumeric()) {
+            (None, true) => start = Some(index),
+            (Some(from), false) => {
+                tokens.push(Token {
+                    text: text[from..index].to_lowercase(),
+                    range: from..index,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// Groups of words or phrases that should find each other, such as
+/// "torch" and "flashlight".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct Synonyms {
+    groups: Vec<Vec<String>>,
+}
+
+impl Synonyms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn groups(&self) -> &[Vec<String>] {
+        &self.groups
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Add a group of equivalent words. Words are normalized to lowercase
+    /// single-spaced phrases; groups with fewer than two distinct words are
+    /// ignored. Returns whether a group was added.
+    pub fn add_group<I, S>(&mut self, words: I) -> bool
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut group: Vec<String> = Vec::new();
+        for word in words {
+            let phrase = normalize(word.as_ref());
+            if !phrase.is_empty() && !group.contains(&phrase) {
+                group.push(phrase);
+            }
+        }
+        if group.len() < 2 {
+            return false;
+        }
+        self.groups.push(group);
+        true
+    }
+
+    /// Parse a line like `torch = flashlight = lamp` and add it as a group.
+    pub fn add_line(&mut self, line: &str) -> bool {
+        self.add_group(line.split('='))
+    }
+
+    /// Remove every group containing `word`, normalized as in
+    /// [`add_group`](Self::add_group). Returns how many were removed.
+    pub fn remove_word(&mut self, word: &str) -> usize {
+        let word = normalize(word);
+        let before = self.groups.len();
+        self.groups.retain(|group| !group.contains(&word));
+        before - self.groups.len()
+    }
+
+    /// `query` plus every variant obtained by replacing words or phrases with
+    /// their synonyms, lowercased. The original query always comes first.
+    pub fn expand(&self, query: &str) -> Vec<String> {
+        let mut variants = vec![query.trim().to_lowercase()];
+        let mut next = 0;
+        while next < variants.len() && variants.len() < MAX_VARIANTS {
+            let current = variants[next].clone();
+            next += 1;
+            for group in &self.groups {
+                for member in group {
+                    for range in phrase_matches(&current, member) {
+                        for other in group.iter().filter(|o| *o != member) {
+                            let variant = format!(
+                                "{}{}{}",
+                                &current[..range.start],
+                                other,
+                                &current[range.end..]
+                            );
+                            if variants.len() < MAX_VARIANTS && !variants.contains(&variant) {
+                                variants.push(variant);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        variants
+    }
+}
+
+/// `word` as a lowercase single-spaced phrase, as synonyms are kept.
+fn normalize(word: &str) -> String {
+    tokenize(word)
+        .into_iter()
+        .map(|t| t.text)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Byte ranges where the lowercase `phrase` occurs in the lowercase `text`
+/// as whole words.
+fn phrase_matches(text: &str, phrase: &str) -> Vec<Range<usize>> {
+    let is_boundary = |c: Option<char>| c.is_none_or(|c| !c.is_alphanumeric());
+    text.match_indices(phrase)
+        .map(|(start, _)| start..start + phrase.len())
+        .filter(|r| {
+            is_boundary(text[..r.start].chars().next_back())
+                && is_boundary(text[r.end..].chars().next())
+        })
+        .collect()
+}
+
+/// Search `root` and everything below it for `query`, ignoring case.
+///
+/// Hits are ordered by [`MatchKind`], then by depth, then by name.
+pub fn search<'a>(root: &'a Space, query: &str) -> Vec<SearchHit<'a>> {
+    search_with(root, query, &Synonyms::new())
+}
+
+/// Like [`search`], but also find entities matching any synonym variant of
+/// `query`. Each entity is reported once, with its best match.
+pub fn search_with<'a>(root: &'a Space, query: &str, synonyms: &Synonyms) -> Vec<SearchHit<'a>> {
+    search_variants(root, query, synonyms, &CancellationToken::new())
+        .expect("token is never cancelled")
+}
+
+fn search_variants<'a>(
+    root: &'a Space,
+    query: &str,
+    synonyms: &Synonyms,
+    cancel: &CancellationToken,
+) -> Result<Vec<SearchHit<'a>>, Cancelled> {
+    let mut hits: Vec<SearchHit<'a>> = Vec::new();
+    let mut seen: HashMap<*const (), usize> = HashMap::new();
+    for variant in synonyms.expand(query) {
+        for hit in search_once(root, &variant, cancel)? {
+            let key = match hit.target {
+                Target::Item(item) => item as *const Item as *const (),
+                Target::Space(space) => space as *const Space as *const (),
+            };
+            match seen.get(&key) {
+                Some(&index) if hits[index].kind <= hit.kind => {}
+                Some(&index) => hits[index] = hit,
+                None => {
+                    seen.insert(key, hits.len());
+                    hits.push(hit);
+                }
+            }
+        }
+    }
+    sort_hits(&mut hits);
+    Ok(hits)
+}
+
+/// Search with a parsed [`Query`].
+///
+/// The free text of the query is searched like [`search_with`] and the
+/// results are narrowed down to those matching every field term. A query
+/// without free text lists every entity matching its field terms, ranked by
+/// its `name:` term when there is one.
+pub fn search_query<'a>(root: &'a Space, query: &Query, synonyms: &Synonyms) -> Vec<SearchHit<'a>> {
+    search_query_cancellable(root, query, synonyms, &CancellationToken::new())
+        .expect("token is never cancelled")
+}
+
+/// Like [`search_query`], stopping with [`Cancelled`] once `cancel` is
+/// cancelled.
+pub fn search_query_cancellable<'a>(
+    root: &'a Space,
+    query: &Query,
+    synonyms: &Synonyms,
+    cancel: &CancellationToken,
+) -> Result<Vec<SearchHit<'a>>, Cancelled> {
+    let matches_fields = |target: &Target| match target {
+        Target::Item(item) => query.matches_item_fields(item),
+        Target::Space(space) => query.matches_space_fields(space),
+    };
+    if let Some(text) = query.text() {
+        let mut hits = search_variants(root, &text, synonyms, cancel)?;
+        hits.retain(|hit| matches_fields(&hit.target));
+        return Ok(hits);
+    }
+
+    let name_term = query
+        .field_terms()
+        .find(|t| t.field == Field::Name)
+        .map(|t| Folded::new(&t.value));
+    let desc_term = query
+        .field_terms()
+        .find(|t| t.field == Field::Description)
+        .map(|t| Folded::new(&t.value));
+    let mut hits = Vec::new();
+    let mut path = Vec::new();
+    visit(root, &mut path, cancel, &mut |target, path| {
+        if !matches_fields(&target) {
+            return;
+        }
+        let hit = match (&name_term, &desc_term, target) {
+            (Some(term), _, _) => match_name(target, term, path),
+            (None, Some(term), Target::Item(item)) => Some(SearchHit {
+                path: path.to_vec(),
+                target,
+                kind: MatchKind::Description,
+                spans: term.find_in(item.description()),
+            }),
+            _ => None,
+        };
+        hits.push(hit.unwrap_or_else(|| SearchHit {
+            path: path.to_vec(),
+            target,
+            kind: MatchKind::Field,
+            spans: Vec::new(),
+        }));
+    })?;
+    sort_hits(&mut hits);
+    Ok(hits)
+}
+
+/// Call `f` for `space`, every space below it and all their items, along
+/// with the path of spaces leading to each. Stops before the next space once
+/// `cancel` is cancelled.
+fn visit<'a>(
+    space: &'a Space,
+    path: &mut Vec<&'a str>,
+    cancel: &CancellationToken,
+    f: &mut impl FnMut(Target<'a>, &[&'a str]),
+) -> Result<(), Cancelled> {
+    cancel.check()?;
+    f(Target::Space(space), path);
+    path.push(space.name());
+    for item in space.items() {
+        f(Target::Item(item), path);
+    }
+    for child in space.spaces() {
+        visit(child, path, cancel, f)?;
+    }
+    path.pop();
+    Ok(())
+}
+
+fn search_once<'a>(
+    root: &'a Space,
+    query: &str,
+    cancel: &CancellationToken,
+) -> Result<Vec<SearchHit<'a>>, Cancelled> {
+    let query = Folded::new(query);
+    let mut hits = Vec::new();
+    if query.text.is_empty() {
+        return Ok(hits);
+    }
+    let mut path = Vec::new();
+    if let Some(hit) = match_name(Target::Space(root), &query, &path) {
+        hits.push(hit);
+    }
+    collect(root, &query, &mut path, &mut hits, cancel)?;
+    Ok(hits)
+}
+
+fn sort_hits(hits: &mut [SearchHit<'_>]) {
+    hits.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then(a.path.len().cmp(&b.path.len()))
+            .then_with(|| a.target.name().cmp(b.target.name()))
+    });
+}
+
+fn collect<'a>(
+    space: &'a Space,
+    query: &Folded,
+    path: &mut Vec<&'a str>,
+    hits: &mut Vec<SearchHit<'a>>,
+    cancel: &CancellationToken,
+) -> Result<(), Cancelled> {
+    cancel.check()?;
+    path.push(space.name());
+    for item in space.items() {
+        let hit = match_name(Target::Item(item), query, path).or_else(|| {
+            let spans = query.find_in(item.description());
+            (!spans.is_empty()).then(|| SearchHit {
+                path: path.clone(),
+                target: Target::Item(item),
+                kind: MatchKind::Description,
+                spans,
+            })
+        });
+        hits.extend(hit);
+    }
+    for child in space.spaces() {
+        hits.extend(match_name(Target::Space(child), query, path));
+        collect(child, query, path, hits, cancel)?;
+    }
+    path.pop();
+    Ok(())
+}
+
+fn match_name<'a>(target: Target<'a>, query: &Folded, path: &[&'a str]) -> Option<SearchHit<'a>> {
+    let name = target.name();
+    let spans = query.find_in(name);
+    let first = spans.first()?;
+    let kind = if first.start == 0 && first.end == name.len() {
+        MatchKind::Exact
+    } else if first.start == 0 {
+        MatchKind::Prefix
+    } else {
+        MatchKind::Substring
+    };
+    Some(SearchHit {
+        path: path.to_vec(),
+        target,
+        kind,
+        spans,
+    })
+}
+
+/// Lowercased text plus, for every byte of it, the byte offset of the
+/// character it came from in the original text.
+pub(crate) struct Folded {
+    text: String,
+    origin: Vec<usize>,
+}
+
+impl Folded {
+    pub(crate) fn new(text: &str) -> Self {
+        let mut folded = String::with_capacity(text.len());
+        let mut origin = Vec::with_capacity(text.len());
+        for (index, c) in text.char_indices() {
+            for lower in c.to_lowercase() {
+                folded.push(lower);
+                origin.resize(folded.len(), index);
+            }
+        }
+        Self {
+            text: folded,
+            origin,
+        }
+    }
+
+    /// Non-overlapping occurrences of `self` in `haystack`, as byte ranges of
+    /// the original `haystack`.
+    pub(crate) fn find_in(&self, haystack: &str) -> Vec<Range<usize>> {
+        let folded = Folded::new(haystack);
+        let mut spans = Vec::new();
+        let mut from = 0;
+        while let Some(pos) = folded.text[from..].find(&self.text) {
+            let start = from + pos;
+            let end = start + self.text.len();
+            let original_end = match folded.origin.get(end) {
+                Some(&next) => next,
+                None => haystack.len(),
+            };
+            spans.push(folded.origin[start]..original_end);
+            from = end;
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> Space {
+        let drawer = Space::builder()
+            .name("drawer")
+            .push_item(Item::builder().name("Hammer").build())
+            .push_item(Item::builder().name("hammer drill").build())
+            .push_item(
+                Item::builder()
+                    .name("claw")
+                    .description("a hammer part")
+                    .build(),
+            )
+            .build();
+        Space::builder()
+            .name("garage")
+            .root(true)
+            .push_item(Item::builder().name("sledgehammer").build())
+            .push_space(drawer)
+            .build()
+    }
+
+    #[test]
+    fn cancelled_search_stops() {
+        let root = tree();
+        let query = Query::parse("hammer").unwrap();
+        let cancel = CancellationToken::new();
+        let hits = search_query_cancellable(&root, &query, &Synonyms::new(), &cancel).unwrap();
+        assert_eq!(hits.len(), 4);
+
+        cancel.cancel();
+        let result = search_query_cancellable(&root, &query, &Synonyms::new(), &cancel);
+        assert_eq!(result.unwrap_err(), Cancelled);
+        let query = Query::parse("meta.serial:X").unwrap();
+        let result = search_query_cancellable(&root, &query, &Synonyms::new(), &cancel);
+        assert_eq!(result.unwrap_err(), Cancelled);
+    }
+
+    #[test]
+    fn hits_are_ranked_by_match_kind() {
+        let root = tree();
+        let hits = search(&root, "hammer");
+        let found: Vec<(String, MatchKind)> =
+            hits.iter().map(|h| (h.path_string(), h.kind)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("garage/drawer/Hammer".to_string(), MatchKind::Exact),
+                ("garage/drawer/hammer drill".to_string(), MatchKind::Prefix),
+                ("garage/sledgehammer".to_string(), MatchKind::Substring),
+                ("garage/drawer/claw".to_string(), MatchKind::Description),
+            ]
+        );
+        assert_eq!(hits[2].spans, vec![6..12]);
+        assert_eq!(hits[3].spans, vec![2..8]);
+    }
+
+    #[test]
+    fn spans_point_into_original_text() {
+        let root = Space::builder()
+            .name("root")
+            .push_item(Item::builder().name("ÁLLVÁNY és állvány").build())
+            .build();
+        let hits = search(&root, "állvány");
+        let name = hits[0].target.name();
+        let matched: Vec<&str> = hits[0].spans.iter().map(|s| &name[s.clone()]).collect();
+        assert_eq!(matched, vec!["ÁLLVÁNY", "állvány"]);
+    }
+
+    #[test]
+    fn tokenizer_splits_on_non_alphanumerics() {
+        let tokens = tokenize("USB-C  Kábel");
+        let words: Vec<&str> = tokens.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(words, vec!["usb", "c", "kábel"]);
+        assert_eq!(tokens[2].range, 7..13);
+    }
+
+    #[test]
+    fn synonyms_expand_whole_words_and_phrases() {
+        let mut synonyms = Synonyms::new();
+        assert!(synonyms.add_line("Torch = flashlight"));
+        assert!(synonyms.add_line("allen key = hex  key"));
+        assert!(!synonyms.add_line("lamp = LAMP"));
+
+        assert_eq!(
+            synonyms.expand("big torch"),
+            vec!["big torch", "big flashlight"]
+        );
+        assert_eq!(synonyms.expand("torches"), vec!["torches"]);
+        assert_eq!(synonyms.expand("Allen key"), vec!["allen key", "hex key"]);
+
+        assert_eq!(synonyms.remove_word("FLASHLIGHT"), 1);
+        assert_eq!(synonyms.groups().len(), 1);
+        assert_eq!(synonyms.remove_word("Hex   Key"), 1);
+        assert!(synonyms.is_empty());
+    }
+
+    #[test]
+    fn search_with_synonyms_finds_each_entity_once() {
+        let root = Space::builder()
+            .name("shed")
+            .push_item(Item::builder().name("flashlight").build())
+            .push_item(
+                Item::builder()
+                    .name("torch")
+                    .description("flashlight")
+                    .build(),
+            )
+            .build();
+        let mut synonyms = Synonyms::new();
+        synonyms.add_line("torch = flashlight");
+
+        let hits = search_with(&root, "torch", &synonyms);
+        let found: Vec<(&str, MatchKind)> =
+            hits.iter().map(|h| (h.target.name(), h.kind)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("flashlight", MatchKind::Exact),
+                ("torch", MatchKind::Exact)
+            ]
+        );
+    }
+
+    #[test]
+    fn query_fields_narrow_results() {
+        let mut root = tree();
+        root.find_space_mut("drawer").unwrap().add_item(
+            Item::builder()
+                .name("drill bits")
+                .metadata("serial", "ABC123")
+                .build(),
+        );
+        let synonyms = Synonyms::new();
+
+        let query = Query::parse("hammer name:drill").unwrap();
+        let hits = search_query(&root, &query, &synonyms);
+        let names: Vec<&str> = hits.iter().map(|h| h.target.name()).collect();
+        assert_eq!(names, vec!["hammer drill"]);
+
+        let query = Query::parse("meta.serial:abc").unwrap();
+        let hits = search_query(&root, &query, &synonyms);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].kind, MatchKind::Field);
+        assert_eq!(hits[0].path_string(), "garage/drawer/drill bits");
+
+        let query = Query::parse(r#"desc:"hammer part""#).unwrap();
+        let hits = search_query(&root, &query, &synonyms);
+        assert_eq!(hits[0].target.name(), "claw");
+        assert_eq!(hits[0].spans, vec![2..13]);
+    }
+
+    #[test]
+    fn entries_list_paths_depth_first() {
+        let root = tree();
+        let paths: Vec<String> = entries(&root).iter().map(Entry::path_string).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "garage",
+                "garage/sledgehammer",
+                "garage/drawer",
+                "garage/drawer/Hammer",
+                "garage/drawer/hammer drill",
+                "garage/drawer/claw",
+            ]
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_matches_names() {
+        let root = tree();
+        let pattern = regex::Regex::new("(?i)^hammer( |$)").unwrap();
+        let paths: Vec<String> = find_regex(&root, &pattern)
+            .iter()
+            .map(Entry::path_string)
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["garage/drawer/Hammer", "garage/drawer/hammer drill"]
+        );
+    }
+
+    #[test]
+    fn similar_items_are_close_in_spelling() {
+        let root = tree();
+        let paths = |name| -> Vec<String> {
+            similar_items(&root, name)
+                .iter()
+                .map(Entry::path_string)
+                .collect()
+        };
+        assert_eq!(paths("hammers"), ["garage/drawer/Hammer"]);
+        assert_eq!(paths("hammer-drill"), ["garage/drawer/hammer drill"]);
+        assert!(paths("drawer").is_empty());
+    }
+
+    #[test]
+    fn spaces_match_and_empty_query_finds_nothing() {
+        let root = tree();
+        let hits = search(&root, "DRAW");
+        assert_eq!(hits.len(), 1);
+        assert!(matches!(hits[0].target, Target::Space(s) if s.name() == "drawer"));
+        assert!(search(&root, "").is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+//! Tree-wide settings stored on the root space.
+
+use serde::{Deserialize, Serialize};
+
+use crate::kits::Kits;
+use crate::limits::TreeLimits;
+use crate::search::Synonyms;
+use crate::views::Views;
+
+/// Settings that travel with the data file, so everyone sharing the file
+/// gets the same behaviour.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct Settings {
+    /// Words the search treats as equivalent.
+    pub synonyms: Synonyms,
+    /// Named sets of items that belong together.
+    #[serde(skip_serializing_if = "Kits::is_empty")]
+    pub kits: Kits,
+    /// Groupings of items besides where they are kept.
+    #[serde(skip_serializing_if = "Views::is_empty")]
+    pub views: Views,
+    /// Bounds on the shape of the tree, checked when adding to it.
+    #[serde(skip_serializing_if = "TreeLimits::is_default")]
+    pub limits: TreeLimits,
+}
+
+impl Settings {
+    /// Whether every setting has its default value.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
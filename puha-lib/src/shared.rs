@@ -0,0 +1,222 @@
+//! A space tree that can be shared between threads.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use arc_swap::ArcSwap;
+
+use crate::Space;
+
+/// Notification sent to subscribers after a [`SharedSpace`] was modified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    /// Revision of the tree after the change. Starts at 0 and increases by one
+    /// with every call to [`SharedSpace::update`].
+    pub revision: u64,
+}
+
+struct Inner {
+    tree: ArcSwap<Space>,
+    revision: AtomicU64,
+    /// Held by writers, so updates apply one after another.
+    writer: Mutex<()>,
+    subscribers: Mutex<Vec<Sender<Change>>>,
+}
+
+/// Cheaply cloneable handle to a space tree shared between threads.
+///
+/// Readers get an immutable snapshot of the tree without taking a lock, so
+/// they never wait for each other or for writers. Writers take turns through
+/// [`update`](Self::update), which changes a copy of the tree and then
+/// publishes it, after which every subscriber receives a [`Change`].
+/// Snapshots taken before an update keep showing the tree as it was.
+#[derive(Clone)]
+pub struct SharedSpace {
+    inner: Arc<Inner>,
+}
+
+impl SharedSpace {
+    pub fn new(tree: Space) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                tree: ArcSwap::from_pointee(tree),
+                revision: AtomicU64::new(0),
+                writer: Mutex::new(()),
+                subscribers: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Run `f` with the current tree.
+    pub fn read<R>(&self, f: impl FnOnce(&Space) -> R) -> R {
+        f(&self.inner.tree.load())
+    }
+
+    /// The current tree, unaffected by later updates.
+    pub fn load(&self) -> Arc<Space> {
+        self.inner.tree.load_full()
+    }
+
+    /// Run `f` on a copy of the tree, publish the copy and notify
+    /// subscribers. Subscribers are notified before the next update starts,
+    /// so they receive changes in the order of their revisions.
+    pub fn update<R>(&self, f: impl FnOnce(&mut Space) -> R) -> R {
+        let _writer = self
+            .inner
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut tree = Space::clone(&self.inner.tree.load());
+        let result = f(&mut tree);
+        self.inner.tree.store(Arc::new(tree));
+        let revision = self.inner.revision.fetch_add(1, Ordering::SeqCst) + 1;
+        self.notify(Change { revision });
+        result
+    }
+
+    /// Clone the current tree.
+    pub fn snapshot(&self) -> Space {
+        self.read(Space::clone)
+    }
+
+    /// Current revision of the tree.
+    pub fn revision(&self) -> u64 {
+        self.inner.revision.load(Ordering::SeqCst)
+    }
+
+    /// Receive a [`Change`] after every future update.
+    pub fn subscribe(&self) -> Receiver<Change> {
+        let (tx, rx) = mpsc::channel();
+        self.inner
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(tx);
+        rx
+    }
+
+    fn notify(&self, change: Change) {
+        let mut subscribers = self
+            .inner
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        subscribers.retain(|tx| tx.send(change).is_ok());
+    }
+}
+
+impl From<Space> for SharedSpace {
+    fn from(tree: Space) -> Self {
+        Self::new(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn updates_are_visible_and_notified() {
+        let shared = SharedSpace::new(Space::builder().name("root").root(true).build());
+        let changes = shared.subscribe();
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    shared.update(|root| {
+                        root.add_item(Item::builder().name(format!("item{i}")).build())
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(shared.read(|root| root.items().len()), 4);
+        assert_eq!(shared.revision(), 4);
+        let revisions: Vec<u64> = changes.try_iter().map(|c| c.revision).collect();
+        assert_eq!(revisions, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn concurrent_writers_and_readers_stay_consistent() {
+        const WRITERS: usize = 4;
+        const READERS: usize = 4;
+        const UPDATES: usize = 50;
+        let shared = SharedSpace::new(Space::builder().name("root").root(true).build());
+        let changes = shared.subscribe();
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                let shared = shared.clone();
+                let done = done.clone();
+                std::thread::spawn(move || {
+                    // Every snapshot is a whole update or none of it, and
+                    // later snapshots never go back.
+                    let mut seen = 0;
+                    while !done.load(Ordering::SeqCst) {
+                        let count = shared.read(|root| root.items().len());
+                        assert!(count >= seen && count <= WRITERS * UPDATES);
+                        seen = count;
+                    }
+                })
+            })
+            .collect();
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|w| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    for u in 0..UPDATES {
+                        shared.update(|root| {
+                            root.add_item(Item::builder().name(format!("item{w}-{u}")).build())
+                        });
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        done.store(true, Ordering::SeqCst);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let total = (WRITERS * UPDATES) as u64;
+        assert_eq!(shared.revision(), total);
+        let names: std::collections::HashSet<String> = shared.read(|root| {
+            root.items()
+                .iter()
+                .map(|item| item.name().to_string())
+                .collect()
+        });
+        assert_eq!(names.len(), WRITERS * UPDATES);
+        let revisions: Vec<u64> = changes.try_iter().map(|c| c.revision).collect();
+        assert_eq!(revisions, (1..=total).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn dropped_subscribers_are_pruned() {
+        let shared = SharedSpace::new(Space::builder().name("root").build());
+        drop(shared.subscribe());
+        shared.update(|root| root.set_name("home"));
+        assert!(shared.inner.subscribers.lock().unwrap().is_empty());
+        assert_eq!(shared.snapshot().name(), "home");
+    }
+
+    #[test]
+    fn loaded_trees_do_not_block_or_see_updates() {
+        let shared = SharedSpace::new(Space::builder().name("root").build());
+        let before = shared.load();
+        shared.update(|root| root.set_name("home"));
+        assert_eq!(before.name(), "root");
+        assert_eq!(shared.load().name(), "home");
+        shared.read(|root| shared.update(|inner| inner.set_name(root.name().to_uppercase())));
+        assert_eq!(shared.load().name(), "HOME");
+    }
+}
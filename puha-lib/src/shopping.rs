@@ -0,0 +1,202 @@
+//! Shopping lists: the items whose quantity fell below the minimum to keep
+//! in stock, grouped by category.
+
+use std::collections::BTreeMap;
+
+use crate::Space;
+use crate::search::{self, Target};
+
+/// An item to buy more of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortfall {
+    pub name: String,
+    pub path: String,
+    /// How many there are; an item with a minimum but no quantity counts as
+    /// having none.
+    pub quantity: u32,
+    pub min_quantity: u32,
+}
+
+impl Shortfall {
+    /// How many to buy to get back to the minimum.
+    pub fn missing(&self) -> u32 {
+        self.min_quantity - self.quantity
+    }
+}
+
+/// Items below their minimum, by category.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShoppingList {
+    /// Groups in category order, with items without a category last.
+    pub groups: Vec<(Option<String>, Vec<Shortfall>)>,
+}
+
+impl ShoppingList {
+    /// The items under `root` whose quantity is below their minimum.
+    pub fn of(root: &Space) -> Self {
+        let mut categorized: BTreeMap<String, Vec<Shortfall>> = BTreeMap::new();
+        let mut other = Vec::new();
+        for entry in search::entries(root) {
+            let Target::Item(item) = entry.target else {
+                continue;
+            };
+            let Some(min_quantity) = item.min_quantity else {
+                continue;
+            };
+            let quantity = item.quantity.unwrap_or(0);
+            if quantity >= min_quantity {
+                continue;
+            }
+            let shortfall = Shortfall {
+                name: item.name.clone(),
+                path: entry.path_string(),
+                quantity,
+                min_quantity,
+            };
+            match &item.category {
+                Some(category) => categorized
+                    .entry(category.clone())
+                    .or_default()
+                    .push(shortfall),
+                None => other.push(shortfall),
+            }
+        }
+        let mut groups: Vec<_> = categorized.into_iter().map(|(c, s)| (Some(c), s)).collect();
+        if !other.is_empty() {
+            groups.push((None, other));
+        }
+        Self { groups }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// A Markdown checklist with a heading per category.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for (category, items) in &self.groups {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "## {}\n\n",
+                category.as_deref().unwrap_or("Other")
+            ));
+            for item in items {
+                out.push_str(&format!(
+                    "- [ ] {} × {} ({}, have {} of {})\n",
+                    item.missing(),
+                    item.name,
+                    item.path,
+                    item.quantity,
+                    item.min_quantity
+                ));
+            }
+        }
+        out
+    }
+
+    /// CSV with a header row and a row per item.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("category,name,path,quantity,min_quantity,missing\n");
+        for (category, items) in &self.groups {
+            for item in items {
+                out.push_str(&format!(
+                    "{},{},{},{},{},{}\n",
+                    csv_field(category.as_deref().unwrap_or("")),
+                    csv_field(&item.name),
+                    csv_field(&item.path),
+                    item.quantity,
+                    item.min_quantity,
+                    item.missing()
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// `field`, quoted if it holds a comma, quote or line break.
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    fn pantry() -> Space {
+        Space::builder()
+            .name("home")
+            .push_item(
+                Item::builder()
+                    .name("rice")
+                    .category("food")
+                    .quantity(1)
+                    .min_quantity(3)
+                    .build(),
+            )
+            .push_item(
+                Item::builder()
+                    .name("AA batteries, 4-pack")
+                    .min_quantity(1)
+                    .build(),
+            )
+            .push_item(
+                Item::builder()
+                    .name("pasta")
+                    .category("food")
+                    .quantity(5)
+                    .min_quantity(2)
+                    .build(),
+            )
+            .push_item(Item::builder().name("drill").quantity(0).build())
+            .build()
+    }
+
+    #[test]
+    fn lists_items_below_their_minimum_by_category() {
+        let list = ShoppingList::of(&pantry());
+        let names: Vec<(Option<&str>, Vec<&str>)> = list
+            .groups
+            .iter()
+            .map(|(c, items)| {
+                (
+                    c.as_deref(),
+                    items.iter().map(|i| i.name.as_str()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (Some("food"), vec!["rice"]),
+                (None, vec!["AA batteries, 4-pack"])
+            ]
+        );
+        assert_eq!(list.groups[0].1[0].missing(), 2);
+        assert!(ShoppingList::of(&Space::builder().name("empty").build()).is_empty());
+    }
+
+    #[test]
+    fn renders_markdown_and_csv() {
+        let list = ShoppingList::of(&pantry());
+        assert_eq!(
+            list.to_markdown(),
+            "## food\n\n- [ ] 2 × rice (home/rice, have 1 of 3)\n\n\
+             ## Other\n\n- [ ] 1 × AA batteries, 4-pack (home/AA batteries, 4-pack, have 0 of 1)\n"
+        );
+        assert_eq!(
+            list.to_csv(),
+            "category,name,path,quantity,min_quantity,missing\n\
+             food,rice,home/rice,1,3,2\n\
+             ,\"AA batteries, 4-pack\",\"home/AA batteries, 4-pack\",0,1,1\n"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Recognising files that are not puha data, to explain why they do not
+//! load rather than report where the JSON parser gave up.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// The byte order mark some editors put at the start of UTF-8 files.
+pub const BOM: &str = "\u{feff}";
+
+/// `data` without a leading byte order mark.
+pub fn strip_bom(data: &str) -> &str {
+    data.strip_prefix(BOM).unwrap_or(data)
+}
+
+/// Advice on what is wrong with data that failed to load, judging by its
+/// start, if it is a common mistake rather than damaged JSON.
+pub fn mistake(start: &str) -> Option<&'static str> {
+    let start = strip_bom(start).trim_start();
+    let first_line = start.lines().next().unwrap_or_default();
+    if start.is_empty() {
+        Some("the file is empty; start a new tree with `puha new-root <name>`")
+    } else if start.starts_with('{') {
+        None
+    } else if start.starts_with('[') {
+        Some("the file holds a JSON list, but puha data is a single root space object")
+    } else if first_line.starts_with("---") || looks_like_yaml_key(first_line) {
+        Some("this looks like YAML, but puha data files are JSON")
+    } else if first_line.contains([',', ';', '\t']) {
+        Some("this looks like a CSV export, but puha data files are JSON")
+    } else {
+        None
+    }
+}
+
+/// `error`, from loading the file at `path`, or advice from [`mistake`] if
+/// the file's start shows a common mistake.
+pub(crate) fn explain(path: &Path, error: serde_json::Error) -> Box<dyn std::error::Error> {
+    let mut start = Vec::new();
+    let read = File::open(path).and_then(|file| file.take(1024).read_to_end(&mut start));
+    match read
+        .ok()
+        .and_then(|_| mistake(&String::from_utf8_lossy(&start)))
+    {
+        Some(advice) => format!("{}: {advice}", path.display()).into(),
+        None => error.into(),
+    }
+}
+
+/// Whether `line` reads like `key: value` or `key:`.
+fn looks_like_yaml_key(line: &str) -> bool {
+    let Some((key, rest)) = line.split_once(':') else {
+        return false;
+    };
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ' '))
+        && (rest.is_empty() || rest.starts_with(' '))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_common_mistakes() {
+        assert!(mistake("  \n").unwrap().contains("empty"));
+        assert!(mistake("[{\"name\": \"home\"}]").unwrap().contains("list"));
+        assert!(mistake("---\nname: home\n").unwrap().contains("YAML"));
+        assert!(mistake("name: home\nitems:\n").unwrap().contains("YAML"));
+        assert!(
+            mistake("Name,Description\nlamp,desk\n")
+                .unwrap()
+                .contains("CSV")
+        );
+        assert_eq!(mistake("\u{feff}{\"name\": "), None);
+        assert_eq!(mistake("garbage"), None);
+    }
+
+    #[test]
+    fn strips_a_byte_order_mark() {
+        assert_eq!(strip_bom("\u{feff}{}"), "{}");
+        assert_eq!(strip_bom("{}"), "{}");
+    }
+}
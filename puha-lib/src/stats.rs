@@ -0,0 +1,126 @@
+//! Size figures for a tree.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem::size_of;
+
+use crate::links::Link;
+use crate::stock::StockChange;
+use crate::{Item, Space};
+
+/// How many of each thing a tree holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    pub spaces: usize,
+    pub items: usize,
+    /// Metadata entries over all items.
+    pub metadata: usize,
+}
+
+impl TreeStats {
+    /// Count everything in `root` and below it, `root` included.
+    pub fn of(root: &Space) -> Self {
+        let mut stats = Self::default();
+        stats.add(root);
+        stats
+    }
+
+    /// Spaces and items together.
+    pub fn nodes(&self) -> usize {
+        self.spaces + self.items
+    }
+
+    fn add(&mut self, space: &Space) {
+        self.spaces += 1;
+        self.items += space.items().len();
+        self.metadata += space
+            .items()
+            .iter()
+            .map(|i| i.metadata().len())
+            .sum::<usize>();
+        for child in space.spaces() {
+            self.add(child);
+        }
+    }
+}
+
+/// Estimate of the bytes `root` and everything below it take up in memory.
+///
+/// Counts the structs themselves, the heap buffers of their strings and
+/// vectors and a per-entry overhead for maps. Allocator overhead and unknown
+/// fields kept for round-tripping are not included, so the real figure is
+/// somewhat higher.
+pub fn estimated_memory(root: &Space) -> usize {
+    size_of::<Space>() + space_heap(root)
+}
+
+fn space_heap(space: &Space) -> usize {
+    let mut bytes = space.name.capacity()
+        + option_heap(&space.icon)
+        + option_heap(&space.color)
+        + space.items.capacity() * size_of::<Item>()
+        + space.spaces.capacity() * size_of::<Space>();
+    for item in &space.items {
+        bytes += item.name.capacity()
+            + item.description.capacity()
+            + option_heap(&item.icon)
+            + option_heap(&item.color)
+            + option_heap(&item.category)
+            + item.stock_changes.capacity() * size_of::<StockChange>()
+            + map_heap(&item.metadata)
+            + set_heap(&item.tags)
+            + item.attachments.capacity() * size_of::<String>()
+            + item.attachments.iter().map(String::capacity).sum::<usize>()
+            + item.receipts.capacity() * size_of::<String>()
+            + item.receipts.iter().map(String::capacity).sum::<usize>()
+            + item.links.capacity() * size_of::<Link>();
+    }
+    for child in &space.spaces {
+        bytes += space_heap(child);
+    }
+    bytes
+}
+
+fn option_heap(text: &Option<String>) -> usize {
+    text.as_ref().map_or(0, String::capacity)
+}
+
+/// B-tree nodes hold up to 11 entries; assume they are two thirds full.
+fn map_heap(map: &BTreeMap<String, String>) -> usize {
+    let entries: usize = map.iter().map(|(k, v)| k.capacity() + v.capacity()).sum();
+    entries + map.len() * size_of::<(String, String)>() * 3 / 2
+}
+
+/// Like [`map_heap`], for sets.
+fn set_heap(set: &BTreeSet<String>) -> usize {
+    let entries: usize = set.iter().map(String::capacity).sum();
+    entries + set.len() * size_of::<String>() * 3 / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_estimate_grow_with_the_tree() {
+        let small = Space::builder().name("home").build();
+        let big = Space::builder()
+            .name("home")
+            .push_space(
+                Space::builder()
+                    .name("garage")
+                    .push_item(
+                        Item::builder()
+                            .name("drill")
+                            .metadata("serial", "X1")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        let stats = TreeStats::of(&big);
+        assert_eq!((stats.spaces, stats.items, stats.metadata), (2, 1, 1));
+        assert_eq!(stats.nodes(), 3);
+        assert!(estimated_memory(&big) > estimated_memory(&small));
+    }
+}
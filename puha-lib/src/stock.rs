@@ -0,0 +1,263 @@
+//! Using up and restocking items, with a history of the changes to tell how
+//! fast they run out.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Item;
+use crate::value::{Date, ValueError};
+
+pub(crate) const SECONDS_PER_DAY: u64 = 86_400;
+
+/// A moment in UTC, to the second, written like `2024-05-01T08:30:00Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "String", into = "String")]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    pub fn now() -> Self {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self(seconds)
+    }
+
+    pub fn date(self) -> Date {
+        Date::from_days((self.0 / SECONDS_PER_DAY) as i64)
+    }
+
+    /// Days from `self` to `later`, with fractions.
+    pub fn days_until(self, later: Timestamp) -> f64 {
+        (later.0 as f64 - self.0 as f64) / SECONDS_PER_DAY as f64
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.0 % SECONDS_PER_DAY;
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}Z",
+            self.date(),
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ValueError {
+            message: format!("invalid timestamp '{s}'; expected e.g. 2024-05-01T08:30:00Z"),
+        };
+        let (date, time) = s.split_once('T').ok_or_else(invalid)?;
+        let date: Date = date.parse()?;
+        let time = time.strip_suffix('Z').ok_or_else(invalid)?;
+        let parts: Vec<u64> = time
+            .split(':')
+            .map(|p| Some(p).filter(|p| p.len() == 2)?.parse().ok())
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        let [hours @ 0..24, minutes @ 0..60, seconds @ 0..60] = parts[..] else {
+            return Err(invalid());
+        };
+        let days = u64::try_from(date.days()).map_err(|_| invalid())?;
+        Ok(Self(
+            days * SECONDS_PER_DAY + hours * 3600 + minutes * 60 + seconds,
+        ))
+    }
+}
+
+impl TryFrom<String> for Timestamp {
+    type Error = ValueError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Timestamp> for String {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.to_string()
+    }
+}
+
+/// A change to an item's quantity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StockChange {
+    pub at: Timestamp,
+    /// Negative when some were used up.
+    pub delta: i64,
+}
+
+/// Using up more of an item than there is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotEnough {
+    pub have: u32,
+    pub wanted: u32,
+}
+
+impl fmt::Display for NotEnough {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "only {} left, cannot use {}", self.have, self.wanted)
+    }
+}
+
+impl std::error::Error for NotEnough {}
+
+/// Adding more of an item than a quantity can count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TooMany {
+    pub have: u32,
+    pub adding: u32,
+}
+
+impl fmt::Display for TooMany {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} already, cannot add {} more", self.have, self.adding)
+    }
+}
+
+impl std::error::Error for TooMany {}
+
+impl Item {
+    /// Changes to the quantity made with [`consume`](Self::consume) and
+    /// [`restock`](Self::restock), oldest first.
+    pub fn stock_changes(&self) -> &[StockChange] {
+        &self.stock_changes
+    }
+
+    /// Use up `n` of the item at `at`, returning how many are left. An item
+    /// without a quantity has none. Using up none records nothing.
+    pub fn consume(&mut self, n: u32, at: Timestamp) -> Result<u32, NotEnough> {
+        let have = self.quantity.unwrap_or(0);
+        let left = have.checked_sub(n).ok_or(NotEnough { have, wanted: n })?;
+        self.change_stock(left, -i64::from(n), at);
+        Ok(left)
+    }
+
+    /// Add `n` of the item at `at`, returning how many there are now.
+    /// Adding none records nothing.
+    pub fn restock(&mut self, n: u32, at: Timestamp) -> Result<u32, TooMany> {
+        let have = self.quantity.unwrap_or(0);
+        let now = have.checked_add(n).ok_or(TooMany { have, adding: n })?;
+        self.change_stock(now, i64::from(n), at);
+        Ok(now)
+    }
+
+    fn change_stock(&mut self, quantity: u32, delta: i64, at: Timestamp) {
+        if delta == 0 {
+            return;
+        }
+        self.quantity = Some(quantity);
+        self.stock_changes.push(StockChange { at, delta });
+        self.version += 1;
+    }
+
+    /// How many of the item are used up per day on average, from the first
+    /// recorded change until `now`. `None` before anything was used up.
+    pub fn daily_use(&self, now: Timestamp) -> Option<f64> {
+        let first = self.stock_changes.first()?;
+        let used: i64 = self
+            .stock_changes
+            .iter()
+            .filter(|c| c.delta < 0)
+            .map(|c| -c.delta)
+            .sum();
+        if used == 0 {
+            return None;
+        }
+        // Less than a day of history says little; count it as a day.
+        Some(used as f64 / first.at.days_until(now).max(1.0))
+    }
+
+    /// A text chart of the quantity after each recorded change, one line
+    /// per change, with a bar as long as the quantity, scaled to `width`.
+    pub fn stock_chart(&self, width: usize) -> String {
+        let current = i64::from(self.quantity.unwrap_or(0));
+        let total: i64 = self.stock_changes.iter().map(|c| c.delta).sum();
+        let mut level = current - total;
+        let levels: Vec<i64> = self
+            .stock_changes
+            .iter()
+            .map(|c| {
+                level += c.delta;
+                level
+            })
+            .collect();
+        let max = levels.iter().copied().max().unwrap_or(0).max(1);
+        let mut out = String::new();
+        for (change, level) in self.stock_changes.iter().zip(levels) {
+            let bar = (level.max(0) as usize * width).div_ceil(max as usize);
+            out.push_str(&format!(
+                "{}  {:>+5}  {:>5}  {}\n",
+                change.at.date(),
+                change.delta,
+                level,
+                "#".repeat(bar)
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_round_trip() {
+        let at: Timestamp = "2024-05-01T08:30:05Z".parse().unwrap();
+        assert_eq!(at.to_string(), "2024-05-01T08:30:05Z");
+        assert_eq!(at.date().to_string(), "2024-05-01");
+        assert_eq!(Timestamp(0).to_string(), "1970-01-01T00:00:00Z");
+        for bad in ["2024-05-01", "2024-05-01T24:00:00Z", "2024-05-01T8:30:05Z"] {
+            assert!(bad.parse::<Timestamp>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn consuming_and_restocking_are_recorded() {
+        let day = |d: u64| Timestamp(d * SECONDS_PER_DAY);
+        let mut item = Item::builder().name("coffee").quantity(4).build();
+        assert_eq!(item.consume(3, day(0)), Ok(1));
+        assert_eq!(
+            item.consume(2, day(1)),
+            Err(NotEnough { have: 1, wanted: 2 })
+        );
+        assert_eq!(item.restock(5, day(2)), Ok(6));
+        assert_eq!(
+            item.restock(u32::MAX, day(3)),
+            Err(TooMany {
+                have: 6,
+                adding: u32::MAX
+            })
+        );
+        assert_eq!(item.restock(0, day(3)), Ok(6));
+        assert_eq!(item.consume(0, day(3)), Ok(6));
+        assert_eq!(item.consume(3, day(4)), Ok(3));
+        assert_eq!(item.quantity(), Some(3));
+        assert_eq!(
+            item.stock_changes()
+                .iter()
+                .map(|c| c.delta)
+                .collect::<Vec<_>>(),
+            [-3, 5, -3]
+        );
+        assert_eq!(item.daily_use(day(6)), Some(1.0));
+        assert_eq!(
+            item.stock_chart(6),
+            "1970-01-01     -3      1  #\n\
+             1970-01-03     +5      6  ######\n\
+             1970-01-05     -3      3  ###\n"
+        );
+        assert_eq!(Item::builder().name("new").build().daily_use(day(1)), None);
+    }
+}
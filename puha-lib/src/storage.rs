@@ -0,0 +1,628 @@
+//! Storage backends for persisting a space tree.
+//!
+//! The [`JsonFile`] backend keeps the whole tree in a single JSON document.
+//! The [`Dir`] backend mirrors the tree as directories, one `space.json` per
+//! space, so individual spaces can be loaded on their own and saving only
+//! touches the files whose contents actually changed. [`Stdio`] reads the
+//! tree from standard input and writes it to standard output, for pipelines.
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Space, limits, sniff, strict};
+
+/// File name holding a single space in the directory layout.
+pub const SPACE_FILE: &str = "space.json";
+
+/// Key listing the child directories of a space in the directory layout.
+const CHILDREN_KEY: &str = "children";
+
+/// A place a space tree can be loaded from and saved to.
+pub trait Storage {
+    fn load(&self) -> Result<Space, Box<dyn std::error::Error>>;
+
+    /// Load the tree, rejecting unknown fields and structural anomalies.
+    fn load_strict(&self) -> Result<Space, Box<dyn std::error::Error>>;
+
+    fn save(&self, space: &Space) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// How trees are written to disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveOptions {
+    /// Write indented, multi-line JSON instead of a single line.
+    pub pretty: bool,
+    /// Number of spaces per indentation level when `pretty` is set.
+    pub indent: usize,
+    /// Write object keys in alphabetical order, so the output only depends on
+    /// the data and not on field declaration order.
+    pub sort_keys: bool,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self {
+            pretty: true,
+            indent: 2,
+            sort_keys: false,
+        }
+    }
+}
+
+impl SaveOptions {
+    /// Single-line output.
+    pub fn compact() -> Self {
+        Self {
+            pretty: false,
+            ..Self::default()
+        }
+    }
+
+    /// Serialize `value` according to these options.
+    pub fn to_vec<T: Serialize + ?Sized>(&self, value: &T) -> serde_json::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        self.to_writer(&mut out, value)?;
+        Ok(out)
+    }
+
+    /// Serialize `value` according to these options into `writer`, as it
+    /// goes rather than building the whole text first.
+    pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(
+        &self,
+        writer: W,
+        value: &T,
+    ) -> serde_json::Result<()> {
+        if self.sort_keys {
+            // `serde_json::Map` keeps its keys sorted.
+            let value = serde_json::to_value(value)?;
+            return self.write(writer, &value);
+        }
+        self.write(writer, value)
+    }
+
+    fn write<W: io::Write, T: Serialize + ?Sized>(
+        &self,
+        writer: W,
+        value: &T,
+    ) -> serde_json::Result<()> {
+        if self.pretty {
+            let indent = " ".repeat(self.indent);
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            let mut serializer = serde_json::Serializer::with_formatter(writer, formatter);
+            value.serialize(&mut serializer)
+        } else {
+            serde_json::to_writer(writer, value)
+        }
+    }
+}
+
+/// Available storage backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// The whole tree in one JSON file.
+    #[default]
+    Json,
+    /// One directory per space, each holding a `space.json`.
+    Dir,
+}
+
+impl Backend {
+    /// Open a storage of this kind at `path`.
+    pub fn open(self, path: impl Into<PathBuf>) -> Box<dyn Storage> {
+        self.open_with(path, SaveOptions::default())
+    }
+
+    /// Open a storage of this kind at `path` that saves with `options`.
+    pub fn open_with(self, path: impl Into<PathBuf>, options: SaveOptions) -> Box<dyn Storage> {
+        match self {
+            Backend::Json => Box::new(JsonFile::new(path).with_options(options)),
+            Backend::Dir => Box::new(Dir::new(path).with_options(options)),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Json => f.write_str("json"),
+            Backend::Dir => f.write_str("dir"),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Backend::Json),
+            "dir" => Ok(Backend::Dir),
+            other => Err(format!("unknown backend '{other}' (expected json or dir)")),
+        }
+    }
+}
+
+/// Single-file JSON storage.
+#[derive(Debug, Clone)]
+pub struct JsonFile {
+    path: PathBuf,
+    options: SaveOptions,
+}
+
+impl JsonFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            options: SaveOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: SaveOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Storage for JsonFile {
+    fn load(&self) -> Result<Space, Box<dyn std::error::Error>> {
+        Space::from_file(&self.path)
+    }
+
+    fn load_strict(&self) -> Result<Space, Box<dyn std::error::Error>> {
+        Space::from_file_strict(&self.path)
+    }
+
+    fn save(&self, space: &Space) -> Result<(), Box<dyn std::error::Error>> {
+        space.save_to_file_with(&self.path, &self.options)
+    }
+}
+
+/// Storage on standard input and output.
+///
+/// Standard input is read once, at the first load; loading again parses
+/// the same text. Saving writes the whole tree to standard output.
+#[derive(Debug, Default)]
+pub struct Stdio {
+    options: SaveOptions,
+    input: OnceLock<String>,
+}
+
+impl Stdio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(mut self, options: SaveOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn input(&self) -> io::Result<&str> {
+        if let Some(input) = self.input.get() {
+            return Ok(input);
+        }
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        Ok(self.input.get_or_init(|| input))
+    }
+}
+
+impl Storage for Stdio {
+    fn load(&self) -> Result<Space, Box<dyn std::error::Error>> {
+        parse_input(self.input()?, false)
+    }
+
+    fn load_strict(&self) -> Result<Space, Box<dyn std::error::Error>> {
+        parse_input(self.input()?, true)
+    }
+
+    fn save(&self, space: &Space) -> Result<(), Box<dyn std::error::Error>> {
+        limits::check_depth(space)?;
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        space.save_to_writer(&mut out, &self.options)?;
+        writeln!(out)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Parse a tree read from standard input, with advice if it is not puha
+/// data at all.
+fn parse_input(input: &str, strict: bool) -> Result<Space, Box<dyn std::error::Error>> {
+    let data = sniff::strip_bom(input);
+    let parsed = if strict {
+        strict::parse(data)
+    } else {
+        serde_json::from_str(data)
+    };
+    let space = parsed.map_err(|e| match sniff::mistake(input) {
+        Some(advice) => format!("standard input: {advice}"),
+        None => format!("standard input: {e}"),
+    })?;
+    if strict {
+        strict::check_structure(&space)?;
+    }
+    Ok(space)
+}
+
+/// Directory-per-space storage.
+///
+/// The space at `path` is stored in `path/space.json`; each child space lives
+/// in a subdirectory named after it, recursively.
+#[derive(Debug, Clone)]
+pub struct Dir {
+    path: PathBuf,
+    options: SaveOptions,
+}
+
+impl Dir {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            options: SaveOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: SaveOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load only the subtree stored under `relative`, e.g. `kitchen/drawer`,
+    /// which must stay inside the directory.
+    pub fn load_subtree(
+        &self,
+        relative: impl AsRef<Path>,
+    ) -> Result<Space, Box<dyn std::error::Error>> {
+        let relative = relative.as_ref();
+        if !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(format!("{}: not a space directory", relative.display()).into());
+        }
+        load_dir(&self.path.join(relative), false)
+    }
+}
+
+impl Storage for Dir {
+    fn load(&self) -> Result<Space, Box<dyn std::error::Error>> {
+        load_dir(&self.path, false)
+    }
+
+    fn load_strict(&self) -> Result<Space, Box<dyn std::error::Error>> {
+        let space = load_dir(&self.path, true)?;
+        strict::check_structure(&space)?;
+        Ok(space)
+    }
+
+    fn save(&self, space: &Space) -> Result<(), Box<dyn std::error::Error>> {
+        limits::check_depth(space)?;
+        save_dir(&self.path, space, &self.options)
+    }
+}
+
+fn load_dir(dir: &Path, strict: bool) -> Result<Space, Box<dyn std::error::Error>> {
+    let file = dir.join(SPACE_FILE);
+    let data = std::fs::read_to_string(&file)?;
+    let data = sniff::strip_bom(&data);
+    if strict {
+        strict::check_dir_file(data).map_err(|e| format!("{}: {e}", file.display()))?;
+    }
+    let mut value: serde_json::Value = serde_json::from_str(data)?;
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| format!("{}: expected a JSON object", dir.display()))?;
+    let children = match object.remove(CHILDREN_KEY) {
+        Some(children) => serde_json::from_value::<Vec<String>>(children)?,
+        None => Vec::new(),
+    };
+    object.insert("spaces".into(), serde_json::Value::Array(Vec::new()));
+    let mut space: Space = serde_json::from_value(value)?;
+    for child in children {
+        // Only a name the backend could have written, so the file cannot
+        // send loading outside the directory.
+        if !is_dir_name(&child) {
+            return Err(format!("{}: bad child directory {child:?}", file.display()).into());
+        }
+        space.spaces.push(load_dir(&dir.join(child), strict)?);
+    }
+    Ok(space)
+}
+
+fn save_dir(
+    dir: &Path,
+    space: &Space,
+    options: &SaveOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut used = HashSet::new();
+    let children: Vec<String> = space
+        .spaces
+        .iter()
+        .map(|child| unique_dir_name(child.name(), &mut used))
+        .collect();
+
+    let mut value = serde_json::to_value(space)?;
+    let object = value
+        .as_object_mut()
+        .expect("space serializes to an object");
+    object.remove("spaces");
+    object.insert(CHILDREN_KEY.into(), serde_json::to_value(&children)?);
+    let json = options.to_vec(&value)?;
+    write_if_changed(&dir.join(SPACE_FILE), &json)?;
+
+    for (child, name) in space.spaces.iter().zip(&children) {
+        save_dir(&dir.join(name), child, options)?;
+    }
+    remove_stale_children(dir, &used)?;
+    Ok(())
+}
+
+/// Write `contents` to `path` unless the file already holds exactly that.
+fn write_if_changed(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Ok(existing) = std::fs::read(path)
+        && existing == contents
+    {
+        return Ok(());
+    }
+    write_atomically(path, |writer| writer.write_all(contents))
+}
+
+/// Write the file at `path` through a temporary file next to it that then
+/// replaces it, so a save cut short, such as by Ctrl-C or a full disk,
+/// leaves the old contents rather than a truncated file.
+pub fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut io::BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".tmp");
+    let temp = path.with_file_name(name);
+    let result = (|| {
+        let mut writer = io::BufWriter::new(File::create(&temp)?);
+        write(&mut writer)?;
+        writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?
+            .sync_all()?;
+        std::fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Remove child space directories left over from an earlier save. Other
+/// directories, such as ones whose `space.json` this backend did not write,
+/// are left alone.
+fn remove_stale_children(dir: &Path, used: &HashSet<String>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        if used.contains(&name) || !is_dir_name(&name) || !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let path = entry.path();
+        if is_space_file(&path.join(SPACE_FILE)) {
+            std::fs::remove_dir_all(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `name` could be a child directory name from [`unique_dir_name`]:
+/// a single path component not starting with a dot.
+fn is_dir_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\'])
+}
+
+/// Whether `file` is a space saved by this backend, which always lists the
+/// space's children.
+fn is_space_file(file: &Path) -> bool {
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|data| serde_json::from_str::<serde_json::Value>(sniff::strip_bom(&data)).ok())
+        .is_some_and(|value| value.get(CHILDREN_KEY).is_some_and(|c| c.is_array()))
+}
+
+/// Turn a space name into a directory name that is safe on common file
+/// systems and unique among its siblings (compared case-insensitively).
+fn unique_dir_name(name: &str, used: &mut HashSet<String>) -> String {
+    let mut base: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    base = base.trim().trim_start_matches('.').to_string();
+    if base.is_empty() {
+        base.push('_');
+    }
+
+    let mut candidate = base.clone();
+    let mut n = 2;
+    while !used.insert(candidate.to_lowercase()) {
+        candidate = format!("{base}-{n}");
+        n += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    fn sample() -> Space {
+        let drawer = Space::builder()
+            .name("drawer")
+            .push_item(Item::builder().name("fork").description("steel").build())
+            .build();
+        let kitchen = Space::builder().name("kitchen").push_space(drawer).build();
+        Space::builder()
+            .name("home")
+            .root(true)
+            .push_space(kitchen)
+            .push_space(Space::builder().name("A/B closet").build())
+            .build()
+    }
+
+    #[test]
+    fn dir_round_trip_and_partial_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Dir::new(dir.path());
+        let root = sample();
+        storage.save(&root).unwrap();
+
+        assert!(dir.path().join("kitchen/drawer/space.json").is_file());
+        assert!(dir.path().join("A_B closet/space.json").is_file());
+        assert_eq!(storage.load().unwrap(), root);
+
+        let drawer = storage.load_subtree("kitchen/drawer").unwrap();
+        assert_eq!(drawer.items()[0].name(), "fork");
+    }
+
+    #[test]
+    fn dir_save_removes_stale_spaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Dir::new(dir.path());
+        let mut root = sample();
+        storage.save(&root).unwrap();
+
+        root.remove_space("kitchen").unwrap();
+        storage.save(&root).unwrap();
+
+        assert!(!dir.path().join("kitchen").exists());
+        assert_eq!(storage.load().unwrap(), root);
+
+        let other = dir.path().join("notes");
+        std::fs::create_dir(&other).unwrap();
+        std::fs::write(other.join(SPACE_FILE), "{}").unwrap();
+        storage.save(&root).unwrap();
+        assert!(other.join(SPACE_FILE).is_file());
+    }
+
+    #[test]
+    fn dir_children_stay_inside_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Dir::new(dir.path().join("home"));
+        storage.save(&sample()).unwrap();
+
+        let file = dir.path().join("home").join(SPACE_FILE);
+        let data = std::fs::read_to_string(&file).unwrap();
+        std::fs::write(&file, data.replace("\"kitchen\"", "\"../kitchen\"")).unwrap();
+        let err = storage.load().unwrap_err().to_string();
+        assert!(err.contains("bad child directory"), "{err}");
+        assert!(storage.load_subtree("../home").is_err());
+    }
+
+    #[test]
+    fn dir_strict_load_rejects_unknown_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Dir::new(dir.path());
+        let mut root = sample();
+        root.repair_names();
+        storage.save(&root).unwrap();
+        assert!(storage.load_strict().is_ok());
+
+        let file = dir.path().join("kitchen").join(SPACE_FILE);
+        let data = std::fs::read_to_string(&file).unwrap();
+        std::fs::write(&file, data.replacen('{', "{\"colour\": 1,", 1)).unwrap();
+        let err = storage.load_strict().unwrap_err().to_string();
+        assert!(err.contains("unknown field `colour`"), "{err}");
+        assert!(storage.load().is_ok());
+    }
+
+    #[test]
+    fn save_options_control_layout() {
+        let root = Space::builder().name("home").root(true).build();
+
+        let compact = String::from_utf8(SaveOptions::compact().to_vec(&root).unwrap()).unwrap();
+        assert!(!compact.contains('\n'));
+
+        let sorted = SaveOptions {
+            indent: 4,
+            sort_keys: true,
+            ..SaveOptions::default()
+        };
+        let sorted = String::from_utf8(sorted.to_vec(&root).unwrap()).unwrap();
+        assert!(sorted.starts_with("{\n    \"items\""), "{sorted}");
+        let keys: Vec<&str> = sorted
+            .lines()
+            .filter_map(|line| line.trim().split('"').nth(1))
+            .collect();
+        let mut expected = keys.clone();
+        expected.sort();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn sibling_dir_names_are_unique() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_dir_name("Box", &mut used), "Box");
+        assert_eq!(unique_dir_name("box", &mut used), "box-2");
+        assert_eq!(unique_dir_name("..", &mut used), "_");
+    }
+
+    #[test]
+    fn failed_writes_leave_the_old_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("home.json");
+        std::fs::write(&path, "old").unwrap();
+
+        let err = write_atomically(&path, |writer| {
+            writer.write_all(b"half")?;
+            Err(io::Error::other("interrupted"))
+        });
+        assert!(err.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+
+        write_atomically(&path, |writer| writer.write_all(b"new")).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn standard_input_is_parsed_like_a_file() {
+        let root = Space::builder()
+            .name("home")
+            .root(true)
+            .push_item(Item::builder().name("lamp").build())
+            .build();
+        let data = SaveOptions::compact().to_vec(&root).unwrap();
+        let data = String::from_utf8(data).unwrap();
+        assert_eq!(parse_input(&data, true).unwrap(), root);
+
+        let unknown = data.replacen('{', "{\"colour\": \"red\", ", 1);
+        assert!(parse_input(&unknown, false).is_ok());
+        assert!(parse_input(&unknown, true).is_err());
+        let error = parse_input("name,description\n", false).unwrap_err();
+        assert!(error.to_string().contains("CSV"), "{error}");
+    }
+}
@@ -0,0 +1,282 @@
+//! Strict validation of data files.
+//!
+//! Normal loading is lenient: unknown fields are kept and the tree is taken
+//! as is. Strict loading instead rejects anything that does not look like a
+//! file written by this version of puha.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+
+use crate::Space;
+
+// Mirrors of the on-disk format that only accept known field names. Field
+// types are checked by the regular deserialization afterwards. Every field of
+// `Item` and `Space` must be listed here.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct StrictItem {
+    #[serde(default)]
+    id: IgnoredAny,
+    name: IgnoredAny,
+    description: IgnoredAny,
+    #[serde(default)]
+    metadata: IgnoredAny,
+    #[serde(default)]
+    tags: IgnoredAny,
+    #[serde(default)]
+    attachments: IgnoredAny,
+    #[serde(default)]
+    quantity: IgnoredAny,
+    #[serde(default)]
+    min_quantity: IgnoredAny,
+    #[serde(default)]
+    stock_changes: IgnoredAny,
+    #[serde(default)]
+    category: IgnoredAny,
+    #[serde(default)]
+    purchase_date: IgnoredAny,
+    #[serde(default)]
+    purchase_price: IgnoredAny,
+    #[serde(default)]
+    warranty_until: IgnoredAny,
+    #[serde(default)]
+    receipts: IgnoredAny,
+    #[serde(default)]
+    links: IgnoredAny,
+    #[serde(default)]
+    last_verified_at: IgnoredAny,
+    #[serde(default)]
+    missing_at: IgnoredAny,
+    #[serde(default)]
+    size: IgnoredAny,
+    #[serde(default)]
+    weight: IgnoredAny,
+    #[serde(default)]
+    icon: IgnoredAny,
+    #[serde(default)]
+    color: IgnoredAny,
+    #[serde(default)]
+    version: IgnoredAny,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct StrictSpace {
+    #[serde(default)]
+    id: IgnoredAny,
+    name: IgnoredAny,
+    items: Vec<StrictItem>,
+    spaces: Vec<StrictSpace>,
+    root: IgnoredAny,
+    #[serde(default)]
+    locked: IgnoredAny,
+    #[serde(default)]
+    icon: IgnoredAny,
+    #[serde(default)]
+    color: IgnoredAny,
+    #[serde(default)]
+    capacity: IgnoredAny,
+    #[serde(default)]
+    max_weight: IgnoredAny,
+    #[serde(default)]
+    version: IgnoredAny,
+    #[serde(default)]
+    settings: IgnoredAny,
+}
+
+/// A single space as stored by the directory backend, where child spaces are
+/// referenced by directory name.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct StrictDirSpace {
+    #[serde(default)]
+    id: IgnoredAny,
+    name: IgnoredAny,
+    items: Vec<StrictItem>,
+    root: IgnoredAny,
+    #[serde(default)]
+    locked: IgnoredAny,
+    #[serde(default)]
+    icon: IgnoredAny,
+    #[serde(default)]
+    color: IgnoredAny,
+    #[serde(default)]
+    capacity: IgnoredAny,
+    #[serde(default)]
+    max_weight: IgnoredAny,
+    #[serde(default)]
+    version: IgnoredAny,
+    #[serde(default)]
+    settings: IgnoredAny,
+    #[serde(default)]
+    children: IgnoredAny,
+}
+
+/// Structural problems found in an otherwise well-formed tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrictError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for StrictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "strict validation failed:")?;
+        for problem in &self.problems {
+            write!(f, "\n  {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StrictError {}
+
+/// Parse a whole tree, rejecting unknown fields with their line and column.
+pub(crate) fn parse(data: &str) -> Result<Space, serde_json::Error> {
+    serde_json::from_str::<StrictSpace>(data)?;
+    serde_json::from_str(data)
+}
+
+/// Check a single directory-backend `space.json` for unknown fields.
+pub(crate) fn check_dir_file(data: &str) -> Result<(), serde_json::Error> {
+    serde_json::from_str::<StrictDirSpace>(data).map(|_| ())
+}
+
+/// Check a loaded tree for invalid or duplicate names and misplaced root
+/// flags.
+pub fn check_structure(root: &Space) -> Result<(), StrictError> {
+    let mut problems = Vec::new();
+    if !root.root() {
+        problems.push(format!(
+            "'{}': top-level space is not marked as root",
+            root.name()
+        ));
+    }
+    let mut seen = HashSet::new();
+    walk(root, root.name(), true, &mut seen, &mut problems);
+    for issue in root.invalid_names() {
+        problems.push(format!("'{}': {}", issue.path, issue.error));
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(StrictError { problems })
+    }
+}
+
+fn walk<'a>(
+    space: &'a Space,
+    path: &str,
+    top: bool,
+    seen: &mut HashSet<&'a str>,
+    problems: &mut Vec<String>,
+) {
+    if !top && space.root() {
+        problems.push(format!("'{path}': nested space is marked as root"));
+    }
+    if !seen.insert(space.name()) {
+        problems.push(format!("'{path}': duplicate space name '{}'", space.name()));
+    }
+    let mut items = HashSet::new();
+    for item in space.items() {
+        if !items.insert(item.name()) {
+            problems.push(format!("'{path}': duplicate item name '{}'", item.name()));
+        }
+    }
+    for child in space.spaces() {
+        let child_path = format!("{path}/{}", child.name());
+        walk(child, &child_path, false, seen, problems);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn unknown_fields_report_position() {
+        let json = "{\n  \"name\": \"root\",\n  \"items\": [],\n  \"spaces\": [],\n  \"root\": true,\n  \"colour\": 1\n}";
+        let err = parse(json).unwrap_err();
+        assert!(err.to_string().contains("unknown field `colour`"));
+        assert_eq!(err.line(), 6);
+    }
+
+    #[test]
+    fn every_field_is_known_to_strict_parser() {
+        let mut item = Item::builder()
+            .id(uuid::Uuid::from_u128(1))
+            .name("pen")
+            .description("blue")
+            .metadata("brand", "Parker")
+            .tag("office")
+            .attachment("photos/pen.jpg")
+            .quantity(3)
+            .min_quantity(2)
+            .category("stationery")
+            .purchase_date("2024-05-01".parse().unwrap())
+            .purchase_price("12.50".parse().unwrap())
+            .warranty_until("2026-05-01".parse().unwrap())
+            .receipt("receipt.pdf")
+            .link(crate::LinkKind::PartOf, uuid::Uuid::from_u128(2))
+            .size("15x1x1".parse().unwrap())
+            .weight("20g".parse().unwrap())
+            .icon("🖊")
+            .color("blue")
+            .build();
+        item.set_name("pencil");
+        item.consume(1, crate::Timestamp(0)).unwrap();
+        item.verify(crate::Timestamp(0));
+        item.mark_missing(crate::Timestamp(1));
+        let mut root = Space::builder()
+            .name("root")
+            .root(true)
+            .push_item(item)
+            .push_space(
+                Space::builder()
+                    .id(uuid::Uuid::from_u128(2))
+                    .name("box")
+                    .locked(true)
+                    .icon("📦")
+                    .color("#aa5500")
+                    .capacity("40x30x20".parse().unwrap())
+                    .max_weight("5kg".parse().unwrap())
+                    .build(),
+            )
+            .build();
+        root.settings_mut().synonyms.add_line("torch = flashlight");
+        let json = serde_json::to_string(&root).unwrap();
+        assert_eq!(parse(&json).unwrap(), root);
+    }
+
+    #[test]
+    fn structure_problems_are_collected() {
+        let pen = Item::builder().name("pen").build();
+        let root = Space::builder()
+            .name("home")
+            .push_item(pen.clone())
+            .push_item(pen)
+            .push_item(Item::builder().name("a/b").build())
+            .push_space(Space::builder().name("box").root(true).build())
+            .push_space(Space::builder().name("box").build())
+            .build();
+
+        let err = check_structure(&root).unwrap_err();
+        assert_eq!(
+            err.problems,
+            vec![
+                "'home': top-level space is not marked as root",
+                "'home': duplicate item name 'pen'",
+                "'home/box': nested space is marked as root",
+                "'home/box': duplicate space name 'box'",
+                "'home/a/b': name 'a/b' must not contain '/'",
+            ]
+        );
+    }
+}
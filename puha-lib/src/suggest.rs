@@ -0,0 +1,156 @@
+//! Suggesting where to put a new item, judging by where items with similar
+//! names and tags already are.
+//!
+//! Every space with items is described by the words of its items' names and
+//! tags, weighted by TF-IDF: a word counts for more the more often it occurs
+//! in the space and the fewer spaces it occurs in. Spaces are ranked by the
+//! cosine similarity of their words to the new item's.
+
+use std::collections::HashMap;
+
+use crate::Space;
+use crate::search::{self, Target};
+
+/// A space to put an item in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// The space's location as `space/.../name`.
+    pub path: String,
+    /// How well the space fits, from 0 to 1.
+    pub score: f64,
+}
+
+/// Spaces under and including `root` for an item called `name` with `tags`,
+/// best first. Spaces sharing no word with the item are left out.
+pub fn suggest(root: &Space, name: &str, tags: &[&str]) -> Vec<Suggestion> {
+    let spaces: Vec<(String, HashMap<String, f64>)> = search::entries(root)
+        .into_iter()
+        .filter_map(|entry| match entry.target {
+            Target::Space(space) if !space.items().is_empty() => {
+                let mut counts = HashMap::new();
+                for item in space.items() {
+                    for word in words(item.name(), item.tags().iter().map(String::as_str)) {
+                        *counts.entry(word).or_insert(0.0) += 1.0;
+                    }
+                }
+                Some((entry.path_string(), counts))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut spaces_with: HashMap<&str, f64> = HashMap::new();
+    for (_, counts) in &spaces {
+        for word in counts.keys() {
+            *spaces_with.entry(word).or_insert(0.0) += 1.0;
+        }
+    }
+    // Smoothed, so a word every space has still counts a little.
+    let total = spaces.len() as f64;
+    let idf = |word: &str| {
+        ((1.0 + total) / (1.0 + spaces_with.get(word).copied().unwrap_or(0.0))).ln() + 1.0
+    };
+
+    let mut query: HashMap<String, f64> = HashMap::new();
+    for word in words(name, tags.iter().copied()) {
+        *query.entry(word).or_insert(0.0) += 1.0;
+    }
+    let query: HashMap<&str, f64> = query
+        .iter()
+        .map(|(word, count)| (word.as_str(), count * idf(word)))
+        .collect();
+    let query_norm = norm(query.values());
+
+    let mut suggestions: Vec<Suggestion> = spaces
+        .iter()
+        .filter_map(|(path, counts)| {
+            let weights: HashMap<&str, f64> = counts
+                .iter()
+                .map(|(word, count)| (word.as_str(), count * idf(word)))
+                .collect();
+            let dot: f64 = query
+                .iter()
+                .filter_map(|(word, weight)| Some(weight * weights.get(word)?))
+                .sum();
+            (dot > 0.0).then(|| Suggestion {
+                path: path.clone(),
+                score: dot / (query_norm * norm(weights.values())),
+            })
+        })
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    suggestions
+}
+
+/// The lowercase words of a name and its tags.
+fn words<'a>(name: &'a str, tags: impl Iterator<Item = &'a str>) -> Vec<String> {
+    std::iter::once(name)
+        .chain(tags)
+        .flat_map(search::tokenize)
+        .map(|token| token.text)
+        .collect()
+}
+
+fn norm<'a>(weights: impl Iterator<Item = &'a f64>) -> f64 {
+    weights.map(|w| w * w).sum::<f64>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    fn space(name: &str, items: &[(&str, &[&str])]) -> Space {
+        let mut builder = Space::builder().name(name);
+        for (item, tags) in items {
+            let item = tags
+                .iter()
+                .fold(Item::builder().name(*item), |item, tag| item.tag(*tag));
+            builder = builder.push_item(item.build());
+        }
+        builder.build()
+    }
+
+    fn home() -> Space {
+        let mut root = space("home", &[]);
+        root.add_space(space(
+            "office",
+            &[
+                ("USB-C cable", &["cable"]),
+                ("HDMI cable", &[]),
+                ("mouse", &[]),
+            ],
+        ));
+        root.add_space(space(
+            "kitchen",
+            &[("bread knife", &[]), ("cable tie", &[])],
+        ));
+        root.add_space(space("shed", &[("drill", &["tools"]), ("saw", &["tools"])]));
+        root
+    }
+
+    #[test]
+    fn ranks_spaces_by_shared_words() {
+        let paths = |suggestions: Vec<Suggestion>| -> Vec<String> {
+            suggestions.into_iter().map(|s| s.path).collect()
+        };
+        assert_eq!(
+            paths(suggest(&home(), "Lightning cable", &[])),
+            ["home/office", "home/kitchen"]
+        );
+        assert_eq!(paths(suggest(&home(), "hammer", &["tools"])), ["home/shed"]);
+        assert!(suggest(&home(), "sofa", &[]).is_empty());
+    }
+
+    #[test]
+    fn scores_an_identical_space_highest() {
+        let root = space("box", &[("lamp", &[])]);
+        let suggestions = suggest(&root, "lamp", &[]);
+        assert_eq!(suggestions.len(), 1);
+        assert!((suggestions[0].score - 1.0).abs() < 1e-9);
+    }
+}
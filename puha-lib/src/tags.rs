@@ -0,0 +1,149 @@
+//! Counting tags and changing the tags of many items at once.
+
+use std::collections::BTreeMap;
+
+use crate::query::Query;
+use crate::{Space, path};
+
+/// How many items under `space` carry each tag.
+pub fn counts(space: &Space) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    count_below(space, &mut counts);
+    counts
+}
+
+fn count_below(space: &Space, counts: &mut BTreeMap<String, usize>) {
+    for tag in space.items.iter().flat_map(|item| &item.tags) {
+        *counts.entry(tag.clone()).or_default() += 1;
+    }
+    for child in &space.spaces {
+        count_below(child, counts);
+    }
+}
+
+/// Replace tag `from` with `to` on every item under `space`, returning how
+/// many items had `from`. Items that already have both just lose `from`.
+pub fn rename(space: &mut Space, from: &str, to: &str) -> usize {
+    let mut renamed = 0;
+    for item in &mut space.items {
+        if item.remove_tag(from) {
+            item.add_tag(to);
+            renamed += 1;
+        }
+    }
+    for child in &mut space.spaces {
+        renamed += rename(child, from, to);
+    }
+    renamed
+}
+
+/// Add the tags in `add` to, and remove those in `remove` from, every item
+/// under `space` that matches `query`. Returns the paths of the items whose
+/// tags changed, starting at `space`.
+pub fn retag(space: &mut Space, query: &Query, add: &[&str], remove: &[&str]) -> Vec<String> {
+    let mut changed = Vec::new();
+    retag_below(space, query, add, remove, &mut Vec::new(), &mut changed);
+    changed
+}
+
+fn retag_below(
+    space: &mut Space,
+    query: &Query,
+    add: &[&str],
+    remove: &[&str],
+    parents: &mut Vec<String>,
+    changed: &mut Vec<String>,
+) {
+    parents.push(space.name.clone());
+    let segments: Vec<&str> = parents.iter().map(String::as_str).collect();
+    for item in space
+        .items
+        .iter_mut()
+        .filter(|item| query.matches_item(item))
+    {
+        let mut any = false;
+        for tag in add {
+            any |= item.add_tag(*tag);
+        }
+        for tag in remove {
+            any |= item.remove_tag(tag);
+        }
+        if any {
+            changed.push(path::join(&segments, &item.name));
+        }
+    }
+    for child in &mut space.spaces {
+        retag_below(child, query, add, remove, parents, changed);
+    }
+    parents.pop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn retags_matching_items_only() {
+        let mut root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("vase").tag("misc").build())
+            .push_space(
+                Space::builder()
+                    .name("shelf")
+                    .push_item(Item::builder().name("glass vase").tag("fragile").build())
+                    .push_item(Item::builder().name("book").tag("misc").build())
+                    .build(),
+            )
+            .build();
+        let query = Query::parse("name:vase").unwrap();
+        let changed = retag(&mut root, &query, &["fragile"], &["misc"]);
+        // The glass vase already has the tag and nothing to remove.
+        assert_eq!(changed, ["home/vase"]);
+        let tags = |item: &Item| -> Vec<String> { item.tags().iter().cloned().collect() };
+        assert_eq!(tags(&root.items()[0]), ["fragile"]);
+        assert_eq!(tags(&root.spaces()[0].items()[1]), ["misc"]);
+    }
+
+    #[test]
+    fn tag_filters_select_the_tags_they_change() {
+        let mut root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("vase").tag("Misc").build())
+            .push_item(Item::builder().name("book").tag("misc").build())
+            .build();
+        let query = Query::parse("tag:misc").unwrap();
+        assert_eq!(retag(&mut root, &query, &[], &["misc"]), ["home/book"]);
+        assert_eq!(root.items()[0].tags().len(), 1);
+        assert!(root.items()[1].tags().is_empty());
+    }
+
+    #[test]
+    fn counts_and_renames_across_the_tree() {
+        let mut root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("cup").tag("kitchen").build())
+            .push_space(
+                Space::builder()
+                    .name("cupboard")
+                    .push_item(Item::builder().name("pan").tag("Kitchen").build())
+                    .push_item(
+                        Item::builder()
+                            .name("pot")
+                            .tag("kitchen")
+                            .tag("Kitchen")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let counts = |root: &Space| -> Vec<(String, usize)> { counts(root).into_iter().collect() };
+        assert_eq!(
+            counts(&root),
+            [("Kitchen".to_string(), 2), ("kitchen".to_string(), 2)]
+        );
+        assert_eq!(rename(&mut root, "Kitchen", "kitchen"), 2);
+        assert_eq!(counts(&root), [("kitchen".to_string(), 3)]);
+        assert_eq!(rename(&mut root, "Kitchen", "kitchen"), 0);
+    }
+}
@@ -0,0 +1,314 @@
+//! Output templates such as `{path}\t{name}`, for shaping command output.
+//!
+//! Placeholders:
+//!
+//! - `{path}`: full path of the entity,
+//! - `{parent}`: path of the space containing it,
+//! - `{name}`, `{description}`, `{kind}` (`item` or `space`),
+//! - `{qty}` and `{min_qty}`: the item's quantity and the minimum to keep,
+//! - `{tags}`: the item's tags, separated by commas,
+//! - `{category}`, `{purchased}` and `{price}`: the item's category,
+//!   purchase date and purchase price,
+//! - `{warranty}`: the last day the item is under warranty,
+//! - `{verified}`: when an audit last found the item,
+//! - `{size}` and `{weight}`: the item's outer dimensions and weight,
+//! - `{meta.KEY}`: the item's metadata value for `KEY`.
+//!
+//! `\t`, `\n`, `\0` and `\\` are expanded, and `{{`/`}}` produce literal
+//! braces. Placeholders that do not apply to an entity render as empty text.
+
+use std::fmt;
+
+use crate::path;
+use crate::search::{Entry, Target};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Placeholder {
+    Path,
+    Parent,
+    Name,
+    Description,
+    Kind,
+    Quantity,
+    MinQuantity,
+    Tags,
+    Category,
+    Purchased,
+    Price,
+    Warranty,
+    Verified,
+    Size,
+    Weight,
+    Meta(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Placeholder),
+}
+
+/// Returned when a template string is malformed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid template: {}", self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// A parsed output template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(input: &str) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('0') => literal.push('\0'),
+                    Some('\\') => literal.push('\\'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(TemplateError {
+                                    message: format!("unclosed placeholder '{{{name}'"),
+                                });
+                            }
+                        }
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(parse_placeholder(&name)?));
+                }
+                '}' => {
+                    return Err(TemplateError {
+                        message: "unmatched '}' (write '}}' for a literal brace)".into(),
+                    });
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Render the template for one entity.
+    pub fn render(&self, entry: &Entry) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Field(field) => match field {
+                    Placeholder::Path => {
+                        entry.write_path(&mut out).expect("writing to a String");
+                    }
+                    Placeholder::Parent => {
+                        if let Some((last, parents)) = entry.path.split_last() {
+                            path::write_joined(&mut out, parents, last)
+                                .expect("writing to a String");
+                        }
+                    }
+                    Placeholder::Name => out.push_str(entry.target.name()),
+                    Placeholder::Description => {
+                        if let Target::Item(item) = entry.target {
+                            out.push_str(item.description());
+                        }
+                    }
+                    Placeholder::Kind => out.push_str(match entry.target {
+                        Target::Item(_) => "item",
+                        Target::Space(_) => "space",
+                    }),
+                    Placeholder::Quantity => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(quantity) = item.quantity()
+                        {
+                            out.push_str(&quantity.to_string());
+                        }
+                    }
+                    Placeholder::MinQuantity => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(min_quantity) = item.min_quantity()
+                        {
+                            out.push_str(&min_quantity.to_string());
+                        }
+                    }
+                    Placeholder::Tags => {
+                        if let Target::Item(item) = entry.target {
+                            let tags: Vec<&str> = item.tags().iter().map(String::as_str).collect();
+                            out.push_str(&tags.join(","));
+                        }
+                    }
+                    Placeholder::Category => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(category) = item.category()
+                        {
+                            out.push_str(category);
+                        }
+                    }
+                    Placeholder::Purchased => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(date) = item.purchase_date()
+                        {
+                            out.push_str(&date.to_string());
+                        }
+                    }
+                    Placeholder::Price => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(price) = item.purchase_price()
+                        {
+                            out.push_str(&price.to_string());
+                        }
+                    }
+                    Placeholder::Warranty => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(date) = item.warranty_until()
+                        {
+                            out.push_str(&date.to_string());
+                        }
+                    }
+                    Placeholder::Verified => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(at) = item.last_verified_at()
+                        {
+                            out.push_str(&at.to_string());
+                        }
+                    }
+                    Placeholder::Size => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(size) = item.size()
+                        {
+                            out.push_str(&size.to_string());
+                        }
+                    }
+                    Placeholder::Weight => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(weight) = item.weight()
+                        {
+                            out.push_str(&weight.to_string());
+                        }
+                    }
+                    Placeholder::Meta(key) => {
+                        if let Target::Item(item) = entry.target
+                            && let Some(value) = item.metadata().get(key)
+                        {
+                            out.push_str(value);
+                        }
+                    }
+                },
+            }
+        }
+        out
+    }
+}
+
+impl std::str::FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn parse_placeholder(name: &str) -> Result<Placeholder, TemplateError> {
+    Ok(match name {
+        "path" => Placeholder::Path,
+        "parent" => Placeholder::Parent,
+        "name" => Placeholder::Name,
+        "description" => Placeholder::Description,
+        "kind" => Placeholder::Kind,
+        "qty" => Placeholder::Quantity,
+        "min_qty" => Placeholder::MinQuantity,
+        "tags" => Placeholder::Tags,
+        "category" => Placeholder::Category,
+        "purchased" => Placeholder::Purchased,
+        "price" => Placeholder::Price,
+        "warranty" => Placeholder::Warranty,
+        "verified" => Placeholder::Verified,
+        "size" => Placeholder::Size,
+        "weight" => Placeholder::Weight,
+        _ => match name.strip_prefix("meta.") {
+            Some(key) if !key.is_empty() => Placeholder::Meta(key.to_string()),
+            _ => {
+                return Err(TemplateError {
+                    message: format!("unknown placeholder '{{{name}}}'"),
+                });
+            }
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::entries;
+    use crate::{Item, Space};
+
+    #[test]
+    fn renders_placeholders_and_escapes() {
+        let root = Space::builder()
+            .name("home")
+            .push_item(
+                Item::builder()
+                    .name("drill")
+                    .description("cordless")
+                    .metadata("serial", "X1")
+                    .quantity(2)
+                    .build(),
+            )
+            .build();
+        let all = entries(&root);
+        let template =
+            Template::parse(r"{kind}\t{path}\t{parent}\t{description}\t{meta.serial}\t{qty}{{x}}")
+                .unwrap();
+
+        assert_eq!(template.render(&all[0]), "space\thome\t\t\t\t{x}");
+        assert_eq!(
+            template.render(&all[1]),
+            "item\thome/drill\thome\tcordless\tX1\t2{x}"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        assert_eq!(
+            Template::parse("{colour}").unwrap_err().message,
+            "unknown placeholder '{colour}'"
+        );
+        assert!(Template::parse("{name").is_err());
+        assert!(Template::parse("name}").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Handing items over to another inventory, such as when someone moves out
+//! and takes their things along.
+//!
+//! Moving the entities between the files is up to the caller; this module
+//! picks them out, marks their new owner and describes the move in a
+//! [`Manifest`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::query::Query;
+use crate::stock::Timestamp;
+use crate::{Item, Space, path};
+
+/// Metadata key holding the owner of transferred items.
+pub const OWNER: &str = "owner";
+
+/// A record of one transfer, kept next to the inventory it went to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub at: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// The inventory the entities came from.
+    pub from: String,
+    /// The inventory they went to.
+    pub to: String,
+    /// Paths the spaces had in `from`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spaces: Vec<String>,
+    /// Paths the items had in `from`, not counting those in `spaces`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<String>,
+}
+
+/// Remove the items under `space` that match `query`, returning them with
+/// their paths, starting at `space`.
+pub fn take_items(space: &mut Space, query: &Query) -> Vec<(String, Item)> {
+    let mut taken = Vec::new();
+    take(space, query, &mut Vec::new(), &mut taken);
+    taken
+}
+
+fn take(
+    space: &mut Space,
+    query: &Query,
+    parents: &mut Vec<String>,
+    taken: &mut Vec<(String, Item)>,
+) {
+    parents.push(space.name.clone());
+    let (matching, kept): (Vec<Item>, Vec<Item>) = std::mem::take(&mut space.items)
+        .into_iter()
+        .partition(|item| query.matches_item(item));
+    space.items = kept;
+    if !matching.is_empty() {
+        space.version += 1;
+    }
+    let segments: Vec<&str> = parents.iter().map(String::as_str).collect();
+    taken.extend(
+        matching
+            .into_iter()
+            .map(|item| (path::join(&segments, &item.name), item)),
+    );
+    for child in &mut space.spaces {
+        take(child, query, parents, taken);
+    }
+    parents.pop();
+}
+
+/// Record `owner` on every item under `space`.
+pub fn set_owner(space: &mut Space, owner: &str) {
+    for item in &mut space.items {
+        item.set_metadata(OWNER, owner);
+    }
+    for child in &mut space.spaces {
+        set_owner(child, owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> Space {
+        Space::builder()
+            .name("home")
+            .push_item(
+                Item::builder()
+                    .name("guitar")
+                    .description("alice's")
+                    .build(),
+            )
+            .push_space(
+                Space::builder()
+                    .name("closet")
+                    .push_item(Item::builder().name("coat").description("alice's").build())
+                    .push_item(Item::builder().name("boots").description("bob's").build())
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn takes_matching_items_with_their_paths() {
+        let mut root = home();
+        let taken = take_items(&mut root, &"desc:alice".parse().unwrap());
+        let paths: Vec<&str> = taken.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["home/guitar", "home/closet/coat"]);
+        assert!(root.items().is_empty());
+        assert_eq!(root.spaces()[0].items()[0].name(), "boots");
+    }
+
+    #[test]
+    fn marks_the_owner_everywhere_below() {
+        let mut root = home();
+        set_owner(&mut root, "alice");
+        let coat = &root.spaces()[0].items()[0];
+        assert_eq!(
+            coat.metadata().get(OWNER).map(String::as_str),
+            Some("alice")
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! What items are worth: their purchase prices, depreciated over time
+//! according to their category.
+//!
+//! Prices are kept in hundredths, so sums are exact, and carry no currency;
+//! an inventory is expected to use one.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::search::{self, Target};
+use crate::shopping::csv_field;
+use crate::{Item, Space};
+
+/// Average length of a year in days, leap years included.
+const DAYS_PER_YEAR: f64 = 365.2425;
+
+/// Text that is not a valid date or price.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueError {
+    pub message: String,
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+fn error(message: String) -> ValueError {
+    ValueError { message }
+}
+
+/// A calendar date, written `YYYY-MM-DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "String", into = "String")]
+pub struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u32, day: u32) -> Result<Self, ValueError> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(error(format!(
+                "{year:04}-{month:02}-{day:02} is not a date"
+            )));
+        }
+        Ok(Self { year, month, day })
+    }
+
+    pub fn year(self) -> i32 {
+        self.year
+    }
+
+    pub fn month(self) -> u32 {
+        self.month
+    }
+
+    pub fn day(self) -> u32 {
+        self.day
+    }
+
+    /// Today in UTC.
+    pub fn today() -> Self {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self::from_days((seconds / 86_400) as i64)
+    }
+
+    /// Days from `self` to `later`, negative if `later` is earlier.
+    pub fn days_until(self, later: Date) -> i64 {
+        later.days() - self.days()
+    }
+
+    /// Days since 1970-01-01.
+    pub(crate) fn days(self) -> i64 {
+        // Howard Hinnant's days_from_civil, with years starting in March.
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    pub(crate) fn from_days(days: i64) -> Self {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (year_of_era + era * 400) as i32 + i32::from(month <= 2);
+        Self { year, month, day }
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl FromStr for Date {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || error(format!("invalid date '{s}'; expected YYYY-MM-DD"));
+        let mut parts = s.splitn(3, '-');
+        let mut next = |len: usize| {
+            parts
+                .next()
+                .filter(|p| p.len() == len && p.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|p| p.parse().ok())
+                .ok_or_else(invalid)
+        };
+        let (year, month, day) = (next(4)?, next(2)?, next(2)?);
+        Date::new(year as i32, month, day)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl TryFrom<String> for Date {
+    type Error = ValueError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Date> for String {
+    fn from(date: Date) -> Self {
+        date.to_string()
+    }
+}
+
+/// An amount of money, in hundredths, written like `249.90`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "String", into = "String")]
+pub struct Price(pub u64);
+
+impl FromStr for Price {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || error(format!("invalid price '{s}'; expected e.g. 249 or 249.90"));
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let digits = |p: &str| p.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty() || !digits(whole) || !digits(fraction) || fraction.len() > 2 {
+            return Err(invalid());
+        }
+        let whole: u64 = whole.parse().map_err(|_| invalid())?;
+        let fraction: u64 = format!("{fraction:0<2}").parse().map_err(|_| invalid())?;
+        whole
+            .checked_mul(100)
+            .and_then(|w| w.checked_add(fraction))
+            .map(Price)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}", self.0 / 100, self.0 % 100)
+    }
+}
+
+impl TryFrom<String> for Price {
+    type Error = ValueError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Price> for String {
+    fn from(price: Price) -> Self {
+        price.to_string()
+    }
+}
+
+/// How an item loses value as it ages.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[non_exhaustive]
+pub enum Depreciation {
+    /// Loses the same amount every year, and all of it after `years`.
+    Linear { years: f64 },
+    /// Loses `rate`, a fraction such as 0.2, of what is left every year.
+    Declining { rate: f64 },
+}
+
+impl Depreciation {
+    /// What `price` is worth after `years`.
+    pub fn apply(&self, price: Price, years: f64) -> Price {
+        let years = years.max(0.0);
+        let left = match *self {
+            Depreciation::Linear { years: life } if life > 0.0 => (1.0 - years / life).max(0.0),
+            Depreciation::Linear { .. } => 0.0,
+            Depreciation::Declining { rate } => (1.0 - rate.clamp(0.0, 1.0)).powf(years),
+        };
+        Price((price.0 as f64 * left).round() as u64)
+    }
+}
+
+/// An item with a purchase price and what it is worth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemValue {
+    pub path: String,
+    pub category: Option<String>,
+    pub purchased: Option<Date>,
+    pub price: Price,
+    /// The price depreciated to the valuation date. Items without a purchase
+    /// date, or whose category has no schedule, keep their price.
+    pub current: Price,
+    /// The item's receipts, as stored in its [`receipts`](crate::Item::receipts).
+    pub receipts: Vec<String>,
+}
+
+impl Item {
+    /// What the item is worth on `date`: its purchase price depreciated by
+    /// the schedule for its category in `schedules`. Items without a
+    /// purchase date, or whose category has no schedule, keep their price.
+    pub fn current_value(
+        &self,
+        schedules: &BTreeMap<String, Depreciation>,
+        date: Date,
+    ) -> Option<Price> {
+        let price = self.purchase_price?;
+        let schedule = self.category.as_ref().and_then(|c| schedules.get(c));
+        Some(match (schedule, self.purchase_date) {
+            (Some(schedule), Some(purchased)) => {
+                let years = purchased.days_until(date) as f64 / DAYS_PER_YEAR;
+                schedule.apply(price, years)
+            }
+            _ => price,
+        })
+    }
+}
+
+/// The items under a space that have a purchase price, and their worth.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Valuation {
+    pub items: Vec<ItemValue>,
+}
+
+impl Valuation {
+    /// Value the items under `root` on `date`, depreciating each by the
+    /// schedule for its category in `schedules`.
+    pub fn of(root: &Space, schedules: &BTreeMap<String, Depreciation>, date: Date) -> Self {
+        let mut items = Vec::new();
+        for entry in search::entries(root) {
+            let Target::Item(item) = entry.target else {
+                continue;
+            };
+            let (Some(price), Some(current)) =
+                (item.purchase_price, item.current_value(schedules, date))
+            else {
+                continue;
+            };
+            items.push(ItemValue {
+                path: entry.path_string(),
+                category: item.category.clone(),
+                purchased: item.purchase_date,
+                price,
+                current,
+                receipts: item.receipts.clone(),
+            });
+        }
+        Self { items }
+    }
+
+    /// What the items cost together, saturating at `u64::MAX` cents.
+    pub fn total_price(&self) -> Price {
+        Price(
+            self.items
+                .iter()
+                .fold(0, |total, item| total.saturating_add(item.price.0)),
+        )
+    }
+
+    /// What the items are worth together, saturating at `u64::MAX` cents.
+    pub fn total_current(&self) -> Price {
+        Price(
+            self.items
+                .iter()
+                .fold(0, |total, item| total.saturating_add(item.current.0)),
+        )
+    }
+
+    /// CSV with a header row and a row per item.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("path,category,purchased,price,current\n");
+        for item in &self.items {
+            out.push_str(&format!(
+                "{},{},{},{},{}\n",
+                csv_field(&item.path),
+                csv_field(item.category.as_deref().unwrap_or("")),
+                item.purchased.map(|d| d.to_string()).unwrap_or_default(),
+                item.price,
+                item.current
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn dates_parse_and_count_days() {
+        let date: Date = "2024-02-29".parse().unwrap();
+        assert_eq!(date.to_string(), "2024-02-29");
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("2023-1-01".parse::<Date>().is_err());
+        assert!("yesterday".parse::<Date>().is_err());
+        assert_eq!(Date::from_days(0), Date::new(1970, 1, 1).unwrap());
+        assert_eq!(Date::from_days(date.days()), date);
+        assert_eq!(date.days_until("2025-03-01".parse().unwrap()), 366);
+    }
+
+    #[test]
+    fn prices_are_exact_hundredths() {
+        assert_eq!("249.9".parse(), Ok(Price(24_990)));
+        assert_eq!("12".parse(), Ok(Price(1_200)));
+        assert_eq!(Price(5).to_string(), "0.05");
+        for bad in ["", ".5", "1.234", "-3", "1,50"] {
+            assert!(bad.parse::<Price>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn depreciates_by_category() {
+        let root = Space::builder()
+            .name("home")
+            .push_item(
+                Item::builder()
+                    .name("laptop")
+                    .category("electronics")
+                    .purchase_date("2020-01-01".parse().unwrap())
+                    .purchase_price(Price(100_000))
+                    .build(),
+            )
+            .push_item(
+                Item::builder()
+                    .name("sofa")
+                    .category("furniture")
+                    .purchase_date("2020-01-01".parse().unwrap())
+                    .purchase_price(Price(50_000))
+                    .build(),
+            )
+            .push_item(
+                Item::builder()
+                    .name("painting")
+                    .purchase_price(Price(30_000))
+                    .build(),
+            )
+            .push_item(Item::builder().name("mug").build())
+            .build();
+        let schedules = BTreeMap::from([
+            (
+                "electronics".to_string(),
+                Depreciation::Linear { years: 4.0 },
+            ),
+            (
+                "furniture".to_string(),
+                Depreciation::Declining { rate: 0.5 },
+            ),
+        ]);
+
+        let valuation = Valuation::of(&root, &schedules, "2022-01-01".parse().unwrap());
+        let current: Vec<u64> = valuation.items.iter().map(|i| i.current.0).collect();
+        assert_eq!(current, [49_965, 12_488, 30_000]);
+        assert_eq!(valuation.total_price(), Price(180_000));
+
+        let later = Valuation::of(&root, &schedules, "2030-01-01".parse().unwrap());
+        assert_eq!(later.items[0].current, Price(0));
+        assert_eq!(
+            later.to_csv(),
+            "path,category,purchased,price,current\n\
+             home/laptop,electronics,2020-01-01,1000.00,0.00\n\
+             home/sofa,furniture,2020-01-01,500.00,0.49\n\
+             home/painting,,,300.00,300.00\n"
+        );
+    }
+
+    #[test]
+    fn totals_saturate() {
+        let item = |name: &str| {
+            Item::builder()
+                .name(name)
+                .purchase_price(Price(u64::MAX - 1))
+                .build()
+        };
+        let root = Space::builder()
+            .name("vault")
+            .push_item(item("gold"))
+            .push_item(item("gems"))
+            .build();
+        let valuation = Valuation::of(&root, &BTreeMap::new(), "2022-01-01".parse().unwrap());
+        assert_eq!(valuation.total_price(), Price(u64::MAX));
+        assert_eq!(valuation.total_current(), Price(u64::MAX));
+    }
+}
@@ -0,0 +1,185 @@
+//! Views: named groupings of items besides where they are kept, such as
+//! "by project" or "by owner".
+//!
+//! A view is a tree of groups holding items by id, so the same item can be
+//! in any number of views, and in several groups of one view. Views are
+//! stored in the root's [`Settings`](crate::Settings).
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::search::{self, Target};
+use crate::{Item, Space};
+
+/// Views by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct Views {
+    views: BTreeMap<String, Group>,
+}
+
+/// A group in a view, with items and groups of its own.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Group {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    items: Vec<Uuid>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    groups: BTreeMap<String, Group>,
+}
+
+impl Group {
+    /// Ids of the items directly in the group, in the order added.
+    pub fn items(&self) -> &[Uuid] {
+        &self.items
+    }
+
+    /// The group's groups by name.
+    pub fn groups(&self) -> &BTreeMap<String, Group> {
+        &self.groups
+    }
+
+    /// How many levels of groups are below this one.
+    pub fn depth(&self) -> usize {
+        self.groups
+            .values()
+            .map(|group| group.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Remove `id` from this group and those below it, returning from how
+    /// many it was removed.
+    fn remove(&mut self, id: Uuid) -> usize {
+        let before = self.items.len();
+        self.items.retain(|item| *item != id);
+        let here = before - self.items.len();
+        here + self
+            .groups
+            .values_mut()
+            .map(|g| g.remove(id))
+            .sum::<usize>()
+    }
+}
+
+impl Views {
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+
+    /// The views' names, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.views.keys().map(String::as_str)
+    }
+
+    /// The top group of the view called `name`.
+    pub fn get(&self, name: &str) -> Option<&Group> {
+        self.views.get(name)
+    }
+
+    /// Create an empty view, returning whether there was none by that name.
+    pub fn create(&mut self, name: &str) -> bool {
+        if self.views.contains_key(name) {
+            return false;
+        }
+        self.views.insert(name.to_string(), Group::default());
+        true
+    }
+
+    /// Delete a view, returning whether there was one by that name.
+    pub fn delete(&mut self, name: &str) -> bool {
+        self.views.remove(name).is_some()
+    }
+
+    /// Add the item with id `item` to the group at `path` in the view
+    /// called `name`, creating the groups on the way. An empty `path` is the
+    /// view's top group. `None` if there is no such view, otherwise whether
+    /// the item was not in the group yet.
+    pub fn add<S: AsRef<str>>(&mut self, name: &str, path: &[S], item: Uuid) -> Option<bool> {
+        let mut group = self.views.get_mut(name)?;
+        for segment in path {
+            group = group
+                .groups
+                .entry(segment.as_ref().to_string())
+                .or_default();
+        }
+        if group.items.contains(&item) {
+            return Some(false);
+        }
+        group.items.push(item);
+        Some(true)
+    }
+
+    /// Remove the item with id `item` from every group of the view called
+    /// `name`. `None` if there is no such view, otherwise from how many
+    /// groups it was removed.
+    pub fn remove(&mut self, name: &str, item: Uuid) -> Option<usize> {
+        Some(self.views.get_mut(name)?.remove(item))
+    }
+}
+
+/// Items by id, to look up the items of a view.
+pub type ItemsById<'a> = BTreeMap<Uuid, &'a Item>;
+
+/// The items under `root` that have an id.
+pub fn items_by_id(root: &Space) -> ItemsById<'_> {
+    search::entries(root)
+        .into_iter()
+        .filter_map(|entry| match entry.target {
+            Target::Item(item) => Some((item.id?, item)),
+            Target::Space(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_are_grouped_by_path() {
+        let (drill, saw) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let mut views = Views::default();
+        assert!(views.create("by project"));
+        assert!(!views.create("by project"));
+        assert_eq!(
+            views.add("by project", &["kitchen", "tools"], drill),
+            Some(true)
+        );
+        assert_eq!(
+            views.add("by project", &["kitchen", "tools"], drill),
+            Some(false)
+        );
+        assert_eq!(views.add("by project", &["shed"], drill), Some(true));
+        assert_eq!(views.add::<&str>("by project", &[], saw), Some(true));
+        assert_eq!(views.add("by owner", &["me"], saw), None);
+
+        let view = views.get("by project").unwrap();
+        assert_eq!(view.items(), [saw]);
+        assert_eq!(view.groups()["kitchen"].groups()["tools"].items(), [drill]);
+        assert_eq!(views.remove("by project", drill), Some(2));
+        assert!(
+            views.get("by project").unwrap().groups()["shed"]
+                .items()
+                .is_empty()
+        );
+        assert!(views.delete("by project"));
+        assert!(views.is_empty());
+    }
+
+    #[test]
+    fn looks_up_items_by_id() {
+        let id = Uuid::from_u128(1);
+        let root = Space::builder()
+            .name("home")
+            .push_item(Item::builder().name("drill").id(id).build())
+            .push_item(Item::builder().name("saw").build())
+            .build();
+        let items = items_by_id(&root);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[&id].name(), "drill");
+    }
+}
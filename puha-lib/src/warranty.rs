@@ -0,0 +1,84 @@
+//! Warranties: which items are still covered, and which are about to lapse.
+
+use crate::Space;
+use crate::search::{self, Target};
+use crate::value::Date;
+
+/// An item under warranty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warranty {
+    pub path: String,
+    pub until: Date,
+    /// Days from the day asked about to the last day of the warranty.
+    pub days_left: i64,
+    /// How many receipts are linked to the item.
+    pub receipts: usize,
+}
+
+/// The items under `root` still under warranty on `today`, soonest to lapse
+/// first. With `within`, only those whose warranty ends in that many days.
+pub fn warranties(root: &Space, today: Date, within: Option<i64>) -> Vec<Warranty> {
+    let mut warranties: Vec<Warranty> = search::entries(root)
+        .into_iter()
+        .filter_map(|entry| {
+            let Target::Item(item) = entry.target else {
+                return None;
+            };
+            let until = item.warranty_until?;
+            let days_left = today.days_until(until);
+            (days_left >= 0 && within.is_none_or(|days| days_left <= days)).then(|| Warranty {
+                path: entry.path_string(),
+                until,
+                days_left,
+                receipts: item.receipts.len(),
+            })
+        })
+        .collect();
+    warranties.sort_by(|a, b| a.until.cmp(&b.until).then_with(|| a.path.cmp(&b.path)));
+    warranties
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Item;
+
+    #[test]
+    fn lists_covered_items_soonest_first() {
+        let date = |s: &str| s.parse::<Date>().unwrap();
+        let root = Space::builder()
+            .name("home")
+            .push_item(
+                Item::builder()
+                    .name("tv")
+                    .warranty_until(date("2026-01-01"))
+                    .build(),
+            )
+            .push_item(
+                Item::builder()
+                    .name("laptop")
+                    .warranty_until(date("2025-03-01"))
+                    .receipt("receipt.pdf")
+                    .build(),
+            )
+            .push_item(
+                Item::builder()
+                    .name("kettle")
+                    .warranty_until(date("2024-12-31"))
+                    .build(),
+            )
+            .push_item(Item::builder().name("mug").build())
+            .build();
+        let today = date("2025-01-01");
+
+        let all = warranties(&root, today, None);
+        let paths: Vec<&str> = all.iter().map(|w| w.path.as_str()).collect();
+        assert_eq!(paths, ["home/laptop", "home/tv"]);
+        assert_eq!((all[0].days_left, all[0].receipts), (59, 1));
+
+        let expiring = warranties(&root, today, Some(60));
+        assert_eq!(expiring.len(), 1);
+        assert_eq!(expiring[0].path, "home/laptop");
+        assert_eq!(warranties(&root, date("2025-03-01"), Some(0)).len(), 1);
+    }
+}
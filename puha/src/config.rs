@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use puha_lib::{Backend, Depreciation, Limits, SaveOptions};
+use serde::Deserialize;
+
+/// Settings read from the configuration file.
+///
+/// Every field is optional; missing fields keep their defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// How the data file is formatted when saving.
+    pub save: SaveOptions,
+    /// Language whose rules `--sort` follows and whose way of writing numbers
+    /// and dates output uses, e.g. `hu` or `sv-SE`.
+    pub locale: Option<String>,
+    /// Size limits checked when loading.
+    pub limits: Limits,
+    /// Data file used when neither `--file` nor `--profile` is given.
+    pub file: Option<String>,
+    /// Backend of `file`.
+    pub backend: Option<Backend>,
+    /// Named data files, selected with `--profile`.
+    pub profiles: BTreeMap<String, Profile>,
+    /// Short names for commands, e.g. `ai` for `add-item`. The value may
+    /// include arguments.
+    pub aliases: BTreeMap<String, String>,
+    /// Named sequences of commands, run together. `$1`, `$2`, ... in a
+    /// command stand for the macro's arguments.
+    pub macros: BTreeMap<String, Vec<String>>,
+    /// Record how often each command runs and how long it takes, in a
+    /// local file shown by `puha self stats`.
+    pub usage_stats: bool,
+    /// Command `intake` shows photos with, given the photo's path. Defaults
+    /// to the system's default viewer.
+    pub viewer: Option<String>,
+    /// How items lose value, by category, for `value --current`.
+    pub depreciation: BTreeMap<String, Depreciation>,
+    /// Broker to publish changes to.
+    #[cfg(feature = "mqtt")]
+    pub mqtt: Option<Mqtt>,
+    /// Accepted and ignored by builds without MQTT support, so they can
+    /// share a configuration file with builds that have it.
+    #[cfg(not(feature = "mqtt"))]
+    pub mqtt: Option<serde_json::Value>,
+}
+
+/// A data file and the backend storing it.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Path to the file, or directory for the dir backend.
+    pub file: String,
+    #[serde(default)]
+    pub backend: Backend,
+}
+
+/// An MQTT broker and the topic puha uses on it.
+#[cfg(feature = "mqtt")]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mqtt {
+    pub host: String,
+    #[serde(default = "Mqtt::default_port")]
+    pub port: u16,
+    /// Changes are published to this topic, and `mqtt-listen` takes
+    /// commands from `<topic>/commands`.
+    #[serde(default = "Mqtt::default_topic")]
+    pub topic: String,
+}
+
+#[cfg(feature = "mqtt")]
+impl Mqtt {
+    fn default_port() -> u16 {
+        1883
+    }
+
+    fn default_topic() -> String {
+        "puha".to_string()
+    }
+}
+
+impl Config {
+    /// Load the configuration from `path`, or from the default location when
+    /// no path is given. A missing default file yields the default settings.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        if !explicit && !path.exists() {
+            return Ok(Self::default());
+        }
+        let data =
+            std::fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let config: Self =
+            serde_json::from_str(&data).map_err(|e| format!("{}: {e}", path.display()))?;
+        #[cfg(not(feature = "mqtt"))]
+        if config.mqtt.is_some() {
+            eprintln!(
+                "warning: {}: ignoring mqtt, as this build has no MQTT support",
+                path.display()
+            );
+        }
+        Ok(config)
+    }
+}
+
+/// Split a command line into words at whitespace. Single or double quotes
+/// keep whitespace within a word, and outside single quotes a backslash
+/// escapes the next character.
+pub fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut quote = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('"') | None) => {
+                let escaped = chars.next().ok_or("dangling escape")?;
+                word.get_or_insert_default().push(escaped);
+            }
+            (c, None) if c.is_whitespace() => words.extend(word.take()),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (c, _) => word.get_or_insert_default().push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(format!("unterminated quote in '{text}'"));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Replace `$1`, `$2`, ... in `word` with the matching entry of `args`.
+pub fn substitute(word: &str, args: &[String]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = word;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            out.push('$');
+            continue;
+        }
+        let n: usize = rest[..digits]
+            .parse()
+            .map_err(|_| format!("bad parameter in '{word}'"))?;
+        let arg = n
+            .checked_sub(1)
+            .and_then(|i| args.get(i))
+            .ok_or_else(|| format!("missing argument ${n}"))?;
+        out.push_str(arg);
+        rest = &rest[digits..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// `$PUHA_CONFIG`, or `config.json` in the puha configuration directory.
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("PUHA_CONFIG") {
+        return Some(path.into());
+    }
+    config_dir().map(|dir| dir.join("config.json"))
+}
+
+/// `$XDG_CONFIG_HOME/puha`, falling back to `~/.config/puha`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("puha"))
+}
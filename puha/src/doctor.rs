@@ -0,0 +1,249 @@
+//! Checks of the configuration, data and state, with advice on fixing what
+//! is wrong.
+
+use std::path::Path;
+
+#[cfg(feature = "search-index")]
+use puha_lib::index::SearchIndex;
+use puha_lib::{Collation, MatchOptions, Space, links, path, strict};
+
+use crate::Context;
+use crate::config::{self, Config};
+
+/// Result of a single check.
+enum Outcome {
+    Ok(String),
+    /// Works, but something should be looked at.
+    Warn(String, String),
+    /// Stands in the way of using puha.
+    Fail(String, String),
+}
+
+/// Run every check, print the outcomes and fail if any check failed.
+///
+/// `config_path` is the `--config` given, if any. The configuration and
+/// state are loaded again here, since `doctor` runs with the defaults when
+/// they cannot be loaded.
+pub fn run(cx: &Context, config_path: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut outcomes = vec![check_config(config_path)];
+    let root = match cx.store.storage.load() {
+        Ok(root) => {
+            outcomes.push(Outcome::Ok(format!(
+                "data: {} is readable",
+                cx.store.path.display()
+            )));
+            Some(root)
+        }
+        Err(e) if !cx.store.path.exists() => {
+            outcomes.push(Outcome::Fail(
+                format!("data: {}: {e}", cx.store.path.display()),
+                "set puha up with `puha init`, create it with `puha new-root <name>`, or \
+                 point --file or --profile at existing data"
+                    .into(),
+            ));
+            None
+        }
+        Err(e) => {
+            outcomes.push(Outcome::Fail(
+                format!("data: {}: {e}", cx.store.path.display()),
+                "restore it from a backup or fix the reported spot by hand".into(),
+            ));
+            None
+        }
+    };
+    if let Some(root) = &root {
+        outcomes.push(check_writable(&cx.store.path));
+        outcomes.push(check_limits(cx, root));
+        outcomes.push(check_structure(root));
+        outcomes.push(check_links(root));
+        outcomes.push(check_attachments(cx, root));
+        outcomes.push(check_current(cx, root));
+        #[cfg(feature = "search-index")]
+        outcomes.push(check_index(&cx.store.index, root));
+    }
+    outcomes.push(check_state(cx));
+
+    let mut failures = 0;
+    for outcome in &outcomes {
+        match outcome {
+            Outcome::Ok(message) => println!("ok    {message}"),
+            Outcome::Warn(message, fix) => println!("warn  {message}\n      fix: {fix}"),
+            Outcome::Fail(message, fix) => {
+                failures += 1;
+                println!("FAIL  {message}\n      fix: {fix}");
+            }
+        }
+    }
+    match failures {
+        0 => Ok(()),
+        1 => Err("1 check failed".into()),
+        n => Err(format!("{n} checks failed").into()),
+    }
+}
+
+fn check_config(path: Option<&Path>) -> Outcome {
+    let shown = match path.map(Path::to_path_buf).or_else(config::default_path) {
+        Some(path) if path.exists() => path.display().to_string(),
+        _ => "none, using the defaults".to_string(),
+    };
+    match Config::load(path) {
+        // Without the collation feature the locale is only used for numbers
+        // and dates, where any tag will do.
+        Ok(config) => match config
+            .locale
+            .as_deref()
+            .filter(|_| cfg!(feature = "collation"))
+            .map(Collation::for_locale)
+        {
+            Some(Err(e)) => Outcome::Fail(
+                format!("configuration: {e}"),
+                "set `locale` to a language tag such as `en` or `hu`, or remove it".into(),
+            ),
+            _ => Outcome::Ok(format!("configuration: {shown}")),
+        },
+        Err(e) => Outcome::Fail(
+            format!("configuration: {e}"),
+            "correct the file; unknown keys are rejected, see the README for the valid ones".into(),
+        ),
+    }
+}
+
+fn check_writable(path: &Path) -> Outcome {
+    let writable = if path.is_dir() {
+        std::fs::metadata(path).map(|m| !m.permissions().readonly())
+    } else {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map(|_| true)
+    };
+    match writable {
+        Ok(true) => Outcome::Ok(format!("data: {} is writable", path.display())),
+        Ok(false) => Outcome::Fail(
+            format!("data: {} is read-only", path.display()),
+            "make it writable, e.g. with `chmod u+w`".into(),
+        ),
+        Err(e) => Outcome::Fail(
+            format!("data: {} is not writable: {e}", path.display()),
+            "check its permissions and owner".into(),
+        ),
+    }
+}
+
+fn check_limits(cx: &Context, root: &Space) -> Outcome {
+    let checked = cx
+        .store
+        .limits
+        .check_size(&cx.store.path)
+        .and_then(|()| Ok(cx.store.limits.check_tree(root)?));
+    match checked {
+        Ok(()) => Outcome::Ok("limits: within the configured limits".into()),
+        Err(e) => Outcome::Fail(
+            format!("limits: {e}"),
+            "split up the data, or raise the limit under `limits` in the configuration".into(),
+        ),
+    }
+}
+
+fn check_structure(root: &Space) -> Outcome {
+    match strict::check_structure(root) {
+        Ok(()) => Outcome::Ok("structure: names and root flags are valid".into()),
+        Err(e) => Outcome::Warn(
+            format!("structure: {e}"),
+            "rename duplicates by hand and run `puha repair-names`; --strict refuses \
+             this data until then"
+                .into(),
+        ),
+    }
+}
+
+fn check_links(root: &Space) -> Outcome {
+    match links::dangling(root).as_slice() {
+        [] => Outcome::Ok("links: every link points to an item".into()),
+        [(path, link)] => Outcome::Warn(
+            format!(
+                "links: {path} is {} an item that no longer exists",
+                link.kind
+            ),
+            "run `puha unlink --dangling`".into(),
+        ),
+        dangling => Outcome::Warn(
+            format!(
+                "links: {} links point to items that no longer exist",
+                dangling.len()
+            ),
+            "run `puha unlink --dangling`".into(),
+        ),
+    }
+}
+
+fn check_attachments(cx: &Context, root: &Space) -> Outcome {
+    let dir = cx.store.assets.dir().display();
+    match cx.store.assets.orphans(root).map(|orphans| orphans.len()) {
+        Ok(0) => Outcome::Ok("attachments: every stored file is attached".into()),
+        Ok(1) => Outcome::Warn(
+            format!("attachments: 1 file in {dir} is attached to nothing"),
+            "run `puha gc-attachments`".into(),
+        ),
+        Ok(n) => Outcome::Warn(
+            format!("attachments: {n} files in {dir} are attached to nothing"),
+            "run `puha gc-attachments`".into(),
+        ),
+        Err(e) => Outcome::Fail(
+            format!("attachments: {dir}: {e}"),
+            "check its permissions and owner".into(),
+        ),
+    }
+}
+
+fn check_current(cx: &Context, root: &Space) -> Outcome {
+    let Some(current) = cx.state.current.get(&cx.state_key) else {
+        return Outcome::Ok("current space: the root".into());
+    };
+    let segments: Vec<&str> = current.iter().map(String::as_str).collect();
+    let shown = path::format(&segments);
+    if root.space_at(&segments, &MatchOptions::default()).is_some() {
+        Outcome::Ok(format!("current space: {shown}"))
+    } else {
+        Outcome::Warn(
+            format!("current space: {shown} no longer exists"),
+            "run `puha cd` to go back to the root".into(),
+        )
+    }
+}
+
+#[cfg(feature = "search-index")]
+fn check_index(path: &Path, root: &Space) -> Outcome {
+    if !path.exists() {
+        return Outcome::Ok("search index: none, searching the tree directly".into());
+    }
+    match SearchIndex::from_file(path) {
+        Ok(mut index) => match index.update(root) {
+            0 => Outcome::Ok(format!("search index: {} is up to date", path.display())),
+            _ => Outcome::Warn(
+                format!("search index: {} is out of date", path.display()),
+                "run `puha build-index`".into(),
+            ),
+        },
+        Err(e) => Outcome::Fail(
+            format!("search index: {}: {e}", path.display()),
+            "delete it or run `puha build-index`".into(),
+        ),
+    }
+}
+
+fn check_state(cx: &Context) -> Outcome {
+    let Some(path) = &cx.state_path else {
+        return Outcome::Warn(
+            "state: no state directory, so `cd` cannot remember the current space".into(),
+            "set $PUHA_STATE or $HOME".into(),
+        );
+    };
+    match crate::state::State::load(path) {
+        Ok(_) => Outcome::Ok(format!("state: {}", path.display())),
+        Err(e) => Outcome::Fail(
+            format!("state: {e}"),
+            "delete the file; it only holds the current space of each data file".into(),
+        ),
+    }
+}
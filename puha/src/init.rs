@@ -0,0 +1,110 @@
+//! Setting puha up on first use: creating the data and pointing the
+//! configuration at it.
+
+use std::path::{Path, PathBuf};
+
+use puha_lib::{Backend, SaveOptions, Space};
+
+use crate::config;
+
+/// The configuration file's settings, as written.
+type Settings = serde_json::Map<String, serde_json::Value>;
+
+/// What `init` was told on the command line; anything missing is asked for.
+pub struct Answers {
+    pub name: Option<String>,
+    pub file: Option<String>,
+    pub backend: Option<Backend>,
+}
+
+/// Ask for whatever `answers` leaves open, create the root space unless the
+/// data already exists and record the data file in the configuration at
+/// `config_path`, or the default location.
+pub fn run(
+    answers: Answers,
+    config_path: Option<&Path>,
+    save_options: SaveOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = config_path
+        .map(Path::to_path_buf)
+        .or_else(config::default_path)
+        .ok_or("no configuration directory; pass --config or set $PUHA_CONFIG")?;
+    // Read first, so a broken configuration stops init before it creates
+    // anything.
+    let mut settings = read_settings(&config_path)?;
+    let file = match answers.file {
+        Some(file) => file,
+        None => {
+            let default = data_dir()
+                .ok_or("no data directory; pass --file")?
+                .join("inventory.json");
+            ask("Where to keep the data", &default.to_string_lossy())?
+        }
+    };
+    let file = std::path::absolute(&file)?;
+    let backend = match answers.backend {
+        Some(backend) => backend,
+        None => ask(
+            "Keep it in one JSON file (json) or a directory per space (dir)",
+            "json",
+        )?
+        .parse()?,
+    };
+
+    if file.exists() {
+        println!("{} already exists; keeping it", file.display());
+    } else {
+        let name = match answers.name {
+            Some(name) => name,
+            None => ask("Name of the root space", "home")?,
+        };
+        let root = Space::builder().name(name).root(true).try_build()?;
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        backend.open_with(&file, save_options).save(&root)?;
+        println!("created {}", file.display());
+    }
+
+    settings.insert("file".into(), file.to_string_lossy().into());
+    settings.insert("backend".into(), backend.to_string().into());
+    if let Some(dir) = config_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(
+        &config_path,
+        serde_json::to_string_pretty(&settings)? + "\n",
+    )
+    .map_err(|e| format!("{}: {e}", config_path.display()))?;
+    println!("saved the data location to {}", config_path.display());
+    Ok(())
+}
+
+/// Ask `question`, giving `default` for an empty answer.
+fn ask(question: &str, default: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let answer = crate::prompt(&format!("{question} [{default}]: "))?.ok_or("init cancelled")?;
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer
+    })
+}
+
+/// The settings in the configuration file at `path`, or none if there is
+/// no such file yet.
+fn read_settings(path: &Path) -> Result<Settings, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(Settings::new());
+    }
+    let data = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(serde_json::from_str(&data).map_err(|e| format!("{}: {e}", path.display()))?)
+}
+
+/// `$XDG_DATA_HOME/puha`, falling back to `~/.local/share/puha`.
+fn data_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("puha"))
+}
@@ -0,0 +1,60 @@
+//! Insurance report bundles: one zip archive with what an insurer asks for.
+
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use puha_lib::{Assets, Price, Valuation};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// Write a zip archive to `out`, or to standard output for `-`, with
+/// `report`, a PDF from [`crate::report::render`], a CSV of `valuation`,
+/// and copies of the receipts of the valued items worth at least
+/// `min_value`.
+///
+/// Returns the receipts whose file is missing from `assets`, which are left
+/// out.
+pub fn write(
+    report: &[u8],
+    valuation: &Valuation,
+    min_value: Price,
+    assets: &Assets,
+    out: &Path,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    // Zip archives are written out of order, so the archive is put
+    // together in memory first.
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    zip.start_file("inventory.pdf", options)?;
+    zip.write_all(report)?;
+    zip.start_file("items.csv", options)?;
+    zip.write_all(valuation.to_csv().as_bytes())?;
+
+    let mut missing = Vec::new();
+    for item in valuation.items.iter().filter(|i| i.current >= min_value) {
+        for (i, receipt) in item.receipts.iter().enumerate() {
+            let Ok(contents) = std::fs::read(assets.resolve(receipt)) else {
+                missing.push(receipt.clone());
+                continue;
+            };
+            zip.start_file(receipt_name(&item.path, i, receipt), options)?;
+            zip.write_all(&contents)?;
+        }
+    }
+    crate::write_output(out, zip.finish()?.get_ref())?;
+    Ok(missing)
+}
+
+/// Where the `i`th receipt of the item at `path` goes in the archive, named
+/// after the item so it can be found without the CSV.
+fn receipt_name(path: &str, i: usize, receipt: &str) -> String {
+    let mut name = format!("receipts/{}", path.replace('/', " - "));
+    if i > 0 {
+        name.push_str(&format!(" ({})", i + 1));
+    }
+    if let Some(ext) = Path::new(receipt).extension().and_then(|e| e.to_str()) {
+        name.push('.');
+        name.push_str(ext);
+    }
+    name
+}
@@ -0,0 +1,107 @@
+//! Cataloguing items from a directory of photos, one photo at a time.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config;
+
+/// File extensions taken for photos, compared without case.
+const EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "heic", "webp", "gif"];
+
+/// Longest name suggested from the text in a photo, in characters.
+#[cfg(feature = "ocr")]
+const MAX_SUGGESTED_NAME: usize = 60;
+
+/// An item name and description read from the text in a photo.
+#[cfg_attr(not(feature = "ocr"), allow(dead_code))]
+pub struct Suggestion {
+    pub name: String,
+    pub description: String,
+}
+
+/// The photos directly in `dir`, as absolute paths, in name order.
+pub fn photos(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let dir = std::path::absolute(dir)?;
+    let entries = std::fs::read_dir(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let mut photos = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_photo = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)));
+        if is_photo && path.is_file() {
+            photos.push(path);
+        }
+    }
+    photos.sort();
+    Ok(photos)
+}
+
+/// Open `photo` in `viewer`, a command line the path is appended to, or
+/// else in the system's default viewer. Does not wait for the viewer.
+pub fn show(viewer: Option<&str>, photo: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut words = match viewer {
+        Some(viewer) => config::split_words(viewer)?,
+        None => default_viewer().iter().map(|w| w.to_string()).collect(),
+    };
+    if words.is_empty() {
+        return Err("the `viewer` setting is empty".into());
+    }
+    let program = words.remove(0);
+    Command::new(&program)
+        .args(words)
+        .arg(photo)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("cannot run '{program}': {e}"))?;
+    Ok(())
+}
+
+/// Suggest a name and description for the item in `photo` from the text in
+/// it, read by the `tesseract` command: the first line of text becomes the
+/// name and the rest the description. `None` when no text is found.
+#[cfg(feature = "ocr")]
+pub fn suggest(photo: &Path) -> Result<Option<Suggestion>, Box<dyn std::error::Error>> {
+    let output = Command::new("tesseract")
+        .arg(photo)
+        .arg("stdout")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("cannot run 'tesseract': {e}"))?;
+    if !output.status.success() {
+        return Err(format!("tesseract failed with {}", output.status).into());
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut lines = text
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| line.chars().any(char::is_alphanumeric));
+    let Some(first) = lines.next() else {
+        return Ok(None);
+    };
+    let name: String = first.chars().take(MAX_SUGGESTED_NAME).collect();
+    Ok(Some(Suggestion {
+        name: puha_lib::naming::repair(name.trim()),
+        description: lines.collect::<Vec<_>>().join(" "),
+    }))
+}
+
+/// Suggestions need the `ocr` feature; without it there are none.
+#[cfg(not(feature = "ocr"))]
+pub fn suggest(_photo: &Path) -> Result<Option<Suggestion>, Box<dyn std::error::Error>> {
+    Ok(None)
+}
+
+fn default_viewer() -> &'static [&'static str] {
+    if cfg!(target_os = "macos") {
+        &["open"]
+    } else if cfg!(windows) {
+        &["cmd", "/C", "start", ""]
+    } else {
+        &["xdg-open"]
+    }
+}
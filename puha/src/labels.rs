@@ -0,0 +1,220 @@
+//! Sheets of QR code labels in PDF.
+
+use std::fmt;
+use std::io::BufWriter;
+use std::path::Path;
+use std::str::FromStr;
+
+use printpdf::path::PaintMode;
+use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfLayerReference, Rect};
+use puha_lib::search::Target;
+use puha_lib::{Space, path};
+use qrcode::QrCode;
+
+use crate::report::wrap;
+
+/// A label sheet template; all lengths are in millimetres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub name: &'static str,
+    pub page: (f32, f32),
+    pub columns: usize,
+    pub rows: usize,
+    /// Width and height of a single label.
+    pub label: (f32, f32),
+    /// Distance of the top left label from the left and top page edges.
+    pub origin: (f32, f32),
+    /// Distance between the left and top edges of neighbouring labels.
+    pub pitch: (f32, f32),
+}
+
+pub const LAYOUTS: [Layout; 3] = [
+    Layout {
+        name: "avery-l7160",
+        page: (210.0, 297.0),
+        columns: 3,
+        rows: 7,
+        label: (63.5, 38.1),
+        origin: (7.2, 15.1),
+        pitch: (66.0, 38.1),
+    },
+    Layout {
+        name: "avery-l7163",
+        page: (210.0, 297.0),
+        columns: 2,
+        rows: 7,
+        label: (99.1, 38.1),
+        origin: (4.6, 15.1),
+        pitch: (101.6, 38.1),
+    },
+    Layout {
+        name: "avery-5160",
+        page: (215.9, 279.4),
+        columns: 3,
+        rows: 10,
+        label: (66.7, 25.4),
+        origin: (4.8, 12.7),
+        pitch: (69.8, 25.4),
+    },
+];
+
+impl Layout {
+    fn per_page(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    /// Lower left corner of the label at `index` on its page.
+    fn corner(&self, index: usize) -> (f32, f32) {
+        let (column, row) = (index % self.columns, index / self.columns);
+        let x = self.origin.0 + column as f32 * self.pitch.0;
+        let top = self.page.1 - self.origin.1 - row as f32 * self.pitch.1;
+        (x, top - self.label.1)
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LAYOUTS
+            .into_iter()
+            .find(|layout| layout.name.eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = LAYOUTS.iter().map(|l| l.name).collect();
+                format!(
+                    "unknown layout '{s}', expected one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+const PADDING: f32 = 3.0;
+const NAME_SIZE: f32 = 11.0;
+const PATH_SIZE: f32 = 7.0;
+/// Approximate number of characters per millimetre at 1pt font size.
+const CHARS_PER_MM_PT: f32 = 5.5;
+
+/// Write one label for every item and child space directly in `space`,
+/// whose enclosing spaces are `parents`, to `out`, or to standard output for
+/// `-`. The first `skip` labels of the first sheet are left empty.
+pub fn write(
+    space: &Space,
+    parents: &[&str],
+    layout: Layout,
+    skip: usize,
+    out: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut path: Vec<&str> = parents.to_vec();
+    path.push(space.name());
+    let location = path::format(&path);
+    let targets = space
+        .items()
+        .iter()
+        .map(Target::Item)
+        .chain(space.spaces().iter().map(Target::Space));
+
+    let (width, height) = layout.page;
+    let (doc, page, layer) = PdfDocument::new(
+        format!("Labels for {}", path::join(parents, space.name())),
+        Mm(width),
+        Mm(height),
+        "labels",
+    );
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+    let mut layer = doc.get_page(page).get_layer(layer);
+    for (n, target) in targets.enumerate() {
+        let index = n + skip;
+        if index > 0 && index.is_multiple_of(layout.per_page()) {
+            let (page, new_layer) = doc.add_page(Mm(width), Mm(height), "labels");
+            layer = doc.get_page(page).get_layer(new_layer);
+        }
+        let corner = layout.corner(index % layout.per_page());
+        path.push(target.name());
+        let code = path::format(&path);
+        path.pop();
+        let label = Label {
+            name: target.name(),
+            location: &location,
+            code: &code,
+        };
+        label.draw(&layer, corner, layout.label, &bold, &regular)?;
+    }
+    let mut pdf = BufWriter::new(Vec::new());
+    doc.save(&mut pdf)?;
+    crate::write_output(out, &pdf.into_inner()?)
+}
+
+struct Label<'a> {
+    name: &'a str,
+    /// Path of the space holding the labelled entity.
+    location: &'a str,
+    /// Text encoded in the QR code.
+    code: &'a str,
+}
+
+impl Label<'_> {
+    fn draw(
+        &self,
+        layer: &PdfLayerReference,
+        (x, y): (f32, f32),
+        (width, height): (f32, f32),
+        bold: &IndirectFontRef,
+        regular: &IndirectFontRef,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let size = height - 2.0 * PADDING;
+        draw_qr(layer, self.code, (x + PADDING, y + PADDING), size)?;
+
+        let text_x = x + 2.0 * PADDING + size;
+        let text_width = width - 3.0 * PADDING - size;
+        let mut baseline = y + height - PADDING - NAME_SIZE * 0.35;
+        let chars = (text_width * CHARS_PER_MM_PT / NAME_SIZE) as usize;
+        for line in wrap(self.name, chars).iter().take(3) {
+            layer.use_text(line, NAME_SIZE, Mm(text_x), Mm(baseline), bold);
+            baseline -= NAME_SIZE * 0.45;
+        }
+        let chars = (text_width * CHARS_PER_MM_PT / PATH_SIZE) as usize;
+        let lines = wrap(self.location, chars);
+        let lines = &lines[..lines.len().min(2)];
+        let mut baseline = y + PADDING + lines.len().saturating_sub(1) as f32 * PATH_SIZE * 0.45;
+        for line in lines {
+            layer.use_text(line, PATH_SIZE, Mm(text_x), Mm(baseline), regular);
+            baseline -= PATH_SIZE * 0.45;
+        }
+        Ok(())
+    }
+}
+
+/// Draw `text` as a QR code filling a `size` square with its lower left
+/// corner at `(x, y)`.
+fn draw_qr(
+    layer: &PdfLayerReference,
+    text: &str,
+    (x, y): (f32, f32),
+    size: f32,
+) -> Result<(), qrcode::types::QrError> {
+    let code = QrCode::new(text.as_bytes())?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let module = size / modules as f32;
+    for (i, color) in colors.iter().enumerate() {
+        if *color != qrcode::Color::Dark {
+            continue;
+        }
+        let (column, row) = ((i % modules) as f32, (i / modules) as f32);
+        let left = x + column * module;
+        let top = y + size - row * module;
+        layer.add_rect(
+            Rect::new(Mm(left), Mm(top - module), Mm(left + module), Mm(top))
+                .with_mode(PaintMode::Fill),
+        );
+    }
+    Ok(())
+}
@@ -1,13 +1,137 @@
-use clap::{Parser, Subcommand};
-use puha_lib::{Item, Space};
+mod config;
+mod doctor;
+mod init;
+#[cfg(feature = "insurance")]
+mod insurance;
+mod intake;
+#[cfg(feature = "labels")]
+mod labels;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(any(
+    feature = "search-index",
+    feature = "import-grocy",
+    feature = "import-home-assistant",
+    feature = "xlsx"
+))]
+mod progress;
+#[cfg(feature = "pdf")]
+mod report;
+#[cfg(feature = "shell")]
+mod shell;
+mod state;
+#[cfg(feature = "thumbnails")]
+mod thumbnails;
+mod usage;
+#[cfg(feature = "xlsx")]
+mod xlsx;
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+
+use clap::{CommandFactory, Parser, Subcommand};
+use config::Config;
+#[cfg(any(feature = "pdf", feature = "xlsx"))]
+use puha_lib::Redaction;
+use puha_lib::TreeDiff;
+use puha_lib::assets;
+use puha_lib::audit;
+use puha_lib::capacity;
+#[cfg(feature = "search-index")]
+use puha_lib::index::SearchIndex;
+use puha_lib::kits::{self, Member};
+use puha_lib::links::{self, LinkKind};
+use puha_lib::lock;
+use puha_lib::path::{self, PathError};
+use puha_lib::quick;
+use puha_lib::sample::{self, SampleOptions};
+use puha_lib::search::{self, Entry, Target};
+use puha_lib::stats::{self, TreeStats};
+use puha_lib::storage::{self, Stdio};
+use puha_lib::suggest;
+use puha_lib::tags;
+use puha_lib::transfer;
+use puha_lib::views::{self, Group, ItemsById};
+use puha_lib::warranty;
+use puha_lib::{
+    Assets, Backend, Collation, Date, Formats, Item, Limits, MatchKind, MatchOptions, Price, Query,
+    ShoppingList, Size, Space, Storage, Template, Timestamp, Valuation, Weight,
+};
+#[cfg(any(feature = "import-grocy", feature = "import-home-assistant"))]
+use puha_lib::{IdStrategy, ids};
+use state::State;
 
 /// Command line interface for managing spaces and items.
 #[derive(Parser)]
 #[command(author, version, about)]
 struct Cli {
-    /// Path to the file storing the space tree
-    #[arg(short, long, default_value = "space.json")]
-    file: String,
+    /// Path to the file (or directory, for the dir backend) storing the space
+    /// tree [default: space.json]
+    #[arg(short, long)]
+    file: Option<String>,
+
+    /// Storage backend: `json` for a single file, `dir` for one directory per
+    /// space [default: json]
+    #[arg(short, long)]
+    backend: Option<Backend>,
+
+    /// Use the file and backend of this profile from the configuration
+    #[arg(short, long)]
+    profile: Option<String>,
+
+    /// Reject unknown fields, duplicate names and other anomalies when loading
+    #[arg(long)]
+    strict: bool,
+
+    /// Allow changes inside locked spaces
+    #[arg(long)]
+    unlock: bool,
+
+    /// Configuration file (defaults to $PUHA_CONFIG or ~/.config/puha/config.json)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Save the data file as a single line of JSON
+    #[arg(long)]
+    compact: bool,
+
+    /// Number of spaces to indent the data file with
+    #[arg(long, conflicts_with = "compact")]
+    indent: Option<usize>,
+
+    /// Save object keys in alphabetical order for stable diffs
+    #[arg(long)]
+    sort_keys: bool,
+
+    /// Match space and item names regardless of case
+    #[arg(short = 'i', long, global = true)]
+    ignore_case: bool,
+
+    /// Match space and item names regardless of accents ("Fiók" finds "Fiok")
+    #[arg(long, global = true)]
+    ignore_accents: bool,
+
+    /// End output records with NUL instead of a newline, and split names read
+    /// with --stdin on NUL, for use with `xargs -0`
+    #[arg(short = '0', long, global = true)]
+    print0: bool,
+
+    /// Sort names in listings, following the locale
+    #[arg(long, global = true)]
+    sort: bool,
+
+    /// Language for sorting and for numbers and dates in output, such as `hu`
+    /// (defaults to $PUHA_LOCALE or the `locale` setting)
+    #[arg(long, global = true)]
+    locale: Option<String>,
+
+    /// Do not show progress bars
+    #[arg(short, long, global = true)]
+    quiet: bool,
 
     #[command(subcommand)]
     command: Commands,
@@ -19,134 +143,3243 @@ enum Commands {
     NewRoot { name: String },
 
     /// Show a space and all of its children
-    ShowTree { name: Option<String> },
+    ShowTree {
+        name: Option<String>,
 
-    /// Add an item to a space
+        /// Show a view's groups of items instead of the spaces
+        #[arg(long, conflicts_with = "name")]
+        view: Option<String>,
+
+        /// `json` prints the space as it is saved, with everything in it,
+        /// for `import-space`
+        #[arg(short, long, value_enum, default_value_t = TreeOutput::Text, conflicts_with = "view")]
+        output: TreeOutput,
+    },
+
+    /// Add an item to a space, or to the current space when only ITEM and
+    /// DESCRIPTION are given
+    #[command(override_usage = "puha add-item [OPTIONS] [SPACE] <ITEM> <DESCRIPTION>")]
     AddItem {
-        space: String,
+        /// Space (the current space if left out), item name and description
+        #[arg(num_args = 2..=3, value_names = ["SPACE", "ITEM", "DESCRIPTION"])]
+        args: Vec<String>,
+
+        /// How many of the item there are
+        #[arg(long)]
+        quantity: Option<u32>,
+
+        /// How many of the item to keep in stock
+        #[arg(long)]
+        min_quantity: Option<u32>,
+
+        /// Add the item without checking for items with a similar name
+        #[arg(long)]
+        no_dedupe_check: bool,
+    },
+
+    /// Set how many of an item there are, or with --min how many to keep
+    SetQuantity {
         item: String,
-        description: String,
+
+        quantity: Option<u32>,
+
+        /// How many of the item to keep in stock; 0 for no minimum
+        #[arg(long)]
+        min: Option<u32>,
     },
 
-    /// Add a space to another space
-    AddSpace {
-        parent: String,
-        child: String,
+    /// Use up some of an item, recording when
+    Consume {
+        item: String,
+
+        #[arg(default_value_t = 1)]
+        n: u32,
+    },
+
+    /// Add to an item's quantity, recording when
+    Restock {
+        item: String,
+
+        #[arg(default_value_t = 1)]
+        n: u32,
+    },
+
+    /// Chart how an item's quantity changed with `consume` and `restock`,
+    /// and estimate when it runs out
+    Usage { item: String },
+
+    /// List the items whose quantity fell below their minimum, by category
+    ShoppingList {
+        space: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = ListFormat::Markdown)]
+        format: ListFormat,
+    },
+
+    /// Add an item from one line, e.g. `3x AA batteries -> garage/shelf #consumables`
+    ///
+    /// The quantity, destination and tags are optional. A missing name or
+    /// destination is asked for when run in a terminal; the destination
+    /// defaults to the current space.
+    Quick {
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+
+    /// Add an item for every line of text on the system clipboard
+    #[cfg(feature = "clipboard")]
+    AddFromClipboard {
+        /// Space to add the items to (the current space if left out)
+        space: Option<String>,
+    },
+
+    /// Add items from a directory of photos: show each new photo, ask for
+    /// the item's name and attach the photo to it
+    ///
+    /// Photos are only offered once; an empty name skips one, and the end of
+    /// input (Ctrl-D) stops.
+    Intake {
+        /// Space to add the items to (the current space if left out)
+        space: Option<String>,
+
+        /// Directory of photos
+        #[arg(long)]
+        from: PathBuf,
+    },
+
+    /// Copy attached files kept elsewhere into the data's assets directory,
+    /// storing identical files once, and list files attached to several
+    /// items
+    StoreAttachments,
+
+    /// Delete the files in the assets directory that no item refers to any
+    /// more, after asking
+    GcAttachments {
+        /// Only list the files
+        #[arg(long)]
+        dry_run: bool,
     },
 
+    /// Show thumbnails of an item's attached photos in terminals that support
+    /// the kitty graphics protocol, or else print where they are
+    #[cfg(feature = "thumbnails")]
+    Photos { item: String },
+
+    /// Add a space to another space
+    AddSpace { parent: String, child: String },
+
     /// List all items in a space
-    ListItems { space: String },
+    ListItems {
+        space: Option<String>,
+
+        /// Skip this many matching items
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+
+        /// Show at most this many items
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Only list items whose name contains this text
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Output template, e.g. '{path}\t{description}'
+        #[arg(long)]
+        format: Option<Template>,
+    },
 
     /// List all items and spaces in a space (one level)
-    List { space: String },
+    List {
+        space: Option<String>,
+
+        /// Output template, e.g. '{kind}\t{name}'
+        #[arg(long)]
+        format: Option<Template>,
+    },
+
+    /// Search item and space names and item descriptions, best matches first
+    ///
+    /// Terms like `name:drill`, `desc:"allen key"`, `meta.serial:ABC123` or
+    /// `tag:fragile` only match that field.
+    Search {
+        query: String,
+
+        /// Only search below this space
+        #[arg(long)]
+        space: Option<String>,
+
+        /// Output template, e.g. '{path}\t{meta.serial}'
+        #[arg(long)]
+        format: Option<Template>,
+    },
+
+    /// Suggest spaces to put a new item in, by where items with similar names
+    /// and tags are
+    Suggest {
+        name: String,
+
+        /// A tag the item will have; can be repeated
+        #[arg(long)]
+        tag: Vec<String>,
+
+        /// Show at most this many spaces
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+    },
+
+    /// Build the search index, which `search` then uses and saving keeps up to date
+    #[cfg(feature = "search-index")]
+    BuildIndex,
+
+    /// Set or, without a value, remove a metadata entry on an item
+    SetMeta {
+        item: String,
+        key: String,
+        value: Option<String>,
+    },
+
+    /// Manage the words search treats as equivalent
+    Synonyms {
+        #[command(subcommand)]
+        command: SynonymsCommand,
+    },
+
+    /// Show or set the bounds on the tree's shape that adding to it must
+    /// keep within; empty values remove a bound
+    TreeLimits {
+        /// Deepest nesting of spaces below the root
+        #[arg(long)]
+        max_depth: Option<String>,
+
+        /// Most items and spaces together directly in one space
+        #[arg(long)]
+        max_children: Option<String>,
+
+        /// Longest name, in characters
+        #[arg(long)]
+        max_name_length: Option<String>,
+    },
+
+    /// Manage kits: named sets of items kept anywhere in the tree
+    Kit {
+        #[command(subcommand)]
+        command: KitCommand,
+    },
+
+    /// Manage views: named groupings of items besides where they are kept,
+    /// shown with `show-tree --view`
+    View {
+        #[command(subcommand)]
+        command: ViewCommand,
+    },
+
+    /// Print the paths of all items and spaces with the given name
+    Find {
+        pattern: String,
+
+        /// Output template, e.g. '{parent}\t{name}'
+        #[arg(long)]
+        format: Option<Template>,
+
+        /// Treat the pattern as a regular expression matched against names
+        #[cfg(feature = "regex")]
+        #[arg(long)]
+        regex: bool,
+    },
 
     /// Move one or more items to a space
     MoveItems {
         from: String,
         to: String,
         items: Vec<String>,
+
+        /// Also read item names from standard input, one per line
+        #[arg(long)]
+        stdin: bool,
+
+        /// Treat the item arguments as regular expressions selecting every
+        /// matching item in the source space and below
+        #[cfg(feature = "regex")]
+        #[arg(long)]
+        regex: bool,
     },
 
     /// Move a space and all its children to another space
     MoveSpace { space: String, to: String },
+
+    /// Add a space saved as JSON, as `show-tree -o json` prints it, with
+    /// everything in it
+    ImportSpace {
+        /// File holding the space, or `-` for standard input
+        file: PathBuf,
+
+        /// Space to add it to (the current space, or the root, if left out)
+        #[arg(long)]
+        into: Option<String>,
+    },
+
+    /// Lock a space, so that it and everything below it can only be changed
+    /// with --unlock
+    Lock { space: String },
+
+    /// Unlock a locked space
+    Unlock { space: String },
+
+    /// Write a printable PDF inventory of a space and everything below it
+    #[cfg(feature = "pdf")]
+    Report {
+        space: String,
+
+        #[arg(long)]
+        out: PathBuf,
+
+        /// TrueType font to use, for characters the built-in fonts lack
+        #[arg(long)]
+        font: Option<PathBuf>,
+
+        /// Comma-separated item fields to leave out, e.g.
+        /// `description,metadata.serial`
+        #[arg(long)]
+        redact: Option<Redaction>,
+    },
+
+    /// Write a zip archive for an insurer: a PDF report of a space, a CSV of
+    /// its items with a purchase price, and the receipts of the valuable ones
+    #[cfg(feature = "insurance")]
+    InsuranceReport {
+        space: String,
+
+        #[arg(long)]
+        out: PathBuf,
+
+        /// TrueType font to use, for characters the built-in fonts lack
+        #[arg(long)]
+        font: Option<PathBuf>,
+
+        /// Only include the receipts of items worth at least this much
+        /// today, e.g. 500
+        #[arg(long, default_value = "0")]
+        min_value: Price,
+    },
+
+    /// Write a sheet of QR code labels for every item and space directly in
+    /// a space
+    #[cfg(feature = "labels")]
+    Labels {
+        space: String,
+
+        /// Label sheet template: avery-l7160, avery-l7163 or avery-5160
+        #[arg(long, default_value = "avery-l7160")]
+        layout: labels::Layout,
+
+        /// Leave this many labels empty, to reuse a partly used sheet
+        #[arg(long, default_value_t = 0)]
+        skip: usize,
+
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Import spaces and items from another home inventory tool into a space
+    #[cfg(any(feature = "import-grocy", feature = "import-home-assistant"))]
+    Import {
+        #[arg(long)]
+        from: ImportSource,
+
+        /// Grocy: a JSON file of API responses; Home Assistant: the
+        /// configuration directory
+        export: PathBuf,
+
+        /// Space to import into (defaults to the root)
+        #[arg(long)]
+        into: Option<String>,
+
+        /// How to give ids to what is imported; with deterministic ids,
+        /// importing the same export again updates it instead of adding it
+        /// twice
+        #[arg(long, value_enum, default_value_t = IdChoice::Deterministic)]
+        ids: IdChoice,
+
+        /// Print what the import would add and change without saving it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Export all items to a spreadsheet with one sheet per top-level space
+    #[cfg(feature = "xlsx")]
+    ExportXlsx {
+        #[arg(long)]
+        out: PathBuf,
+
+        /// Comma-separated item fields to leave out, e.g.
+        /// `description,metadata.serial`
+        #[arg(long)]
+        redact: Option<Redaction>,
+    },
+
+    /// Set an item's category and what it cost when
+    SetPurchase {
+        item: String,
+
+        /// Kind of item, which decides how it depreciates; empty to remove
+        #[arg(long)]
+        category: Option<String>,
+
+        /// Purchase date as YYYY-MM-DD; empty to remove
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Purchase price, e.g. 249.90; empty to remove
+        #[arg(long)]
+        price: Option<String>,
+    },
+
+    /// List the items with a purchase price, and what they cost together
+    Value {
+        space: Option<String>,
+
+        /// Also show what the items are worth today, depreciated by the
+        /// schedule configured for their category
+        #[arg(long)]
+        current: bool,
+    },
+
+    /// Set the last day an item is under warranty, and attach its receipts
+    SetWarranty {
+        item: String,
+
+        /// Last day of the warranty as YYYY-MM-DD; empty to remove
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Receipt or other document to store with the item; repeatable
+        #[arg(long = "receipt")]
+        receipts: Vec<PathBuf>,
+    },
+
+    /// List the items still under warranty, soonest to lapse first
+    Warranties {
+        space: Option<String>,
+
+        /// Only the items whose warranty ends within this many days
+        #[arg(long, value_name = "DAYS")]
+        expiring: Option<i64>,
+    },
+
+    /// Set an item's outer dimensions and weight
+    SetSize {
+        item: String,
+
+        /// Width x depth x height in centimetres, e.g. 30x20x10; empty to
+        /// remove
+        #[arg(long)]
+        size: Option<String>,
+
+        /// e.g. 300g or 2.5kg; empty to remove
+        #[arg(long)]
+        weight: Option<String>,
+    },
+
+    /// Set how much a space holds, for `fit`
+    SetCapacity {
+        space: String,
+
+        /// Inner width x depth x height in centimetres, e.g. 80x40x35;
+        /// empty to remove
+        #[arg(long)]
+        size: Option<String>,
+
+        /// How much the things in the space may weigh together, e.g. 20kg;
+        /// empty to remove
+        #[arg(long)]
+        max_weight: Option<String>,
+    },
+
+    /// Suggest spaces with enough room left for an item
+    Fit {
+        item: String,
+
+        /// List the spaces closest to this one first
+        #[arg(long, value_name = "SPACE")]
+        near: Option<String>,
+    },
+
+    /// Link an item to another, e.g. `link charger accessory-for camera`
+    Link {
+        item: String,
+
+        /// part-of, accessory-for or replacement-for
+        kind: LinkKind,
+
+        target: String,
+    },
+
+    /// Remove an item's links to another item, or with --dangling every
+    /// link to an item that no longer exists
+    Unlink {
+        #[arg(required_unless_present = "dangling")]
+        item: Option<String>,
+
+        #[arg(required_unless_present = "dangling")]
+        target: Option<String>,
+
+        #[arg(long, conflicts_with_all = ["item", "target"])]
+        dangling: bool,
+    },
+
+    /// List the links from and to an item
+    Links { item: String },
+
+    /// Move a space, or with --where the matching items in it, to another
+    /// inventory file, recording the move in a manifest next to that file
+    Transfer {
+        space: String,
+
+        /// Only move the items in the space matching this query, e.g.
+        /// `meta.owner:alice`
+        #[arg(long = "where", value_name = "QUERY")]
+        filter: Option<Query>,
+
+        /// Inventory to move to; created if it does not exist
+        #[arg(long)]
+        to_file: PathBuf,
+
+        /// Space in that inventory to move into (its root if left out)
+        #[arg(long)]
+        into: Option<String>,
+
+        /// Record this owner in the `owner` metadata of every moved item
+        #[arg(long)]
+        owner: Option<String>,
+    },
+
+    /// Check that the items in a space are there, one by one, and list what
+    /// is not at the end
+    ///
+    /// Answer y (or Enter) if an item is there, n if it is missing, m if it
+    /// is somewhere else, which asks where and moves it there, or s to skip
+    /// it for now. q or the end of input (Ctrl-D) pauses the audit; running
+    /// it again resumes it with the items not checked yet.
+    Audit {
+        /// Space to audit (the current space if left out)
+        space: Option<String>,
+
+        /// Forget an unfinished audit of the space and start over
+        #[arg(long)]
+        restart: bool,
+    },
+
+    /// List the items the last audit did not find, most valuable first
+    Missing {
+        space: Option<String>,
+
+        /// Also list the items no audit found in this many days, or ever
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+    },
+
+    /// Set the icon and colour a space, or with --item an item, is shown with
+    Style {
+        name: String,
+
+        /// Style an item instead of a space
+        #[arg(long)]
+        item: bool,
+
+        /// Emoji or glyph shown before the name; empty to remove
+        #[arg(long)]
+        icon: Option<String>,
+
+        /// Colour name (red, green, yellow, blue, magenta, cyan, white, black,
+        /// gray) or #rrggbb; empty to remove
+        #[arg(long)]
+        color: Option<String>,
+    },
+
+    /// Rename items and spaces whose names are empty, have surrounding
+    /// whitespace or contain '/'
+    RepairNames {
+        /// Only print the renames without saving them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Apply a JSON Patch (RFC 6902) to the data, all of it or nothing
+    Patch {
+        /// File holding the patch, or `-` for standard input
+        patch: PathBuf,
+
+        /// Print what the patch would add, change and remove without saving it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// List the tags in use with how many items carry each, or rename or
+    /// merge tags
+    Tags {
+        #[command(subcommand)]
+        command: Option<TagsCommand>,
+    },
+
+    /// Add and remove tags on every item matching a query, printing the
+    /// items that change
+    TagBulk {
+        /// Items to change, e.g. `name:vase` or `tag:misc`
+        #[arg(long, value_name = "QUERY")]
+        filter: Query,
+
+        /// Tag to add; can be repeated
+        #[arg(long)]
+        add: Vec<String>,
+
+        /// Tag to remove; can be repeated
+        #[arg(long)]
+        remove: Vec<String>,
+
+        /// Only print the items that would change without saving
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Print the JSON Schema of the data file format
+    #[cfg(feature = "schema")]
+    Schema,
+
+    /// Check that a data file, or dir backend directory, is valid puha data:
+    /// no unknown fields, invalid or duplicate names or misplaced root flags
+    Validate { file: PathBuf },
+
+    /// List the profiles defined in the configuration
+    Profiles,
+
+    /// Set puha up: create the root space and record where the data is kept
+    /// in the configuration, asking for whatever is not given
+    Init {
+        /// Name of the root space
+        name: Option<String>,
+    },
+
+    /// Write a random tree of made-up spaces and items to a new data file,
+    /// for demos, benchmarks and bug reports
+    GenSample {
+        /// Number of spaces below the root
+        #[arg(long, default_value_t = 50)]
+        spaces: usize,
+
+        #[arg(long, default_value_t = 2000)]
+        items: usize,
+
+        /// The same seed always gives the same tree
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+
+    /// Count the spaces, items and metadata entries in a space
+    Stats {
+        space: Option<String>,
+
+        /// Also estimate how much memory the tree takes up once loaded
+        #[arg(long)]
+        memory: bool,
+    },
+
+    /// Run commands interactively, with line editing, history and completion
+    #[cfg(feature = "shell")]
+    Shell,
+
+    /// Run the add and move commands published to the `commands` subtopic
+    /// of the configured MQTT topic
+    #[cfg(feature = "mqtt")]
+    MqttListen,
+
+    /// Check the configuration, data and state, and suggest fixes for what
+    /// is wrong
+    Doctor,
+
+    /// Information about puha itself
+    #[command(name = "self")]
+    SelfInfo {
+        #[command(subcommand)]
+        command: SelfCommand,
+    },
+
+    /// An alias or macro from the configuration
+    #[command(external_subcommand)]
+    External(Vec<String>),
+
+    /// Set the current space used when a command's space is left out, or
+    /// go back to the root without an argument
+    Cd { space: Option<String> },
+
+    /// Print the path of the current space
+    Pwd,
 }
 
-fn print_tree(space: &Space, indent: usize) {
-    let padding = "  ".repeat(indent);
-    println!("{}{}", padding, space.name());
-    for item in space.items() {
-        println!("{}  - {}", padding, item.name());
-    }
-    for child in space.spaces() {
-        print_tree(child, indent + 1);
+/// Names of the items at or below `space` matching any of `patterns`.
+#[cfg(feature = "regex")]
+fn select_by_regex(
+    space: &Space,
+    patterns: &[String],
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    // Checked one by one first, so that no pattern completes another.
+    for pattern in patterns {
+        regex::Regex::new(pattern)?;
     }
+    let any: Vec<String> = patterns.iter().map(|p| format!("(?:{p})")).collect();
+    let regex = regex::Regex::new(&any.join("|"))?;
+    Ok(search::find_regex(space, &regex)
+        .into_iter()
+        .filter_map(|entry| match entry.target {
+            Target::Item(item) => Some(item.name().to_string()),
+            Target::Space(_) => None,
+        })
+        .collect())
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+/// The entries at or below `root` whose name matches `name`.
+fn find_by_name<'a>(root: &'a Space, name: &str, matching: &MatchOptions) -> Vec<Entry<'a>> {
+    let mut found = search::entries(root);
+    found.retain(|entry| matching.matches(entry.target.name(), name));
+    found
+}
 
-    match cli.command {
-        Commands::NewRoot { name } => {
-            let root = Space::builder().name(name).root(true).build();
-            root.save_to_file(cli.file)?;
-        }
-        Commands::ShowTree { name } => {
-            let root = Space::from_file(&cli.file)?;
-            let target = if let Some(n) = name {
-                root.find_space(&n).ok_or("space not found")?
-            } else {
-                &root
-            };
-            print_tree(target, 0);
-        }
-        Commands::AddItem {
-            space,
-            item,
-            description,
-        } => {
-            let mut root = Space::from_file(&cli.file)?;
-            let target = root
-                .find_space_mut(&space)
-                .ok_or("space not found")?;
-            let item = Item::builder().name(item).description(description).build();
-            target.add_item(item);
-            root.save_to_file(cli.file)?;
-        }
-        Commands::AddSpace { parent, child } => {
-            let mut root = Space::from_file(&cli.file)?;
-            let target = root
-                .find_space_mut(&parent)
-                .ok_or("space not found")?;
-            let new_space = Space::builder().name(child).build();
-            target.add_space(new_space);
-            root.save_to_file(cli.file)?;
+/// Read names from standard input, separated by newlines or, with `null`,
+/// by NUL. Empty records are skipped.
+fn read_names(null: bool) -> std::io::Result<Vec<String>> {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    let names: Vec<&str> = if null {
+        input.split('\0').collect()
+    } else {
+        input.lines().collect()
+    };
+    Ok(names
+        .into_iter()
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Print a search hit followed by `end`, highlighting the matched text on
+/// terminals. `description` is given for a match in the description, which
+/// `spans` then point into; otherwise they point into `name`.
+fn print_hit(
+    path: &[&str],
+    name: &str,
+    description: Option<&str>,
+    spans: &[std::ops::Range<usize>],
+    highlight: bool,
+    end: char,
+) {
+    let path: String = path.iter().map(|p| format!("{p}/")).collect();
+    let text = match description {
+        Some(description) => {
+            print!("{path}{name}: ");
+            description
         }
-        Commands::ListItems { space } => {
-            let root = Space::from_file(&cli.file)?;
-            let target = root.find_space(&space).ok_or("space not found")?;
-            for item in target.items() {
-                println!("{}", item.name());
-            }
+        None => {
+            print!("{path}");
+            name
         }
-        Commands::List { space } => {
-            let root = Space::from_file(&cli.file)?;
-            let target = root.find_space(&space).ok_or("space not found")?;
-            for item in target.items() {
-                println!("item: {}", item.name());
-            }
-            for sp in target.spaces() {
-                println!("space: {}", sp.name());
-            }
+    };
+    let mut last = 0;
+    for span in spans {
+        print!("{}", &text[last..span.start]);
+        if highlight {
+            print!("\x1b[1;33m{}\x1b[0m", &text[span.clone()]);
+        } else {
+            print!("{}", &text[span.clone()]);
         }
-        Commands::MoveItems { from, to, items } => {
-            let mut root = Space::from_file(&cli.file)?;
-            let mut removed = Vec::new();
-            {
-                let source = root
-                    .find_space_mut(&from)
-                    .ok_or("source space not found")?;
-                for name in &items {
-                    if let Some(item) = source.remove_item(name) {
-                        removed.push(item);
-                    }
-                }
-            }
-            let dest = root
-                .find_space_mut(&to)
-                .ok_or("destination space not found")?;
-            for item in removed {
-                dest.add_item(item);
-            }
-            root.save_to_file(cli.file)?;
+        last = span.end;
+    }
+    print!("{}{end}", &text[last..]);
+}
+
+#[cfg(any(feature = "import-grocy", feature = "import-home-assistant"))]
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ImportSource {
+    #[cfg(feature = "import-grocy")]
+    Grocy,
+    #[cfg(feature = "import-home-assistant")]
+    HomeAssistant,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum TreeOutput {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ListFormat {
+    Markdown,
+    Csv,
+}
+
+#[cfg(any(feature = "import-grocy", feature = "import-home-assistant"))]
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum IdChoice {
+    None,
+    Random,
+    Deterministic,
+}
+
+#[cfg(any(feature = "import-grocy", feature = "import-home-assistant"))]
+impl From<IdChoice> for IdStrategy {
+    fn from(choice: IdChoice) -> Self {
+        match choice {
+            IdChoice::None => IdStrategy::None,
+            IdChoice::Random => IdStrategy::Random,
+            IdChoice::Deterministic => IdStrategy::Deterministic,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum SelfCommand {
+    /// Show how often each command ran and how long it took, as recorded
+    /// with the `usage_stats` configuration setting
+    Stats,
+}
+
+#[derive(Subcommand)]
+enum SynonymsCommand {
+    /// List all synonym groups
+    List,
+
+    /// Add a group, e.g. "torch = flashlight"
+    Add { group: String },
+
+    /// Remove every group containing a word
+    Remove { word: String },
+}
+
+#[derive(Subcommand)]
+enum TagsCommand {
+    /// Rename a tag on every item; the new name must not be in use yet
+    Rename { old: String, new: String },
+
+    /// Replace tag A with tag B on every item
+    Merge { a: String, b: String },
+}
+
+#[derive(Subcommand)]
+enum KitCommand {
+    /// List all kits
+    List,
+
+    /// Create an empty kit
+    Create { name: String },
+
+    /// Add items to a kit
+    Add {
+        name: String,
+
+        #[arg(required = true)]
+        items: Vec<String>,
+    },
+
+    /// Remove an item from a kit
+    Remove { name: String, item: String },
+
+    /// Delete a kit, leaving its items alone
+    Delete { name: String },
+
+    /// List where a kit's items are, and fail if any are gone
+    Check { name: String },
+}
+
+#[derive(Subcommand)]
+enum ViewCommand {
+    /// List all views
+    List,
+
+    /// Create an empty view
+    Create { name: String },
+
+    /// Add items to a group of a view, creating the group if needed
+    Add {
+        name: String,
+
+        /// Path of the group, e.g. `kitchen/tools`; empty for the top
+        group: String,
+
+        #[arg(required = true)]
+        items: Vec<String>,
+    },
+
+    /// Remove an item from every group of a view
+    Remove { name: String, item: String },
+
+    /// Delete a view, leaving its items alone
+    Delete { name: String },
+}
+
+/// ANSI escape sequence selecting `color`, if it is a known colour.
+fn ansi_color(color: &str) -> Option<String> {
+    let code = match color {
+        "black" => "30",
+        "red" => "31",
+        "green" => "32",
+        "yellow" => "33",
+        "blue" => "34",
+        "magenta" => "35",
+        "cyan" => "36",
+        "white" => "37",
+        "gray" | "grey" => "90",
+        hex => {
+            let hex = hex.strip_prefix('#').filter(|h| h.len() == 6)?;
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            let (r, g, b) = (channel(0)?, channel(2)?, channel(4)?);
+            return Some(format!("\x1b[38;2;{r};{g};{b}m"));
+        }
+    };
+    Some(format!("\x1b[{code}m"))
+}
+
+/// `name` with its icon, coloured on terminals.
+fn label(name: &str, icon: Option<&str>, color: Option<&str>, colored: bool) -> String {
+    let mut out = String::new();
+    if let Some(icon) = icon {
+        out.push_str(icon);
+        out.push(' ');
+    }
+    match color.and_then(ansi_color).filter(|_| colored) {
+        Some(escape) => out.push_str(&format!("{escape}{name}\x1b[0m")),
+        None => out.push_str(name),
+    }
+    out
+}
+
+fn print_tree(space: &Space, indent: usize, collation: Option<&Collation>, colored: bool) {
+    let padding = "  ".repeat(indent);
+    let name = label(space.name(), space.icon(), space.color(), colored);
+    let locked = if space.locked() { " (locked)" } else { "" };
+    println!("{padding}{name}{locked}");
+    let mut items: Vec<&Item> = space.items().iter().collect();
+    let mut spaces: Vec<&Space> = space.spaces().iter().collect();
+    if let Some(collation) = collation {
+        collation.sort_by(&mut items, |item| item.name());
+        collation.sort_by(&mut spaces, |space| space.name());
+    }
+    for item in items {
+        let name = label(item.name(), item.icon(), item.color(), colored);
+        println!("{padding}  - {name}");
+    }
+    for child in spaces {
+        print_tree(child, indent + 1, collation, colored);
+    }
+}
+
+/// Print a group of a view like [`print_tree`] prints a space, looking up
+/// its items in `items`.
+fn print_group(
+    name: &str,
+    group: &Group,
+    indent: usize,
+    items: &ItemsById,
+    collation: Option<&Collation>,
+    colored: bool,
+) {
+    let padding = "  ".repeat(indent);
+    println!("{padding}{name}");
+    let mut found: Vec<&Item> = Vec::new();
+    let mut missing = 0;
+    for id in group.items() {
+        match items.get(id) {
+            Some(item) => found.push(item),
+            None => missing += 1,
+        }
+    }
+    let mut groups: Vec<(&String, &Group)> = group.groups().iter().collect();
+    if let Some(collation) = collation {
+        collation.sort_by(&mut found, |item| item.name());
+        collation.sort_by(&mut groups, |(name, _)| name.as_str());
+    }
+    for item in found {
+        let name = label(item.name(), item.icon(), item.color(), colored);
+        println!("{padding}  - {name}");
+    }
+    match missing {
+        0 => {}
+        1 => println!("{padding}  - (1 deleted item)"),
+        n => println!("{padding}  - ({n} deleted items)"),
+    }
+    for (name, group) in groups {
+        print_group(name, group, indent + 1, items, collation, colored);
+    }
+}
+
+/// Copy the stored `file` from `from` into `to`. Files outside the store are
+/// left where they are.
+fn copy_asset(from: &Assets, to: &Assets, file: &str) -> std::io::Result<()> {
+    let path = from.resolve(file);
+    if Path::new(file).is_relative() && path.is_file() {
+        to.add(&path)?;
+    }
+    Ok(())
+}
+
+/// Write `bytes` to the file at `out`, or to standard output for `-`.
+#[cfg(any(feature = "pdf", feature = "xlsx"))]
+fn write_output(out: &Path, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    if out != Path::new("-") {
+        std::fs::write(out, bytes).map_err(|e| format!("{}: {e}", out.display()))?;
+        return Ok(());
+    }
+    let mut stdout = std::io::stdout().lock();
+    if stdout.is_terminal() {
+        return Err("not writing a binary file to the terminal; redirect the output".into());
+    }
+    stdout.write_all(bytes)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The text of the file at `path`, or of standard input for `-`.
+fn read_input(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    if path == Path::new("-") {
+        let mut data = String::new();
+        std::io::stdin().read_to_string(&mut data)?;
+        return Ok(data);
+    }
+    Ok(std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?)
+}
+
+/// Ask `question` on stdout and read a line of answer, trimmed, or `None`
+/// at the end of input.
+fn prompt(question: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    print!("{question}");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer)? == 0 {
+        println!();
+        return Ok(None);
+    }
+    Ok(Some(answer.trim().to_string()))
+}
+
+/// `text` parsed, or `None` if it is empty, for options that remove a field
+/// when given an empty value.
+fn parse_or_clear<T: FromStr>(text: &str) -> Result<Option<T>, T::Err> {
+    if text.is_empty() {
+        Ok(None)
+    } else {
+        text.parse().map(Some)
+    }
+}
+
+/// `spec`, or else the path of the current space, or else the root.
+fn space_or_current(spec: Option<String>, current: Option<&[String]>, root: &Space) -> String {
+    spec.unwrap_or_else(|| match current {
+        Some(segments) => path::format(&segments.iter().map(String::as_str).collect::<Vec<_>>()),
+        None => path::quote(root.name()).into_owned(),
+    })
+}
+
+// Space and item arguments are either a plain name, looked up anywhere in the
+// tree, a path from the root such as `home/"A/B closet"/box`, or a path
+// relative to the current space such as `../shelf`.
+
+/// Split `spec` into path segments, resolving relative paths against the
+/// current space, or the root if there is none.
+fn segments(
+    root: &Space,
+    spec: &str,
+    current: Option<&[String]>,
+) -> Result<Vec<String>, PathError> {
+    match current {
+        Some(current) => path::resolve(spec, current),
+        None => path::resolve(spec, &[root.name()]),
+    }
+}
+
+fn find_space<'a>(
+    root: &'a Space,
+    spec: &str,
+    current: Option<&[String]>,
+    matching: &MatchOptions,
+) -> Result<Option<&'a Space>, PathError> {
+    Ok(locate_space(root, spec, current, matching)?.map(|(_, space)| space))
+}
+
+/// Like [`find_space`], also returning the names of the enclosing spaces.
+fn locate_space<'a>(
+    root: &'a Space,
+    spec: &str,
+    current: Option<&[String]>,
+    matching: &MatchOptions,
+) -> Result<Option<(Vec<&'a str>, &'a Space)>, PathError> {
+    Ok(match segments(root, spec, current)?.as_slice() {
+        [name] => root.locate_space_with(name, matching),
+        segments => root.locate_at(segments, matching),
+    })
+}
+
+fn find_space_mut<'a>(
+    root: &'a mut Space,
+    spec: &str,
+    current: Option<&[String]>,
+    matching: &MatchOptions,
+) -> Result<Option<&'a mut Space>, PathError> {
+    Ok(match segments(root, spec, current)?.as_slice() {
+        [name] => root.find_space_mut_with(name, matching),
+        segments => root.space_at_mut(segments, matching),
+    })
+}
+
+fn find_item_mut<'a>(
+    root: &'a mut Space,
+    spec: &str,
+    current: Option<&[String]>,
+    matching: &MatchOptions,
+) -> Result<Option<&'a mut Item>, PathError> {
+    Ok(match segments(root, spec, current)?.as_slice() {
+        [name] => root.find_item_mut_with(name, matching),
+        segments => root.item_at_mut(segments, matching),
+    })
+}
+
+fn find_item<'a>(
+    root: &'a Space,
+    spec: &str,
+    current: Option<&[String]>,
+    matching: &MatchOptions,
+) -> Result<Option<&'a Item>, PathError> {
+    Ok(match segments(root, spec, current)?.as_slice() {
+        [name] => root.find_item_with(name, matching),
+        segments => segments.split_last().and_then(|(name, parents)| {
+            root.space_at(parents, matching)?
+                .items()
+                .iter()
+                .find(|i| matching.matches(i.name(), name))
+        }),
+    })
+}
+
+fn set_locked(
+    store: &Store,
+    spec: &str,
+    locked: bool,
+    current: Option<&[String]>,
+    matching: &MatchOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut root = store.load()?;
+    find_space_mut(&mut root, spec, current, matching)?
+        .ok_or("space not found")?
+        .set_locked(locked);
+    store.save(&root)
+}
+
+fn remove_space(
+    root: &mut Space,
+    spec: &str,
+    current: Option<&[String]>,
+    matching: &MatchOptions,
+) -> Result<Option<Space>, PathError> {
+    Ok(match segments(root, spec, current)?.as_slice() {
+        [name] => root.remove_space_with(name, matching),
+        segments => root.remove_space_at(segments, matching),
+    })
+}
+
+/// The configured storage together with the loading options.
+struct Store {
+    storage: Box<dyn Storage>,
+    /// Where the storage keeps the tree.
+    path: PathBuf,
+    strict: bool,
+    /// Skip the check for changes inside locked spaces.
+    unlock: bool,
+    limits: Limits,
+    /// Search index kept up to date on save, if it exists.
+    #[cfg(feature = "search-index")]
+    index: PathBuf,
+    /// When set, saving only keeps the tree here until [`Store::commit`].
+    pending: Option<RefCell<Option<Space>>>,
+    /// The tree as last loaded, to check the saved one against for changes
+    /// inside locked spaces and to tell what changed. `Some(None)` once
+    /// loaded if neither is needed, which saves copying a large tree.
+    loaded: RefCell<Option<Option<Space>>>,
+    /// Broker changes are published to.
+    #[cfg(feature = "mqtt")]
+    mqtt: Option<config::Mqtt>,
+    /// Where attached files are kept.
+    assets: Assets,
+}
+
+impl Store {
+    /// Whether the tree is read from standard input and written to standard
+    /// output.
+    fn is_stdio(&self) -> bool {
+        self.path == Path::new("-")
+    }
+
+    /// Where to say what a command did: standard error when the tree is
+    /// written to standard output, so the two do not mix.
+    fn status(&self) -> Box<dyn Write> {
+        if self.is_stdio() {
+            Box::new(std::io::stderr())
+        } else {
+            Box::new(std::io::stdout())
+        }
+    }
+
+    fn load(&self) -> Result<Space, Box<dyn std::error::Error>> {
+        if let Some(pending) = &self.pending
+            && let Some(root) = &*pending.borrow()
+        {
+            return Ok(root.clone());
+        }
+        if !self.is_stdio() && !self.path.exists() {
+            return Err(format!(
+                "{} does not exist; run `puha init` to set puha up, or `puha new-root <name>` \
+                 to start a tree there",
+                self.path.display()
+            )
+            .into());
+        }
+        // Spelled out, as the message carries the advice.
+        self.limits
+            .check_size(&self.path)
+            .map_err(|e| e.to_string())?;
+        let root = if self.strict {
+            self.storage.load_strict()?
+        } else {
+            self.storage.load()?
+        };
+        self.limits.check_tree(&root).map_err(|e| e.to_string())?;
+        let keep = !self.unlock && lock::any_locked(&root);
+        #[cfg(feature = "mqtt")]
+        let keep = keep || self.mqtt.is_some();
+        *self.loaded.borrow_mut() = Some(keep.then(|| root.clone()));
+        Ok(root)
+    }
+
+    fn save(&self, root: &Space) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(pending) = &self.pending {
+            *pending.borrow_mut() = Some(root.clone());
+            return Ok(());
+        }
+        self.write(root)
+    }
+
+    /// Save the tree kept back by [`Store::save`], if any.
+    fn commit(&self) -> Result<(), Box<dyn std::error::Error>> {
+        match self.pending.as_ref().and_then(|p| p.borrow_mut().take()) {
+            Some(root) => self.write(&root),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&self, root: &Space) -> Result<(), Box<dyn std::error::Error>> {
+        // Commands that replace the whole tree never load it.
+        let old = match self.loaded.take() {
+            Some(old) => old,
+            None if self.path.exists() => self.storage.load().ok(),
+            None => None,
+        };
+        if !self.unlock
+            && let Some(old) = &old
+        {
+            lock::check_changes(old, root)
+                .map_err(|e| format!("{e}; unlock it first or pass --unlock"))?;
+        }
+        self.storage.save(root)?;
+        #[cfg(feature = "search-index")]
+        if self.index.exists() {
+            let mut index = SearchIndex::from_file(&self.index)?;
+            index.update(root);
+            index.save_to_file(&self.index)?;
+        }
+        #[cfg(feature = "mqtt")]
+        if let (Some(broker), Some(old)) = (&self.mqtt, &old) {
+            let diff = TreeDiff::between(old, root);
+            // The change is saved either way, so failing to announce it is
+            // only worth a warning.
+            if !diff.is_empty()
+                && let Err(e) = mqtt::publish(broker, &diff)
+            {
+                eprintln!("warning: could not publish changes to MQTT: {e}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where the search index for the data at `file` is kept.
+#[cfg(feature = "search-index")]
+fn index_path(file: &str, backend: Backend) -> PathBuf {
+    match backend {
+        Backend::Json => Path::new(file).with_extension("index.json"),
+        Backend::Dir => Path::new(file).join("search-index.json"),
+    }
+}
+
+/// `<file>.assets` for the JSON backend and `.assets` inside the directory
+/// for the dir backend, where no space's directory can be called that.
+fn assets_path(file: &str, backend: Backend) -> PathBuf {
+    match backend {
+        Backend::Json => Path::new(file).with_extension("assets"),
+        Backend::Dir => {
+            let path = Path::new(file).join(".assets");
+            // Move the store from where it used to be, unless that is now
+            // the directory of a space called `assets`.
+            let old = Path::new(file).join("assets");
+            if !path.exists() && old.is_dir() && !old.join(storage::SPACE_FILE).exists() {
+                let _ = std::fs::rename(&old, &path);
+            }
+            path
+        }
+    }
+}
+
+/// Expand an alias or a macro from the configuration into the command lines
+/// to run. Any other command line, parsed from `argv`, is run as it is.
+fn expand(
+    cli: Cli,
+    argv: &[String],
+    config: &Config,
+) -> Result<Vec<Cli>, Box<dyn std::error::Error>> {
+    let Commands::External(args) = &cli.command else {
+        return Ok(vec![cli]);
+    };
+    // The options before the command apply to every command line.
+    let prefix = &argv[..argv.len() - args.len()];
+    let parse = |words: Vec<String>| Cli::try_parse_from(prefix.iter().cloned().chain(words));
+    let (name, rest) = args.split_first().ok_or("missing command")?;
+    if let Some(alias) = config.aliases.get(name) {
+        let mut words = config::split_words(alias)?;
+        words.extend(rest.iter().cloned());
+        let cli = parse(words)?;
+        if let Commands::External(args) = &cli.command {
+            return Err(format!("alias '{name}' runs unknown command '{}'", args[0]).into());
+        }
+        return Ok(vec![cli]);
+    }
+    if let Some(steps) = config.macros.get(name) {
+        if steps.is_empty() {
+            return Err(format!("macro '{name}' has no commands").into());
+        }
+        let mut clis = Vec::new();
+        for step in steps {
+            let words = config::split_words(step)?
+                .iter()
+                .map(|word| config::substitute(word, rest))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("macro '{name}': {e}"))?;
+            let cli = parse(words)?;
+            if let Commands::External(args) = &cli.command {
+                return Err(format!("macro '{name}' runs unknown command '{}'", args[0]).into());
+            }
+            clis.push(cli);
+        }
+        return Ok(clis);
+    }
+    Err(format!("unknown command '{name}'").into())
+}
+
+/// What commands share when several run together.
+struct Context {
+    /// The command line puha was started with.
+    #[cfg(any(feature = "shell", feature = "mqtt"))]
+    argv: Vec<String>,
+    config: Config,
+    store: Store,
+    state: State,
+    state_path: Option<PathBuf>,
+    /// Key of the data file in the state.
+    state_key: String,
+    state_changed: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let argv: Vec<String> = std::env::args().collect();
+    let cli = Cli::parse_from(&argv);
+    // The doctor reports what cannot be loaded instead of failing on it,
+    // and init creates the configuration.
+    let doctor = matches!(cli.command, Commands::Doctor);
+    let init = matches!(cli.command, Commands::Init { .. });
+    let mut config = match Config::load(cli.config.as_deref()) {
+        Err(_) if doctor || init => Config::default(),
+        config => config?,
+    };
+    let steps = expand(cli, &argv, &config).map_err(|e| match e.downcast::<clap::Error>() {
+        Ok(e) => e.exit(),
+        Err(e) => e,
+    })?;
+    let cli = &steps[0];
+    if let Some(locale) = cli
+        .locale
+        .clone()
+        .or_else(|| std::env::var("PUHA_LOCALE").ok().filter(|l| !l.is_empty()))
+    {
+        config.locale = Some(locale);
+    }
+    let mut save_options = config.save.clone();
+    if cli.compact {
+        save_options.pretty = false;
+    }
+    if let Some(indent) = cli.indent {
+        save_options.pretty = true;
+        save_options.indent = indent;
+    }
+    if cli.sort_keys {
+        save_options.sort_keys = true;
+    }
+    // Explicit --file and --backend win over the profile's.
+    let profile = match &cli.profile {
+        Some(name) => Some(
+            config
+                .profiles
+                .get(name)
+                .ok_or_else(|| format!("unknown profile '{name}'"))?,
+        ),
+        None => None,
+    };
+    let file = cli
+        .file
+        .clone()
+        .or_else(|| profile.map(|p| p.file.clone()))
+        .or_else(|| config.file.clone())
+        .unwrap_or_else(|| "space.json".to_string());
+    let backend = cli
+        .backend
+        .or(profile.map(|p| p.backend))
+        .or(config.backend)
+        .unwrap_or_default();
+    let state_path = state::default_path();
+    let state = match &state_path {
+        // The doctor reports a state file that cannot be loaded itself.
+        Some(path) if doctor => State::load(path).unwrap_or_default(),
+        Some(path) => state::load_json_or_reset(path),
+        None => State::default(),
+    };
+    let store = Store {
+        storage: if file == "-" {
+            Box::new(Stdio::new().with_options(save_options))
+        } else {
+            backend.open_with(&file, save_options)
+        },
+        path: PathBuf::from(&file),
+        strict: cli.strict,
+        unlock: cli.unlock,
+        limits: config.limits.clone(),
+        #[cfg(feature = "search-index")]
+        index: index_path(&file, backend),
+        pending: None,
+        loaded: RefCell::default(),
+        #[cfg(feature = "mqtt")]
+        mqtt: config.mqtt.clone(),
+        assets: Assets::new(assets_path(&file, backend)),
+    };
+    let name = command_name(&argv);
+    let mut cx = Context {
+        #[cfg(any(feature = "shell", feature = "mqtt"))]
+        argv,
+        config,
+        store,
+        state,
+        state_path,
+        state_key: state::key(&file),
+        state_changed: false,
+    };
+    run_tracked(name.as_deref(), steps, &mut cx)
+}
+
+/// What in `command` reads standard input, if anything does.
+fn stdin_use(command: &Commands) -> Option<&'static str> {
+    match command {
+        Commands::Patch { patch, .. } if patch == Path::new("-") => Some("patch -"),
+        Commands::ImportSpace { file, .. } if file == Path::new("-") => Some("import-space -"),
+        Commands::MoveItems { stdin: true, .. } => Some("move-items --stdin"),
+        Commands::Intake { .. } => Some("intake"),
+        Commands::Audit { .. } => Some("audit"),
+        Commands::GcAttachments { dry_run: false } => Some("gc-attachments"),
+        #[cfg(feature = "shell")]
+        Commands::Shell => Some("shell"),
+        _ => None,
+    }
+}
+
+/// The name of the command `argv` runs, as typed.
+fn command_name(argv: &[String]) -> Option<String> {
+    let matches = Cli::command().try_get_matches_from(argv).ok()?;
+    matches.subcommand_name().map(String::from)
+}
+
+/// Like [`run_all`], also recording the run under `name` in the usage
+/// statistics if the configuration enables them.
+fn run_tracked(
+    name: Option<&str>,
+    steps: Vec<Cli>,
+    cx: &mut Context,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    let result = run_all(steps, cx);
+    // A shell session is counted by the commands run in it.
+    if let Some(name) = name.filter(|&name| cx.config.usage_stats && name != "shell") {
+        usage::track(name, started.elapsed(), result.is_ok());
+    }
+    result
+}
+
+/// Run the command lines an invocation expanded to. The steps of a macro
+/// change the tree together or not at all, and a tree written to standard
+/// output is written once, however often a command saves it.
+fn run_all(steps: Vec<Cli>, cx: &mut Context) -> Result<(), Box<dyn std::error::Error>> {
+    if steps.len() > 1 || cx.store.is_stdio() {
+        cx.store.pending = Some(RefCell::default());
+    }
+    let result = steps
+        .into_iter()
+        .try_for_each(|step| run(step, cx))
+        .and_then(|()| cx.store.commit());
+    cx.store.pending = None;
+    result?;
+    if cx.state_changed {
+        let state_path = cx
+            .state_path
+            .as_ref()
+            .ok_or("no state directory; set $PUHA_STATE")?;
+        cx.state.save(state_path)?;
+        cx.state_changed = false;
+    }
+    Ok(())
+}
+
+/// Run a single command line.
+fn run(cli: Cli, cx: &mut Context) -> Result<(), Box<dyn std::error::Error>> {
+    if cx.store.is_stdio()
+        && let Some(what) = stdin_use(&cli.command)
+    {
+        return Err(
+            format!("{what} reads standard input, which holds the tree with --file -").into(),
+        );
+    }
+    #[cfg(feature = "shell")]
+    if let Commands::Shell = cli.command {
+        return shell::run(cx);
+    }
+    #[cfg(feature = "mqtt")]
+    if let Commands::MqttListen = cli.command {
+        return mqtt::listen(cx);
+    }
+    if let Commands::Doctor = cli.command {
+        return doctor::run(cx, cli.config.as_deref());
+    }
+    if let Commands::Init { name } = cli.command {
+        let answers = init::Answers {
+            name,
+            file: cli.file,
+            backend: cli.backend,
+        };
+        return init::run(answers, cli.config.as_deref(), cx.config.save.clone());
+    }
+    let Context {
+        config,
+        store,
+        state,
+        state_key,
+        state_changed,
+        ..
+    } = cx;
+    let current = state.current.get(state_key.as_str()).cloned();
+    let current = current.as_deref();
+    let matching = MatchOptions {
+        ignore_case: cli.ignore_case,
+        ignore_accents: cli.ignore_accents,
+    };
+    let end = if cli.print0 { '\0' } else { '\n' };
+    let collation = match (cli.sort, config.locale.as_deref()) {
+        (false, _) => None,
+        // Without the collation feature the locale only sets how numbers
+        // and dates are written.
+        (true, Some(locale)) if cfg!(feature = "collation") => Some(Collation::for_locale(locale)?),
+        (true, _) => Some(Collation::new()),
+    };
+    let formats = config
+        .locale
+        .as_deref()
+        .map(Formats::for_locale)
+        .unwrap_or_default();
+
+    match cli.command {
+        Commands::NewRoot { name } => {
+            let root = Space::builder().name(name).root(true).try_build()?;
+            store.save(&root)?;
+        }
+        Commands::ShowTree {
+            view: Some(view), ..
+        } => {
+            let root = store.load()?;
+            let group = root
+                .settings()
+                .views
+                .get(&view)
+                .ok_or_else(|| format!("no view named '{view}'"))?;
+            let items = views::items_by_id(&root);
+            let colored = std::io::stdout().is_terminal();
+            print_group(&view, group, 0, &items, collation.as_ref(), colored);
+        }
+        Commands::ShowTree {
+            name,
+            view: None,
+            output,
+        } => {
+            let root = store.load()?;
+            let name = space_or_current(name, current, &root);
+            let target = find_space(&root, &name, current, &matching)?.ok_or("space not found")?;
+            match output {
+                TreeOutput::Text => {
+                    let colored = std::io::stdout().is_terminal();
+                    print_tree(target, 0, collation.as_ref(), colored);
+                }
+                TreeOutput::Json => println!("{}", serde_json::to_string_pretty(target)?),
+            }
+        }
+        Commands::AddItem {
+            mut args,
+            quantity,
+            min_quantity,
+            no_dedupe_check,
+        } => {
+            let mut root = store.load()?;
+            let description = args.pop().unwrap_or_default();
+            let item = args.pop().unwrap_or_default();
+            let space = space_or_current(args.pop(), current, &root);
+            // Ask before adding what may be an item the tree already has.
+            let similar: Option<Vec<String>> = search::similar_items(&root, &item)
+                .first()
+                .filter(|_| !no_dedupe_check)
+                .map(|entry| {
+                    let segments = entry.path.iter().copied().chain([entry.target.name()]);
+                    segments.map(str::to_string).collect()
+                });
+            if let Some(segments) = similar {
+                let existing = root
+                    .item_at_mut(&segments, &MatchOptions::exact())
+                    .ok_or("item not found")?;
+                let (name, parents) = segments.split_last().expect("an item and its spaces");
+                let parents: Vec<&str> = parents.iter().map(String::as_str).collect();
+                let count = existing
+                    .quantity()
+                    .map(|n| format!(" (qty {n})"))
+                    .unwrap_or_default();
+                let warning = format!(
+                    "similar item '{name}' already exists in {}{count}",
+                    path::format(&parents)
+                );
+                if !std::io::stdin().is_terminal() {
+                    eprintln!("warning: {warning}");
+                } else {
+                    let question = format!("{warning}; add anyway / increment instead? [a/i/N] ");
+                    match prompt(&question)?.unwrap_or_default().as_str() {
+                        "a" | "A" => {}
+                        "i" | "I" => {
+                            let total = existing
+                                .quantity()
+                                .unwrap_or(1)
+                                .checked_add(quantity.unwrap_or(1))
+                                .ok_or_else(|| {
+                                    format!("'{name}' would have more than {} items", u32::MAX)
+                                })?;
+                            existing.set_quantity(Some(total));
+                            store.save(&root)?;
+                            writeln!(
+                                store.status(),
+                                "'{name}' in {} now has {total}",
+                                path::format(&parents)
+                            )?;
+                            return Ok(());
+                        }
+                        _ => return Err("item not added".into()),
+                    }
+                }
+            }
+            let limits = root.settings().limits.clone();
+            let target =
+                find_space_mut(&mut root, &space, current, &matching)?.ok_or("space not found")?;
+            let mut item = Item::builder().name(item).description(description);
+            if let Some(quantity) = quantity {
+                item = item.quantity(quantity);
+            }
+            if let Some(min_quantity) = min_quantity {
+                item = item.min_quantity(min_quantity);
+            }
+            let item = item.try_build()?;
+            target
+                .try_add_item(item, &limits)
+                .map_err(|e| e.to_string())?;
+            store.save(&root)?;
+        }
+        Commands::SetQuantity {
+            item,
+            quantity,
+            min,
+        } => {
+            let mut root = store.load()?;
+            let target =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            if let Some(quantity) = quantity {
+                target.set_quantity(Some(quantity));
+            }
+            if let Some(min) = min {
+                target.set_min_quantity(Some(min).filter(|&min| min > 0));
+            }
+            store.save(&root)?;
+        }
+        Commands::Consume { item, n } => {
+            let mut root = store.load()?;
+            let target =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            let left = target
+                .consume(n, Timestamp::now())
+                .map_err(|e| e.to_string())?;
+            store.save(&root)?;
+            writeln!(store.status(), "{left} left")?;
+        }
+        Commands::Restock { item, n } => {
+            let mut root = store.load()?;
+            let target =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            let now = target
+                .restock(n, Timestamp::now())
+                .map_err(|e| e.to_string())?;
+            store.save(&root)?;
+            writeln!(store.status(), "{now} in stock")?;
+        }
+        Commands::Usage { item } => {
+            let mut root = store.load()?;
+            let target =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            if target.stock_changes().is_empty() {
+                println!("no recorded changes; use `puha consume` and `puha restock`");
+                return Ok(());
+            }
+            print!("{}", target.stock_chart(40));
+            if let Some(daily) = target.daily_use(Timestamp::now()) {
+                println!("uses {:.1} a week", daily * 7.0);
+                let left = target.quantity().unwrap_or(0);
+                if left > 0 {
+                    println!("runs out in about {:.0} days", f64::from(left) / daily);
+                }
+            }
+        }
+        Commands::ShoppingList { space, format } => {
+            let root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            let target = find_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            let list = ShoppingList::of(target);
+            match format {
+                ListFormat::Markdown => print!("{}", list.to_markdown()),
+                ListFormat::Csv => print!("{}", list.to_csv()),
+            }
+        }
+        Commands::Quick { text } => {
+            let quick = quick::parse(&text.join(" "))?;
+            let interactive = std::io::stdin().is_terminal();
+            let name = match quick.name {
+                name if !name.is_empty() => name,
+                _ if interactive => prompt("Item: ")?.unwrap_or_default(),
+                _ => return Err("no item name given".into()),
+            };
+            let mut root = store.load()?;
+            let space = match quick.destination {
+                Some(destination) => destination,
+                None => {
+                    let default = space_or_current(None, current, &root);
+                    let answer = if interactive {
+                        prompt(&format!("Space [{default}]: "))?.unwrap_or_default()
+                    } else {
+                        String::new()
+                    };
+                    if answer.is_empty() { default } else { answer }
+                }
+            };
+            let limits = root.settings().limits.clone();
+            let target =
+                find_space_mut(&mut root, &space, current, &matching)?.ok_or("space not found")?;
+            let mut item = Item::builder().name(name);
+            if let Some(quantity) = quick.quantity {
+                item = item.quantity(quantity);
+            }
+            for tag in quick.tags {
+                item = item.tag(tag);
+            }
+            let item = item.try_build()?;
+            target
+                .try_add_item(item, &limits)
+                .map_err(|e| e.to_string())?;
+            store.save(&root)?;
+        }
+        #[cfg(feature = "clipboard")]
+        Commands::AddFromClipboard { space } => {
+            let text = arboard::Clipboard::new()
+                .and_then(|mut clipboard| clipboard.get_text())
+                .map_err(|e| format!("cannot read the clipboard: {e}"))?;
+            let items = text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|name| Item::builder().name(name).try_build())
+                .collect::<Result<Vec<_>, _>>()?;
+            if items.is_empty() {
+                return Err("the clipboard holds no item names".into());
+            }
+            let mut root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            let limits = root.settings().limits.clone();
+            let target =
+                find_space_mut(&mut root, &space, current, &matching)?.ok_or("space not found")?;
+            let count = items.len();
+            for item in items {
+                target
+                    .try_add_item(item, &limits)
+                    .map_err(|e| e.to_string())?;
+            }
+            store.save(&root)?;
+            writeln!(store.status(), "added {count} items to {space}")?;
+        }
+        Commands::Intake { space, from } => {
+            let mut root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            find_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            let attached: HashSet<String> = search::entries(&root)
+                .into_iter()
+                .filter_map(|entry| match entry.target {
+                    Target::Item(item) => Some(item.attachments().to_vec()),
+                    Target::Space(_) => None,
+                })
+                .flatten()
+                .collect();
+            let offered = state.intake.entry(state_key.clone()).or_default();
+            let photos: Vec<PathBuf> = intake::photos(&from)?
+                .into_iter()
+                .filter(|photo| {
+                    let photo = photo.to_string_lossy();
+                    !offered.contains(photo.as_ref()) && !attached.contains(photo.as_ref())
+                })
+                .collect();
+            if photos.is_empty() {
+                println!("no new photos in {}", from.display());
+            }
+            'photos: for photo in photos {
+                if let Err(e) = intake::show(config.viewer.as_deref(), &photo) {
+                    eprintln!("warning: cannot show {}: {e}", photo.display());
+                }
+                let suggestion = intake::suggest(&photo).unwrap_or_else(|e| {
+                    eprintln!("warning: cannot read text in {}: {e}", photo.display());
+                    None
+                });
+                let file_name = photo.file_name().unwrap_or_default().to_string_lossy();
+                let stored = assets::name_of(&photo)?;
+                let holders = assets::holders(&root, &stored);
+                if !holders.is_empty() {
+                    eprintln!(
+                        "warning: {file_name} is already attached to {}",
+                        holders.join(", ")
+                    );
+                }
+                let question = match &suggestion {
+                    Some(suggestion) => {
+                        format!("{file_name}: name [{}] (- to skip): ", suggestion.name)
+                    }
+                    None => format!("{file_name}: name (empty to skip): "),
+                };
+                let item = loop {
+                    let Some(name) = prompt(&question)? else {
+                        break 'photos;
+                    };
+                    let mut item = Item::builder().attachment(&stored);
+                    item = match (&suggestion, name.as_str()) {
+                        (Some(_), "-") | (None, "") => break None,
+                        (Some(suggestion), "") => item
+                            .name(&suggestion.name)
+                            .description(&suggestion.description),
+                        _ => item.name(name),
+                    };
+                    let item = item.try_build();
+                    match item {
+                        Ok(item) => break Some(item),
+                        Err(e) => eprintln!("Error: {e}"),
+                    }
+                };
+                if let Some(item) = item {
+                    let limits = root.settings().limits.clone();
+                    find_space_mut(&mut root, &space, current, &matching)?
+                        .ok_or("space not found")?
+                        .try_add_item(item, &limits)
+                        .map_err(|e| e.to_string())?;
+                    store.assets.add(&photo)?;
+                    store.save(&root)?;
+                }
+                offered.insert(photo.to_string_lossy().into_owned());
+                *state_changed = true;
+            }
+        }
+        Commands::StoreAttachments => {
+            let mut root = store.load()?;
+            let changed = store.assets.store_all(&mut root)?;
+            if changed > 0 {
+                store.save(&root)?;
+            }
+            let mut out = store.status();
+            writeln!(
+                out,
+                "stored {changed} attachments in {}",
+                store.assets.dir().display()
+            )?;
+            for (attachment, items) in assets::shared_attachments(&root) {
+                writeln!(out, "{attachment} is attached to {}", items.join(", "))?;
+            }
+        }
+        Commands::GcAttachments { dry_run } => {
+            let root = store.load()?;
+            let orphans = store.assets.orphans(&root)?;
+            let mut bytes = 0;
+            for orphan in &orphans {
+                bytes += std::fs::metadata(orphan)?.len();
+                println!("{}", orphan.display());
+            }
+            if orphans.is_empty() {
+                println!("no unreferenced attachments");
+            } else if !dry_run {
+                let question = format!("delete {} files ({bytes} bytes)? [y/N] ", orphans.len());
+                if prompt(&question)?.is_some_and(|answer| answer.eq_ignore_ascii_case("y")) {
+                    for orphan in &orphans {
+                        std::fs::remove_file(orphan)
+                            .map_err(|e| format!("{}: {e}", orphan.display()))?;
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "thumbnails")]
+        Commands::Photos { item } => {
+            let mut root = store.load()?;
+            let item =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            let inline = std::io::stdout().is_terminal() && thumbnails::supported();
+            for attachment in item.attachments() {
+                let thumbnail = match store.assets.thumbnail(attachment) {
+                    Ok(thumbnail) => thumbnail,
+                    Err(e) => {
+                        eprintln!("warning: {attachment}: {e}");
+                        continue;
+                    }
+                };
+                if inline {
+                    println!("{attachment}");
+                    thumbnails::show(&thumbnail)?;
+                } else {
+                    println!("{attachment}\t{}", thumbnail.display());
+                }
+            }
+        }
+        Commands::AddSpace { parent, child } => {
+            let mut root = store.load()?;
+            let limits = root.settings().limits.clone();
+            let (parents, _) =
+                locate_space(&root, &parent, current, &matching)?.ok_or("space not found")?;
+            let depth = parents.len();
+            let target =
+                find_space_mut(&mut root, &parent, current, &matching)?.ok_or("space not found")?;
+            let new_space = Space::builder().name(child).try_build()?;
+            target
+                .try_add_space(new_space, depth, &limits)
+                .map_err(|e| e.to_string())?;
+            store.save(&root)?;
+        }
+        Commands::ListItems {
+            space,
+            offset,
+            limit,
+            filter,
+            format,
+        } => {
+            let root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            let (mut path, target) =
+                locate_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            path.push(target.name());
+            let limit = limit.unwrap_or(usize::MAX);
+            let filter = |item: &Item| filter.as_deref().is_none_or(|f| item.name().contains(f));
+            let page = match &collation {
+                Some(collation) => target.items_page_sorted(offset, limit, filter, collation),
+                None => target.items_page_filtered(offset, limit, filter),
+            };
+            for item in page.entries {
+                match &format {
+                    Some(template) => {
+                        let entry = Entry {
+                            path: path.clone(),
+                            target: Target::Item(item),
+                        };
+                        print!("{}{end}", template.render(&entry));
+                    }
+                    None => print!("{}{end}", item.name()),
+                }
+            }
+        }
+        Commands::List { space, format } => {
+            let root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            let (mut path, target) =
+                locate_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            path.push(target.name());
+            let mut items: Vec<Target> = target.items().iter().map(Target::Item).collect();
+            let mut spaces: Vec<Target> = target.spaces().iter().map(Target::Space).collect();
+            if let Some(collation) = &collation {
+                collation.sort_by(&mut items, |t| t.name());
+                collation.sort_by(&mut spaces, |t| t.name());
+            }
+            let children = items.into_iter().chain(spaces);
+            for child in children {
+                match &format {
+                    Some(template) => {
+                        let entry = Entry {
+                            path: path.clone(),
+                            target: child,
+                        };
+                        print!("{}{end}", template.render(&entry));
+                    }
+                    None => match child {
+                        Target::Item(item) => print!("item: {}{end}", item.name()),
+                        Target::Space(sp) => print!("space: {}{end}", sp.name()),
+                    },
+                }
+            }
+        }
+        Commands::Suggest { name, tag, limit } => {
+            let root = store.load()?;
+            let tags: Vec<&str> = tag.iter().map(String::as_str).collect();
+            for suggestion in suggest::suggest(&root, &name, &tags)
+                .into_iter()
+                .take(limit)
+            {
+                print!("{}\t{:.2}{end}", suggestion.path, suggestion.score);
+            }
+        }
+        Commands::Search {
+            query,
+            space,
+            format,
+        } => {
+            let query: Query = query.parse()?;
+            #[cfg(feature = "search-index")]
+            if space.is_none()
+                && format.is_none()
+                && query.field_terms().next().is_none()
+                && store.index.exists()
+            {
+                let query = query.text().unwrap_or_default();
+                let index = SearchIndex::from_file(&store.index)?;
+                let highlight = std::io::stdout().is_terminal();
+                for hit in index.query_with(&query, index.synonyms()) {
+                    let path: Vec<&str> = hit.path.iter().map(String::as_str).collect();
+                    let description = (hit.match_kind == MatchKind::Description)
+                        .then_some(hit.description.as_str());
+                    print_hit(&path, &hit.name, description, &hit.spans, highlight, end);
+                }
+                return Ok(());
+            }
+            let root = store.load()?;
+            let target = match &space {
+                Some(name) => {
+                    find_space(&root, name, current, &matching)?.ok_or("space not found")?
+                }
+                None => &root,
+            };
+            let highlight = std::io::stdout().is_terminal();
+            for hit in search::search_query(target, &query, &root.settings().synonyms) {
+                match &format {
+                    Some(template) => {
+                        let entry = Entry {
+                            path: hit.path,
+                            target: hit.target,
+                        };
+                        print!("{}{end}", template.render(&entry));
+                    }
+                    None => {
+                        let description = match (hit.kind, hit.target) {
+                            (MatchKind::Description, Target::Item(item)) => {
+                                Some(item.description())
+                            }
+                            _ => None,
+                        };
+                        let name = hit.target.name();
+                        print_hit(&hit.path, name, description, &hit.spans, highlight, end);
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "search-index")]
+        Commands::BuildIndex => {
+            let root = store.load()?;
+            let bar = progress::bar(cli.quiet, "Indexing");
+            let index = SearchIndex::build_with_progress(&root, progress::advance(&bar));
+            bar.finish_and_clear();
+            index.save_to_file(&store.index)?;
+        }
+        Commands::SetMeta { item, key, value } => {
+            let mut root = store.load()?;
+            let target =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            match value {
+                Some(value) => {
+                    target.set_metadata(key, value);
+                }
+                None => {
+                    target
+                        .remove_metadata(&key)
+                        .ok_or_else(|| format!("item has no metadata '{key}'"))?;
+                }
+            }
+            store.save(&root)?;
+        }
+        Commands::Synonyms { command } => {
+            let mut root = store.load()?;
+            match command {
+                SynonymsCommand::List => {
+                    for group in root.settings().synonyms.groups() {
+                        println!("{}", group.join(" = "));
+                    }
+                }
+                SynonymsCommand::Add { group } => {
+                    if !root.settings_mut().synonyms.add_line(&group) {
+                        return Err("a synonym group needs at least two different words".into());
+                    }
+                    store.save(&root)?;
+                }
+                SynonymsCommand::Remove { word } => {
+                    if root.settings_mut().synonyms.remove_word(&word) == 0 {
+                        return Err(format!("no synonym group contains '{word}'").into());
+                    }
+                    store.save(&root)?;
+                }
+            }
+        }
+        Commands::TreeLimits {
+            max_depth,
+            max_children,
+            max_name_length,
+        } => {
+            let parse = |value: Option<String>| {
+                value
+                    .as_deref()
+                    .map(parse_or_clear::<usize>)
+                    .transpose()
+                    .map_err(|e| e.to_string())
+            };
+            let (max_depth, max_children, max_name_length) = (
+                parse(max_depth)?,
+                parse(max_children)?,
+                parse(max_name_length)?,
+            );
+            let mut root = store.load()?;
+            if max_depth.is_none() && max_children.is_none() && max_name_length.is_none() {
+                let limits = &root.settings().limits;
+                let show = |limit: Option<usize>| limit.map_or("-".to_string(), |l| l.to_string());
+                println!("max depth\t{}", show(limits.max_depth));
+                println!("max children\t{}", show(limits.max_children));
+                println!("max name length\t{}", show(limits.max_name_length));
+                return Ok(());
+            }
+            let limits = &mut root.settings_mut().limits;
+            if let Some(max_depth) = max_depth {
+                limits.max_depth = max_depth;
+            }
+            if let Some(max_children) = max_children {
+                limits.max_children = max_children;
+            }
+            if let Some(max_name_length) = max_name_length {
+                limits.max_name_length = max_name_length;
+            }
+            if let Err(e) = root.settings().limits.check(&root) {
+                eprintln!("warning: the tree is already over the new limits: {e}");
+            }
+            store.save(&root)?;
+        }
+        Commands::Kit { command } => {
+            let mut root = store.load()?;
+            match command {
+                KitCommand::List => {
+                    for name in root.settings().kits.names() {
+                        println!("{name}");
+                    }
+                }
+                KitCommand::Create { name } => {
+                    if !root.settings_mut().kits.create(&name) {
+                        return Err(format!("kit '{name}' already exists").into());
+                    }
+                    store.save(&root)?;
+                }
+                KitCommand::Add { name, items } => {
+                    if root.settings().kits.get(&name).is_none() {
+                        return Err(format!("no kit named '{name}'").into());
+                    }
+                    for item in items {
+                        let id = find_item_mut(&mut root, &item, current, &matching)?
+                            .ok_or_else(|| format!("item '{item}' not found"))?
+                            .ensure_id();
+                        root.settings_mut().kits.add(&name, id);
+                    }
+                    store.save(&root)?;
+                }
+                KitCommand::Remove { name, item } => {
+                    let id = find_item_mut(&mut root, &item, current, &matching)?
+                        .ok_or("item not found")?
+                        .id();
+                    let removed = id.and_then(|id| root.settings_mut().kits.remove(&name, id));
+                    match removed {
+                        None if root.settings().kits.get(&name).is_none() => {
+                            return Err(format!("no kit named '{name}'").into());
+                        }
+                        Some(true) => store.save(&root)?,
+                        _ => return Err(format!("'{item}' is not in kit '{name}'").into()),
+                    }
+                }
+                KitCommand::Delete { name } => {
+                    if !root.settings_mut().kits.delete(&name) {
+                        return Err(format!("no kit named '{name}'").into());
+                    }
+                    store.save(&root)?;
+                }
+                KitCommand::Check { name } => {
+                    let members = root
+                        .settings()
+                        .kits
+                        .get(&name)
+                        .ok_or_else(|| format!("no kit named '{name}'"))?;
+                    let mut missing = 0;
+                    for member in kits::check(&root, members) {
+                        match member {
+                            Member::Present(path) => println!("ok       {path}"),
+                            Member::Missing(id) => {
+                                missing += 1;
+                                println!("missing  item {id}");
+                            }
+                        }
+                    }
+                    match missing {
+                        0 => {}
+                        1 => return Err("1 item missing".into()),
+                        n => return Err(format!("{n} items missing").into()),
+                    }
+                }
+            }
+        }
+        Commands::View { command } => {
+            let mut root = store.load()?;
+            let no_view = |name: &str| format!("no view named '{name}'");
+            match command {
+                ViewCommand::List => {
+                    for name in root.settings().views.names() {
+                        println!("{name}");
+                    }
+                }
+                ViewCommand::Create { name } => {
+                    if !root.settings_mut().views.create(&name) {
+                        return Err(format!("view '{name}' already exists").into());
+                    }
+                    store.save(&root)?;
+                }
+                ViewCommand::Add { name, group, items } => {
+                    if root.settings().views.get(&name).is_none() {
+                        return Err(no_view(&name).into());
+                    }
+                    let group = if group.is_empty() {
+                        Vec::new()
+                    } else {
+                        path::parse(&group)?
+                    };
+                    for item in items {
+                        let id = find_item_mut(&mut root, &item, current, &matching)?
+                            .ok_or_else(|| format!("item '{item}' not found"))?
+                            .ensure_id();
+                        root.settings_mut().views.add(&name, &group, id);
+                    }
+                    store.save(&root)?;
+                }
+                ViewCommand::Remove { name, item } => {
+                    let id = find_item_mut(&mut root, &item, current, &matching)?
+                        .ok_or("item not found")?
+                        .id();
+                    let views = &mut root.settings_mut().views;
+                    let removed = match id {
+                        Some(id) => views.remove(&name, id),
+                        None => views.get(&name).map(|_| 0),
+                    };
+                    match removed.ok_or_else(|| no_view(&name))? {
+                        0 => return Err(format!("'{item}' is not in view '{name}'").into()),
+                        _ => store.save(&root)?,
+                    }
+                }
+                ViewCommand::Delete { name } => {
+                    if !root.settings_mut().views.delete(&name) {
+                        return Err(no_view(&name).into());
+                    }
+                    store.save(&root)?;
+                }
+            }
+        }
+        Commands::Find {
+            pattern,
+            format,
+            #[cfg(feature = "regex")]
+            regex,
+        } => {
+            let root = store.load()?;
+            #[cfg(feature = "regex")]
+            let found = if regex {
+                search::find_regex(&root, &regex::Regex::new(&pattern)?)
+            } else {
+                find_by_name(&root, &pattern, &matching)
+            };
+            #[cfg(not(feature = "regex"))]
+            let found = find_by_name(&root, &pattern, &matching);
+            for entry in found {
+                match &format {
+                    Some(template) => print!("{}{end}", template.render(&entry)),
+                    None => print!("{}{end}", entry.path_string()),
+                }
+            }
+        }
+        Commands::MoveItems {
+            from,
+            to,
+            mut items,
+            stdin,
+            #[cfg(feature = "regex")]
+            regex,
+        } => {
+            if stdin {
+                items.extend(read_names(cli.print0)?);
+            }
+            let mut root = store.load()?;
+            let mut removed = Vec::new();
+            {
+                let source = find_space_mut(&mut root, &from, current, &matching)?
+                    .ok_or("source space not found")?;
+                #[cfg(feature = "regex")]
+                let items = if regex {
+                    select_by_regex(source, &items)?
+                } else {
+                    items
+                };
+                for name in &items {
+                    if let Some(item) = source.remove_item_with(name, &matching) {
+                        removed.push(item);
+                    }
+                }
+            }
+            let limits = root.settings().limits.clone();
+            let dest = find_space_mut(&mut root, &to, current, &matching)?
+                .ok_or("destination space not found")?;
+            for item in removed {
+                dest.try_add_item(item, &limits)
+                    .map_err(|e| e.to_string())?;
+            }
+            store.save(&root)?;
         }
         Commands::MoveSpace { space, to } => {
-            let mut root = Space::from_file(&cli.file)?;
-            let moved = root.remove_space(&space).ok_or("space not found")?;
-            let dest = root
-                .find_space_mut(&to)
+            let mut root = store.load()?;
+            let moved =
+                remove_space(&mut root, &space, current, &matching)?.ok_or("space not found")?;
+            let limits = root.settings().limits.clone();
+            let (parents, _) = locate_space(&root, &to, current, &matching)?
+                .ok_or("destination space not found")?;
+            let depth = parents.len();
+            let dest = find_space_mut(&mut root, &to, current, &matching)?
                 .ok_or("destination space not found")?;
-            dest.add_space(moved);
-            root.save_to_file(cli.file)?;
+            dest.try_add_space(moved, depth, &limits)
+                .map_err(|e| e.to_string())?;
+            store.save(&root)?;
+        }
+        Commands::ImportSpace { file, into } => {
+            let mut space: Space = serde_json::from_str(&read_input(&file)?)
+                .map_err(|e| format!("{}: {e}", file.display()))?;
+            // A whole tree becomes an ordinary space; its settings stay with
+            // the file it came from.
+            if space.root() {
+                space.set_root(false);
+                *space.settings_mut() = puha_lib::Settings::default();
+            }
+            if let Some(issue) = space.invalid_names().first() {
+                return Err(format!("'{}': {}", issue.path, issue.error).into());
+            }
+            if store.strict {
+                // Checked as a tree of its own, which it is until added.
+                space.set_root(true);
+                let checked = puha_lib::strict::check_structure(&space);
+                space.set_root(false);
+                checked.map_err(|e| e.to_string())?;
+            }
+            // Stored attachments come along from the file's assets
+            // directory, as `transfer` takes them; standard input has none.
+            let attachments: Vec<String> = assets::references(&space)
+                .into_iter()
+                .map(String::from)
+                .collect();
+            let mut root = store.load()?;
+            // A space exported from this same tree keeps the ids, and so the
+            // links, of the one it was exported from unless they change.
+            puha_lib::ids::renew_taken(&mut space, &puha_lib::ids::ids(&root));
+            let into = space_or_current(into, current, &root);
+            let limits = root.settings().limits.clone();
+            let (parents, _) =
+                locate_space(&root, &into, current, &matching)?.ok_or("space not found")?;
+            let depth = parents.len();
+            let dest =
+                find_space_mut(&mut root, &into, current, &matching)?.ok_or("space not found")?;
+            if dest.spaces().iter().any(|s| s.name() == space.name()) {
+                return Err(format!(
+                    "'{}' already has a space named '{}'",
+                    dest.name(),
+                    space.name()
+                )
+                .into());
+            }
+            dest.try_add_space(space, depth, &limits)
+                .map_err(|e| e.to_string())?;
+            if file != Path::new("-") {
+                let from = Assets::new(assets_path(&file.to_string_lossy(), Backend::Json));
+                for attachment in &attachments {
+                    copy_asset(&from, &store.assets, attachment)?;
+                }
+            }
+            store.save(&root)?;
+        }
+        #[cfg(feature = "pdf")]
+        Commands::Report {
+            space,
+            out,
+            font,
+            redact,
+        } => {
+            let mut root = store.load()?;
+            if let Some(redaction) = redact {
+                redaction.apply(&mut root);
+            }
+            let (parents, target) =
+                locate_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            report::write(target, &parents, font.as_deref(), &formats, &out)?;
+        }
+        #[cfg(feature = "insurance")]
+        Commands::InsuranceReport {
+            space,
+            out,
+            font,
+            min_value,
+        } => {
+            let root = store.load()?;
+            let (parents, target) =
+                locate_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            let valuation = Valuation::of(target, &config.depreciation, Date::today());
+            let report = report::render(target, &parents, font.as_deref(), &formats)?;
+            let missing = insurance::write(&report, &valuation, min_value, &store.assets, &out)?;
+            for receipt in missing {
+                eprintln!("warning: receipt '{receipt}' not found, left out");
+            }
+        }
+        #[cfg(feature = "labels")]
+        Commands::Labels {
+            space,
+            layout,
+            skip,
+            out,
+        } => {
+            let root = store.load()?;
+            let (parents, target) =
+                locate_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            labels::write(target, &parents, layout, skip, &out)?;
+        }
+        #[cfg(any(feature = "import-grocy", feature = "import-home-assistant"))]
+        Commands::Import {
+            from,
+            export,
+            into,
+            ids,
+            dry_run,
+        } => {
+            let bar = progress::bar(cli.quiet, "Importing");
+            let imported = match from {
+                #[cfg(feature = "import-grocy")]
+                ImportSource::Grocy => puha_lib::import::grocy::import_with_progress(
+                    &std::fs::read_to_string(&export)?,
+                    progress::advance(&bar),
+                )?,
+                #[cfg(feature = "import-home-assistant")]
+                ImportSource::HomeAssistant => {
+                    let storage = export.join(".storage");
+                    let dir = if storage.is_dir() { storage } else { export };
+                    let read = |name| std::fs::read_to_string(dir.join(name));
+                    puha_lib::import::home_assistant::import_with_progress(
+                        &read("core.area_registry")?,
+                        &read("core.device_registry")?,
+                        progress::advance(&bar),
+                    )?
+                }
+            };
+            bar.finish_and_clear();
+            let mut imported = imported;
+            let mut root = store.load()?;
+            let path: Vec<String> = match &into {
+                Some(name) => {
+                    let (parents, space) =
+                        locate_space(&root, name, current, &matching)?.ok_or("space not found")?;
+                    parents
+                        .into_iter()
+                        .chain([space.name()])
+                        .map(String::from)
+                        .collect()
+                }
+                None => vec![root.name().to_string()],
+            };
+            let parents: Vec<&str> = path.iter().map(String::as_str).collect();
+            IdStrategy::from(ids).assign(&mut imported, &parents);
+            let before = dry_run.then(|| root.clone());
+            let target = match &into {
+                Some(name) => find_space_mut(&mut root, name, current, &matching)?,
+                None => Some(&mut root),
+            };
+            for path in ids::merge_space(target.ok_or("space not found")?, imported) {
+                eprintln!("warning: kept '{path}', which was changed here since it was imported");
+            }
+            match before {
+                Some(before) => print!("{}", TreeDiff::between(&before, &root)),
+                None => store.save(&root)?,
+            }
+        }
+        #[cfg(feature = "xlsx")]
+        Commands::ExportXlsx { out, redact } => {
+            let mut root = store.load()?;
+            if let Some(redaction) = redact {
+                redaction.apply(&mut root);
+            }
+            let bar = progress::bar(cli.quiet, "Exporting");
+            xlsx::write(&root, &out, progress::advance(&bar))?;
+            bar.finish_and_clear();
+        }
+        Commands::SetPurchase {
+            item,
+            category,
+            date,
+            price,
+        } => {
+            // An empty argument removes the field; a missing one keeps it.
+            let category = category.map(|c| Some(c).filter(|c| !c.is_empty()));
+            // Spelled out, as the messages say what is expected.
+            let date = date.as_deref().map(parse_or_clear::<Date>).transpose();
+            let date = date.map_err(|e| e.to_string())?;
+            let price = price.as_deref().map(parse_or_clear::<Price>).transpose();
+            let price = price.map_err(|e| e.to_string())?;
+            let mut root = store.load()?;
+            let target =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            if let Some(category) = category {
+                target.set_category(category);
+            }
+            if let Some(date) = date {
+                target.set_purchase_date(date);
+            }
+            if let Some(price) = price {
+                target.set_purchase_price(price);
+            }
+            store.save(&root)?;
+        }
+        Commands::Value {
+            space,
+            current: depreciated,
+        } => {
+            let root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            let target = find_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            let valuation = Valuation::of(target, &config.depreciation, Date::today());
+            for item in &valuation.items {
+                let purchased = item.purchased.map(|d| formats.date(d)).unwrap_or_default();
+                print!("{}\t{purchased}\t{}", item.path, formats.price(item.price));
+                if depreciated {
+                    print!("\t{}", formats.price(item.current));
+                }
+                println!();
+            }
+            print!("total\t\t{}", formats.price(valuation.total_price()));
+            if depreciated {
+                print!("\t{}", formats.price(valuation.total_current()));
+            }
+            println!();
+        }
+        Commands::SetWarranty {
+            item,
+            until,
+            receipts,
+        } => {
+            let until = until.as_deref().map(parse_or_clear::<Date>).transpose();
+            let until = until.map_err(|e| e.to_string())?;
+            let mut root = store.load()?;
+            let target =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            if let Some(until) = until {
+                target.set_warranty_until(until);
+            }
+            for receipt in &receipts {
+                target.add_receipt(store.assets.add(receipt)?);
+            }
+            store.save(&root)?;
+        }
+        Commands::Warranties { space, expiring } => {
+            let root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            let target = find_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            for warranty in warranty::warranties(target, Date::today(), expiring) {
+                let receipts = match warranty.receipts {
+                    0 => "no receipt".to_string(),
+                    1 => "1 receipt".to_string(),
+                    n => format!("{n} receipts"),
+                };
+                println!(
+                    "{}\t{}\t{} days left\t{receipts}",
+                    warranty.path,
+                    formats.date(warranty.until),
+                    warranty.days_left
+                );
+            }
+        }
+        Commands::SetSize { item, size, weight } => {
+            let size = size.as_deref().map(parse_or_clear::<Size>).transpose();
+            let size = size.map_err(|e| e.to_string())?;
+            let weight = weight.as_deref().map(parse_or_clear::<Weight>).transpose();
+            let weight = weight.map_err(|e| e.to_string())?;
+            let mut root = store.load()?;
+            let target =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            if let Some(size) = size {
+                target.set_size(size);
+            }
+            if let Some(weight) = weight {
+                target.set_weight(weight);
+            }
+            store.save(&root)?;
+        }
+        Commands::SetCapacity {
+            space,
+            size,
+            max_weight,
+        } => {
+            let size = size.as_deref().map(parse_or_clear::<Size>).transpose();
+            let size = size.map_err(|e| e.to_string())?;
+            let max_weight = max_weight
+                .as_deref()
+                .map(parse_or_clear::<Weight>)
+                .transpose();
+            let max_weight = max_weight.map_err(|e| e.to_string())?;
+            let mut root = store.load()?;
+            let target =
+                find_space_mut(&mut root, &space, current, &matching)?.ok_or("space not found")?;
+            if let Some(size) = size {
+                target.set_capacity(size);
+            }
+            if let Some(max_weight) = max_weight {
+                target.set_max_weight(max_weight);
+            }
+            store.save(&root)?;
+        }
+        Commands::Fit { item, near } => {
+            let root = store.load()?;
+            let item = find_item(&root, &item, current, &matching)?.ok_or("item not found")?;
+            if item.size().is_none() && item.weight().is_none() {
+                return Err("the item has no size or weight; set them with set-size".into());
+            }
+            let mut fits = capacity::fits(&root, item);
+            if let Some(near) = near {
+                let (mut near, space) =
+                    locate_space(&root, &near, current, &matching)?.ok_or("space not found")?;
+                near.push(space.name());
+                fits.sort_by_key(|fit| capacity::distance(&fit.path, &near));
+            }
+            if fits.is_empty() {
+                println!("no space has room for it");
+            }
+            for fit in fits {
+                let segments: Vec<&str> = fit.path.iter().map(String::as_str).collect();
+                let mut line = path::format(&segments);
+                if let Some(volume) = fit.volume_left {
+                    line.push_str(&format!("\t{:.1} l free", volume as f64 / 1_000_000.0));
+                }
+                if let Some(weight) = fit.weight_left {
+                    line.push_str(&format!("\t{} free", Weight(weight)));
+                }
+                println!("{line}");
+            }
+        }
+        Commands::Link { item, kind, target } => {
+            let mut root = store.load()?;
+            let to = find_item_mut(&mut root, &target, current, &matching)?
+                .ok_or("target item not found")?
+                .ensure_id();
+            let source =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            if source.id() == Some(to) {
+                return Err("an item cannot link to itself".into());
+            }
+            if !source.add_link(kind, to) {
+                writeln!(store.status(), "already linked")?;
+                return Ok(());
+            }
+            store.save(&root)?;
+        }
+        Commands::Unlink {
+            item,
+            target,
+            dangling,
+        } => {
+            let mut root = store.load()?;
+            let removed = if dangling {
+                links::remove_dangling(&mut root)
+            } else {
+                let (item, target) = item.zip(target).ok_or("missing item or target")?;
+                let Some(to) = find_item_mut(&mut root, &target, current, &matching)?
+                    .ok_or("target item not found")?
+                    .id()
+                else {
+                    return Err("no links to an item without an id".into());
+                };
+                find_item_mut(&mut root, &item, current, &matching)?
+                    .ok_or("item not found")?
+                    .remove_links_to(to)
+            };
+            match removed {
+                0 => writeln!(store.status(), "no links removed")?,
+                1 => writeln!(store.status(), "removed 1 link")?,
+                n => writeln!(store.status(), "removed {n} links")?,
+            }
+            if removed > 0 {
+                store.save(&root)?;
+            }
+        }
+        Commands::Links { item } => {
+            let mut root = store.load()?;
+            let target =
+                find_item_mut(&mut root, &item, current, &matching)?.ok_or("item not found")?;
+            let (id, outgoing) = (target.id(), target.links().to_vec());
+            for link in outgoing {
+                let path = links::path_of(&root, link.target);
+                let path = path.unwrap_or_else(|| format!("{} (missing)", link.target));
+                println!("{}\t{path}", link.kind);
+            }
+            if let Some(id) = id {
+                for (path, kind) in links::incoming(&root, id) {
+                    println!("{path}\t{kind} this");
+                }
+            }
+        }
+        Commands::Transfer {
+            space,
+            filter,
+            to_file,
+            into,
+            owner,
+        } => {
+            // Saving the destination and then the source would delete what
+            // was moved if they are the same data.
+            if let (Ok(to), Ok(from)) = (to_file.canonicalize(), store.path.canonicalize())
+                && to == from
+            {
+                return Err(format!("{} is the data being moved from", to_file.display()).into());
+            }
+            let mut root = store.load()?;
+            let (parents, target) =
+                locate_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            let prefix = path::format(&parents);
+            let mut manifest = transfer::Manifest {
+                at: Timestamp::now(),
+                owner: owner.clone(),
+                from: store.path.display().to_string(),
+                to: to_file.display().to_string(),
+                spaces: Vec::new(),
+                items: Vec::new(),
+            };
+            let full = |path: String| match prefix.as_str() {
+                "" => path,
+                prefix => format!("{prefix}/{path}"),
+            };
+            let (mut items, mut spaces) = (Vec::new(), Vec::new());
+            match &filter {
+                Some(query) => {
+                    let target = find_space_mut(&mut root, &space, current, &matching)?
+                        .ok_or("space not found")?;
+                    for (path, item) in transfer::take_items(target, query) {
+                        manifest.items.push(full(path));
+                        items.push(item);
+                    }
+                    if items.is_empty() {
+                        return Err("no items match".into());
+                    }
+                }
+                None => {
+                    if target.root() {
+                        return Err("the root cannot be transferred; use --where".into());
+                    }
+                    manifest.spaces.push(path::join(&parents, target.name()));
+                    spaces.push(
+                        remove_space(&mut root, &space, current, &matching)?
+                            .ok_or("space not found")?,
+                    );
+                }
+            }
+            if let Some(owner) = &owner {
+                for item in &mut items {
+                    item.set_metadata(transfer::OWNER, owner.as_str());
+                }
+                for space in &mut spaces {
+                    transfer::set_owner(space, owner);
+                }
+            }
+
+            let backend = if to_file.is_dir() {
+                Backend::Dir
+            } else {
+                Backend::Json
+            };
+            let other = backend.open(&to_file);
+            let before = to_file.exists().then(|| other.load()).transpose()?;
+            let mut other_root = match &before {
+                Some(root) => root.clone(),
+                None => {
+                    let name = to_file.file_stem().unwrap_or_default().to_string_lossy();
+                    Space::builder().name(name).root(true).try_build()?
+                }
+            };
+            let limits = other_root.settings().limits.clone();
+            let (dest, depth) = match &into {
+                Some(spec) => {
+                    let segments = path::resolve(spec, &[other_root.name()])?;
+                    let dest = other_root
+                        .space_at_mut(&segments, &matching)
+                        .ok_or("destination space not found")?;
+                    (dest, segments.len() - 1)
+                }
+                None => (&mut other_root, 0),
+            };
+            let other_assets = Assets::new(assets_path(&to_file.to_string_lossy(), backend));
+            for space in &spaces {
+                for file in assets::references(space) {
+                    copy_asset(&store.assets, &other_assets, file)?;
+                }
+            }
+            for item in &items {
+                for file in item.attachments().iter().chain(item.receipts()) {
+                    copy_asset(&store.assets, &other_assets, file)?;
+                }
+            }
+            for item in items {
+                dest.try_add_item(item, &limits)
+                    .map_err(|e| e.to_string())?;
+            }
+            for space in spaces {
+                dest.try_add_space(space, depth, &limits)
+                    .map_err(|e| e.to_string())?;
+            }
+            if !store.unlock
+                && let Some(before) = &before
+            {
+                lock::check_changes(before, &other_root).map_err(|e| {
+                    format!(
+                        "{}: {e}; unlock it first or pass --unlock",
+                        to_file.display()
+                    )
+                })?;
+            }
+
+            // Written first, so a failure leaves everything in both places
+            // rather than nowhere.
+            other.save(&other_root)?;
+            if let Err(e) = store.save(&root) {
+                let restored = match &before {
+                    Some(before) => other.save(before),
+                    None => std::fs::remove_file(&to_file).map_err(Into::into),
+                };
+                if let Err(undo) = restored {
+                    eprintln!(
+                        "warning: could not undo the write to {}: {undo}",
+                        to_file.display()
+                    );
+                }
+                return Err(e);
+            }
+            let log = to_file.with_extension("transfers.jsonl");
+            let mut log_file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&log)?;
+            writeln!(log_file, "{}", serde_json::to_string(&manifest)?)?;
+            let moved = match (manifest.spaces.as_slice(), manifest.items.len()) {
+                ([space], _) => space.clone(),
+                (_, 1) => "1 item".to_string(),
+                (_, n) => format!("{n} items"),
+            };
+            writeln!(
+                store.status(),
+                "moved {moved} to {}; manifest in {}",
+                to_file.display(),
+                log.display()
+            )?;
+        }
+        Commands::Audit { space, restart } => {
+            let mut root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            let (parents, target) =
+                locate_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            let key = path::join(&parents, target.name());
+            let parents: Vec<String> = parents.iter().map(|p| p.to_string()).collect();
+            let mut audited = parents.clone();
+            audited.push(target.name().to_string());
+            let audits = state.audits.entry(state_key.clone()).or_default();
+            if restart {
+                audits.remove(&key);
+            }
+            let audit = audits.entry(key.clone()).or_insert_with(|| state::Audit {
+                started: Timestamp::now(),
+                moved: Vec::new(),
+            });
+            *state_changed = true;
+            let started = audit.started;
+            // Item paths as segments from the root.
+            let full_path = |entry: &Entry| {
+                let mut segments = parents.clone();
+                segments.extend(entry.path.iter().map(|s| s.to_string()));
+                segments.push(entry.target.name().to_string());
+                segments
+            };
+            let exact = MatchOptions::exact();
+            // By id, as names need not be unique and items may be moved.
+            let target = root
+                .space_at_mut(&audited, &exact)
+                .ok_or("space not found")?;
+            let pending = audit::pending(target, started);
+            'items: for id in pending {
+                let Some(shown) = links::path_of(&root, id) else {
+                    continue;
+                };
+                loop {
+                    let Some(answer) = prompt(&format!("{shown}: there? [Y/n/m/s/q] "))? else {
+                        break 'items;
+                    };
+                    let now = Timestamp::now();
+                    match answer.to_lowercase().as_str() {
+                        "" | "y" => root
+                            .find_item_by_id_mut(id)
+                            .ok_or("item not found")?
+                            .verify(now),
+                        "n" => root
+                            .find_item_by_id_mut(id)
+                            .ok_or("item not found")?
+                            .mark_missing(now),
+                        "m" => {
+                            let Some(to) = prompt("  now in: ")? else {
+                                break 'items;
+                            };
+                            let Some((to_parents, to_space)) =
+                                locate_space(&root, &to, current, &matching)?
+                            else {
+                                eprintln!("space not found");
+                                continue;
+                            };
+                            let to = path::join(&to_parents, to_space.name());
+                            let mut to_segments: Vec<String> =
+                                to_parents.iter().map(|s| s.to_string()).collect();
+                            to_segments.push(to_space.name().to_string());
+                            let limits = root.settings().limits.clone();
+                            let mut item = root.remove_item_by_id(id).ok_or("item not found")?;
+                            item.verify(now);
+                            root.space_at_mut(&to_segments, &exact)
+                                .ok_or("space not found")?
+                                .try_add_item(item, &limits)
+                                .map_err(|e| e.to_string())?;
+                            audit.moved.push((shown.clone(), to));
+                        }
+                        "s" => {}
+                        "q" => break 'items,
+                        _ => {
+                            eprintln!("answer y, n, m, s or q");
+                            continue;
+                        }
+                    }
+                    store.save(&root)?;
+                    break;
+                }
+            }
+
+            let space = root.space_at(&audited, &exact).ok_or("space not found")?;
+            let (mut found, mut missing, mut left) = (0, Vec::new(), 0);
+            for entry in search::entries(space) {
+                let Target::Item(item) = entry.target else {
+                    continue;
+                };
+                if item.missing_at().is_some_and(|at| at >= started) {
+                    let segments = full_path(&entry);
+                    missing.push(path::format(
+                        &segments.iter().map(String::as_str).collect::<Vec<_>>(),
+                    ));
+                } else if item.audited_since(started) {
+                    found += 1;
+                } else {
+                    left += 1;
+                }
+            }
+            println!(
+                "{found} found, {} missing, {} moved",
+                missing.len(),
+                audit.moved.len()
+            );
+            for path in &missing {
+                println!("missing  {path}");
+            }
+            for (from, to) in &audit.moved {
+                println!("moved    {from} -> {to}");
+            }
+            if left == 0 {
+                audits.remove(&key);
+            } else {
+                println!("{left} not checked yet; run `puha audit` again to continue");
+            }
+        }
+        Commands::Missing { space, older_than } => {
+            let root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            let target = find_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            let now = Timestamp::now();
+            for item in audit::unaccounted(target, now, older_than, &config.depreciation) {
+                let value = item.value.map(|v| formats.price(v)).unwrap_or_default();
+                let status = match (item.missing_at, item.last_verified_at) {
+                    (Some(at), _) => format!("not found {}", formats.date(at.date())),
+                    (None, Some(at)) => format!("last found {}", formats.date(at.date())),
+                    (None, None) => "never audited".to_string(),
+                };
+                println!("{}\t{value}\t{status}", item.path);
+            }
+        }
+        Commands::Style {
+            name,
+            item,
+            icon,
+            color,
+        } => {
+            if let Some(color) = color.as_deref().filter(|c| !c.is_empty())
+                && ansi_color(color).is_none()
+            {
+                return Err(format!("unknown colour '{color}'").into());
+            }
+            // An empty argument removes the hint; a missing one keeps it.
+            let icon = icon.map(|i| Some(i).filter(|i| !i.is_empty()));
+            let color = color.map(|c| Some(c).filter(|c| !c.is_empty()));
+            let mut root = store.load()?;
+            if item {
+                let target =
+                    find_item_mut(&mut root, &name, current, &matching)?.ok_or("item not found")?;
+                if let Some(icon) = icon {
+                    target.set_icon(icon);
+                }
+                if let Some(color) = color {
+                    target.set_color(color);
+                }
+            } else {
+                let target = find_space_mut(&mut root, &name, current, &matching)?
+                    .ok_or("space not found")?;
+                if let Some(icon) = icon {
+                    target.set_icon(icon);
+                }
+                if let Some(color) = color {
+                    target.set_color(color);
+                }
+            }
+            store.save(&root)?;
+        }
+        Commands::Lock { space } => set_locked(store, &space, true, current, &matching)?,
+        Commands::Unlock { space } => set_locked(store, &space, false, current, &matching)?,
+        Commands::RepairNames { dry_run } => {
+            let mut root = store.load()?;
+            let renames = root.repair_names();
+            let mut out = store.status();
+            for rename in &renames {
+                writeln!(out, "'{}' -> '{}'", rename.from, rename.to)?;
+            }
+            if !dry_run && !renames.is_empty() {
+                store.save(&root)?;
+            }
+        }
+        Commands::Tags { command } => {
+            let mut root = store.load()?;
+            let counts = tags::counts(&root);
+            let (from, to) = match command {
+                None => {
+                    let mut counts: Vec<_> = counts.into_iter().collect();
+                    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+                    for (tag, count) in counts {
+                        print!("{tag}\t{count}{end}");
+                    }
+                    return Ok(());
+                }
+                Some(TagsCommand::Rename { old, new }) => {
+                    if counts.contains_key(&new) {
+                        return Err(
+                            format!("'{new}' is in use; use `tags merge` to combine them").into(),
+                        );
+                    }
+                    (old, new)
+                }
+                Some(TagsCommand::Merge { a, b }) => (a, b),
+            };
+            if to.is_empty() {
+                return Err("tags cannot be empty".into());
+            }
+            if !counts.contains_key(&from) {
+                return Err(format!("no item is tagged '{from}'").into());
+            }
+            if from == to {
+                return Err(format!("'{from}' cannot be merged into itself").into());
+            }
+            match tags::rename(&mut root, &from, &to) {
+                1 => writeln!(store.status(), "1 item changed")?,
+                n => writeln!(store.status(), "{n} items changed")?,
+            }
+            store.save(&root)?;
+        }
+        Commands::TagBulk {
+            filter,
+            add,
+            remove,
+            dry_run,
+        } => {
+            if add.iter().chain(&remove).any(String::is_empty) {
+                return Err("tags cannot be empty".into());
+            }
+            if let Some(tag) = add.iter().find(|tag| remove.contains(tag)) {
+                return Err(format!("'{tag}' is both added and removed").into());
+            }
+            let add: Vec<&str> = add.iter().map(String::as_str).collect();
+            let remove: Vec<&str> = remove.iter().map(String::as_str).collect();
+            let mut root = store.load()?;
+            let changed = tags::retag(&mut root, &filter, &add, &remove);
+            let mut out = store.status();
+            for path in &changed {
+                write!(out, "{path}{end}")?;
+            }
+            if !dry_run && !changed.is_empty() {
+                store.save(&root)?;
+            }
+        }
+        Commands::Patch { patch, dry_run } => {
+            let data = read_input(&patch)?;
+            let patch: puha_lib::Patch =
+                serde_json::from_str(&data).map_err(|e| format!("{}: {e}", patch.display()))?;
+            let mut root = store.load()?;
+            let before = dry_run.then(|| root.clone());
+            root.apply_json_patch(&patch).map_err(|e| e.to_string())?;
+            match before {
+                Some(before) => print!("{}", TreeDiff::between(&before, &root)),
+                None => store.save(&root)?,
+            }
+        }
+        #[cfg(feature = "schema")]
+        Commands::Schema => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&puha_lib::schema::schema())?
+            );
+        }
+        Commands::Validate { file } => {
+            let backend = if file.is_dir() {
+                Backend::Dir
+            } else {
+                Backend::Json
+            };
+            backend.open(&file).load_strict()?;
+            println!("{}: valid", file.display());
+        }
+        Commands::Cd { space } => {
+            match space {
+                Some(spec) => {
+                    let root = store.load()?;
+                    let (mut path, target) =
+                        locate_space(&root, &spec, current, &matching)?.ok_or("space not found")?;
+                    path.push(target.name());
+                    let path = path.into_iter().map(String::from).collect();
+                    state.current.insert(state_key.clone(), path);
+                }
+                None => {
+                    state.current.remove(state_key.as_str());
+                }
+            }
+            *state_changed = true;
+        }
+        Commands::Pwd => {
+            let root = store.load()?;
+            println!("{}", space_or_current(None, current, &root));
+        }
+        Commands::Stats { space, memory } => {
+            let root = store.load()?;
+            let space = space_or_current(space, current, &root);
+            let target = find_space(&root, &space, current, &matching)?.ok_or("space not found")?;
+            let counts = TreeStats::of(target);
+            println!("spaces: {}", counts.spaces);
+            println!("items: {}", counts.items);
+            println!("metadata entries: {}", counts.metadata);
+            if memory {
+                let bytes = stats::estimated_memory(target);
+                let mib = bytes as f64 / (1024.0 * 1024.0);
+                println!("estimated memory: {bytes} bytes ({mib:.1} MiB)");
+            }
+        }
+        Commands::GenSample {
+            spaces,
+            items,
+            seed,
+        } => {
+            if store.path.exists() {
+                return Err(format!(
+                    "{} already exists; pass --file to write the sample elsewhere",
+                    store.path.display()
+                )
+                .into());
+            }
+            let options = SampleOptions {
+                spaces,
+                items,
+                seed,
+            };
+            store.save(&sample::generate(&options))?;
+        }
+        Commands::Doctor => unreachable!("handled above"),
+        Commands::Init { .. } => unreachable!("handled above"),
+        Commands::SelfInfo { command } => match command {
+            SelfCommand::Stats => {
+                println!("puha {}", env!("CARGO_PKG_VERSION"));
+                if !config.usage_stats {
+                    println!("usage statistics are off; set \"usage_stats\": true to record them");
+                }
+                let path = usage::default_path().ok_or("no state directory; set $PUHA_STATE")?;
+                print!("{}", usage::Usage::load(&path).table());
+            }
+        },
+        Commands::Profiles => {
+            for (name, profile) in &config.profiles {
+                let marker = if cli.profile.as_ref() == Some(name) {
+                    '*'
+                } else {
+                    ' '
+                };
+                println!("{marker} {name}\t{} ({})", profile.file, profile.backend);
+            }
+        }
+        #[cfg(feature = "shell")]
+        Commands::Shell => unreachable!("handled above"),
+        #[cfg(feature = "mqtt")]
+        Commands::MqttListen => unreachable!("handled above"),
+        Commands::External(args) => {
+            return Err(format!("unknown command '{}'", args[0]).into());
         }
     }
 
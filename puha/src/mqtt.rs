@@ -0,0 +1,88 @@
+//! Publishing changes to an MQTT broker, and taking commands from it.
+
+use std::thread;
+use std::time::Duration;
+
+use clap::Parser;
+use puha_lib::TreeDiff;
+use rumqttc::{Client, Connection, Event, MqttOptions, Outgoing, Packet, QoS};
+
+use crate::config::{self, Mqtt};
+use crate::{Cli, Context, command_name, run_tracked};
+
+/// Commands accepted from the command topic.
+const ACCEPTED: &[&str] = &["add-item", "add-space", "move-items", "move-space"];
+
+fn connect(config: &Mqtt, role: &str) -> (Client, Connection) {
+    let id = format!("puha-{role}-{}", std::process::id());
+    let mut options = MqttOptions::new(id, &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    Client::new(options, 16)
+}
+
+/// Publish every difference to the configured topic as a JSON object with
+/// its `change`, `kind` and `path`.
+pub fn publish(config: &Mqtt, diff: &TreeDiff) -> Result<(), Box<dyn std::error::Error>> {
+    let (client, mut connection) = connect(config, "publish");
+    // The connection only makes progress while it is iterated.
+    let driver = thread::spawn(move || -> Result<(), Box<rumqttc::ConnectionError>> {
+        for event in connection.iter() {
+            if let Event::Outgoing(Outgoing::Disconnect) = event.map_err(Box::new)? {
+                break;
+            }
+        }
+        Ok(())
+    });
+    let sent = diff.differences.iter().try_for_each(|difference| {
+        let payload = serde_json::to_vec(difference).expect("differences serialize");
+        client.publish(&config.topic, QoS::AtLeastOnce, false, payload)
+    });
+    let disconnected = sent.and_then(|()| client.disconnect());
+    // A refused connection ends the driver with the more telling error.
+    driver.join().expect("MQTT connection thread panicked")?;
+    disconnected?;
+    Ok(())
+}
+
+/// Run the command lines published to `<topic>/commands`, one per message,
+/// until interrupted. Only commands adding or moving things are accepted.
+/// Options given to `puha mqtt-listen` apply to every command.
+pub fn listen(cx: &mut Context) -> Result<(), Box<dyn std::error::Error>> {
+    let config = cx
+        .config
+        .mqtt
+        .clone()
+        .ok_or("no `mqtt` broker configured")?;
+    let prefix: Vec<String> = match cx.argv.iter().rposition(|arg| arg == "mqtt-listen") {
+        Some(i) => [&cx.argv[..i], &cx.argv[i + 1..]].concat(),
+        None => cx.argv[..1].to_vec(),
+    };
+    let (client, mut connection) = connect(&config, "listen");
+    client.subscribe(format!("{}/commands", config.topic), QoS::AtLeastOnce)?;
+    for event in connection.iter() {
+        let Event::Incoming(Packet::Publish(message)) = event? else {
+            continue;
+        };
+        let line = String::from_utf8_lossy(&message.payload);
+        if let Err(e) = run_line(&line, &prefix, cx) {
+            eprintln!("Error: {line}: {e}");
+        }
+    }
+    Ok(())
+}
+
+fn run_line(
+    line: &str,
+    prefix: &[String],
+    cx: &mut Context,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let words = config::split_words(line)?;
+    match words.first() {
+        None => return Ok(()),
+        Some(command) if ACCEPTED.contains(&command.as_str()) => {}
+        Some(command) => return Err(format!("'{command}' is not accepted over MQTT").into()),
+    }
+    let argv: Vec<String> = prefix.iter().cloned().chain(words).collect();
+    let cli = Cli::try_parse_from(&argv)?;
+    run_tracked(command_name(&argv).as_deref(), vec![cli], cx)
+}
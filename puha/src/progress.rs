@@ -0,0 +1,24 @@
+//! Progress bars for long-running commands.
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// A progress bar on standard error showing `message`, unless `quiet` is
+/// set. Like all indicatif bars it is hidden when standard error is not a
+/// terminal.
+pub fn bar(quiet: bool, message: &'static str) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template("{msg} [{bar:40}] {pos}/{len}")
+        .expect("template is valid")
+        .progress_chars("=> ");
+    ProgressBar::new(0).with_style(style).with_message(message)
+}
+
+/// A progress callback for the library that moves `bar`.
+pub fn advance(bar: &ProgressBar) -> impl FnMut(usize, usize) + '_ {
+    |done, total| {
+        bar.set_length(total as u64);
+        bar.set_position(done as u64);
+    }
+}
@@ -0,0 +1,292 @@
+//! Printable PDF inventory report.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    Point,
+};
+use puha_lib::search::{self, Target};
+use puha_lib::{Formats, Space, path};
+
+// A4 portrait, in millimetres.
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+
+const BODY_SIZE: f32 = 10.0;
+const LINE_HEIGHT: f32 = 5.0;
+/// Approximate number of characters per millimetre of line at `BODY_SIZE`.
+/// The built-in fonts come without metrics, so cells are wrapped by count.
+const CHARS_PER_MM: f32 = 0.55;
+
+/// Table columns: heading, left edge and width.
+const COLUMNS: [(&str, f32, f32); 3] = [
+    ("Name", MARGIN, 50.0),
+    ("Description", MARGIN + 55.0, 60.0),
+    ("Details", MARGIN + 120.0, PAGE_WIDTH - 2.0 * MARGIN - 120.0),
+];
+
+/// Write a report on `space`, whose enclosing spaces are `parents`, to `out`,
+/// or to standard output for `-`, with prices and dates written as `formats`
+/// says.
+///
+/// The built-in fonts only cover Western European characters; pass a
+/// TrueType `font` for anything else.
+pub fn write(
+    space: &Space,
+    parents: &[&str],
+    font: Option<&Path>,
+    formats: &Formats,
+    out: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    crate::write_output(out, &render(space, parents, font, formats)?)
+}
+
+/// The report [`write`] writes, as the bytes of a PDF file.
+pub fn render(
+    space: &Space,
+    parents: &[&str],
+    font: Option<&Path>,
+    formats: &Formats,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let title = path::join(parents, space.name());
+    let (doc, page, layer) = PdfDocument::new(
+        format!("Inventory of {title}"),
+        Mm(PAGE_WIDTH),
+        Mm(PAGE_HEIGHT),
+        "content",
+    );
+    let (regular, bold) = match font {
+        Some(font) => {
+            let font = doc.add_external_font(File::open(font)?)?;
+            (font.clone(), font)
+        }
+        None => (
+            doc.add_builtin_font(BuiltinFont::Helvetica)?,
+            doc.add_builtin_font(BuiltinFont::HelveticaBold)?,
+        ),
+    };
+    let layer = doc.get_page(page).get_layer(layer);
+    let mut writer = Writer {
+        doc,
+        layer,
+        regular,
+        bold,
+        formats: *formats,
+        y: PAGE_HEIGHT - MARGIN,
+    };
+
+    writer.cover(space, &title);
+    let mut parents = parents.to_vec();
+    writer.sections(space, &mut parents);
+
+    let mut pdf = BufWriter::new(Vec::new());
+    writer.doc.save(&mut pdf)?;
+    Ok(pdf.into_inner()?)
+}
+
+struct Writer {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    formats: Formats,
+    /// Baseline of the next line, from the bottom of the page.
+    y: f32,
+}
+
+impl Writer {
+    fn cover(&mut self, space: &Space, title: &str) {
+        let (mut spaces, mut items) = (0, 0);
+        for entry in search::entries(space) {
+            match entry.target {
+                Target::Space(_) => spaces += 1,
+                Target::Item(_) => items += 1,
+            }
+        }
+        self.line("Inventory", 24.0, true);
+        self.skip(4.0);
+        self.line(title, 14.0, false);
+        self.skip(8.0);
+        self.line(&format!("Spaces: {spaces}"), 12.0, false);
+        self.line(&format!("Items: {items}"), 12.0, false);
+
+        self.y = MARGIN + 3.0 * LINE_HEIGHT;
+        self.line("Signature: ________________________________", 12.0, false);
+        self.skip(LINE_HEIGHT);
+        self.line("Date: ________________________________", 12.0, false);
+    }
+
+    fn sections<'a>(&mut self, space: &'a Space, parents: &mut Vec<&'a str>) {
+        if parents.is_empty() || !space.items().is_empty() {
+            self.section(space, parents);
+        }
+        parents.push(space.name());
+        for child in space.spaces() {
+            self.sections(child, parents);
+        }
+        parents.pop();
+    }
+
+    fn section(&mut self, space: &Space, parents: &[&str]) {
+        // Each section starts on a new page when it is the first one or when
+        // its heading and first row would not fit.
+        if parents.is_empty() || self.y - 4.0 * LINE_HEIGHT < MARGIN {
+            self.new_page();
+        } else {
+            self.skip(2.0 * LINE_HEIGHT);
+        }
+        self.line(&path::join(parents, space.name()), 14.0, true);
+        self.skip(2.0);
+        if space.items().is_empty() {
+            self.line("No items.", BODY_SIZE, false);
+            return;
+        }
+        self.header();
+        for item in space.items() {
+            let mut details: Vec<String> = item
+                .metadata()
+                .iter()
+                .map(|(key, value)| format!("{key}: {value}"))
+                .collect();
+            if !item.tags().is_empty() {
+                let tags: Vec<&str> = item.tags().iter().map(String::as_str).collect();
+                details.push(format!("tags: {}", tags.join(", ")));
+            }
+            if let Some(category) = item.category() {
+                details.push(format!("category: {category}"));
+            }
+            let formats = self.formats;
+            match (
+                item.purchase_date().map(|d| formats.date(d)),
+                item.purchase_price().map(|p| formats.price(p)),
+            ) {
+                (Some(date), Some(price)) => details.push(format!("bought {date} for {price}")),
+                (Some(date), None) => details.push(format!("bought {date}")),
+                (None, Some(price)) => details.push(format!("bought for {price}")),
+                (None, None) => {}
+            }
+            if let Some(date) = item.warranty_until() {
+                details.push(format!("warranty until {}", formats.date(date)));
+            }
+            match (item.last_verified_at(), item.missing_at()) {
+                (_, Some(at)) => details.push(format!("not found {}", formats.date(at.date()))),
+                (Some(at), None) => details.push(format!("verified {}", formats.date(at.date()))),
+                (None, None) => {}
+            }
+            match (item.size(), item.weight()) {
+                (Some(size), Some(weight)) => details.push(format!("{size} cm, {weight}")),
+                (Some(size), None) => details.push(format!("{size} cm")),
+                (None, Some(weight)) => details.push(weight.to_string()),
+                (None, None) => {}
+            }
+            match item.receipts().len() {
+                0 => {}
+                1 => details.push("1 receipt".to_string()),
+                n => details.push(format!("{n} receipts")),
+            }
+            self.row([item.name(), item.description(), &details.join("\n")]);
+        }
+    }
+
+    fn header(&mut self) {
+        for (heading, x, _) in COLUMNS {
+            self.layer
+                .use_text(heading, BODY_SIZE, Mm(x), Mm(self.y), &self.bold);
+        }
+        self.y -= 2.0;
+        self.rule();
+        self.y -= LINE_HEIGHT;
+    }
+
+    fn row(&mut self, cells: [&str; 3]) {
+        let wrapped: Vec<Vec<String>> = COLUMNS
+            .iter()
+            .zip(cells)
+            .map(|((_, _, width), text)| wrap(text, (width * CHARS_PER_MM) as usize))
+            .collect();
+        let lines = wrapped.iter().map(Vec::len).max().unwrap_or(1).max(1);
+        if self.y - lines as f32 * LINE_HEIGHT < MARGIN {
+            self.new_page();
+            self.header();
+        }
+        for ((_, x, _), cell) in COLUMNS.iter().zip(&wrapped) {
+            for (i, text) in cell.iter().enumerate() {
+                let y = self.y - i as f32 * LINE_HEIGHT;
+                self.layer
+                    .use_text(text, BODY_SIZE, Mm(*x), Mm(y), &self.regular);
+            }
+        }
+        self.y -= lines as f32 * LINE_HEIGHT;
+    }
+
+    fn line(&mut self, text: &str, size: f32, bold: bool) {
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer
+            .use_text(text, size, Mm(MARGIN), Mm(self.y), font);
+        // Font sizes are in points; this leaves about a third of the size
+        // between lines.
+        self.y -= size * 0.45;
+    }
+
+    fn skip(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn rule(&self) {
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(MARGIN), Mm(self.y)), false),
+                (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(self.y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self
+            .doc
+            .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "content");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+}
+
+/// Break `text` into lines of at most `width` characters, at spaces where
+/// possible.
+pub(crate) fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word = word;
+            loop {
+                let len = line.chars().count();
+                let extra = if line.is_empty() { 0 } else { 1 };
+                if len + extra + word.chars().count() <= width {
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(word);
+                    break;
+                }
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                    continue;
+                }
+                // A single word longer than a whole line.
+                let split = word
+                    .char_indices()
+                    .nth(width)
+                    .map_or(word.len(), |(i, _)| i);
+                lines.push(word[..split].to_string());
+                word = &word[split..];
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
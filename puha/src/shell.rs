@@ -0,0 +1,178 @@
+//! Interactive shell.
+
+use std::borrow::Cow;
+
+use clap::{CommandFactory, Parser};
+use puha_lib::path;
+use puha_lib::search;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::HistoryHinter;
+use rustyline::history::FileHistory;
+use rustyline::{Editor, Helper, Hinter, Validator};
+
+use crate::{Cli, Commands, Context, command_name, config, expand, run_tracked, state};
+
+/// Completes commands and the names in the tree, and hints at commands
+/// from the history.
+#[derive(Helper, Hinter, Validator)]
+struct ShellHelper {
+    /// Commands, aliases and macros.
+    commands: Vec<String>,
+    /// Names of the spaces and items in the tree.
+    names: Vec<String>,
+    #[rustyline(Hinter)]
+    hinter: HistoryHinter,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word = &line[start..pos];
+        // The first word is the command; all others are completed as names.
+        let pool = if line[..start].trim().is_empty() {
+            &self.commands
+        } else {
+            &self.names
+        };
+        let candidates = pool
+            .iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: quote_word(&path::quote(candidate)).into_owned(),
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Highlighter for ShellHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("\x1b[2m{hint}\x1b[0m"))
+    }
+}
+
+/// `word` quoted so that [`config::split_words`] reads it back as one word.
+fn quote_word(word: &str) -> Cow<'_, str> {
+    if !word.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '\\') {
+        return Cow::Borrowed(word);
+    }
+    if !word.contains('\'') {
+        return Cow::Owned(format!("'{word}'"));
+    }
+    let escaped = word.replace('\\', "\\\\").replace('"', "\\\"");
+    Cow::Owned(format!("\"{escaped}\""))
+}
+
+/// Read commands from the terminal and run them until end of input or
+/// `exit`. Options given to `puha shell` apply to every command.
+pub fn run(cx: &mut Context) -> Result<(), Box<dyn std::error::Error>> {
+    let argv = cx.argv.clone();
+    let prefix: Vec<String> = match argv.iter().rposition(|arg| arg == "shell") {
+        Some(i) => [&argv[..i], &argv[i + 1..]].concat(),
+        None => argv[..1].to_vec(),
+    };
+
+    let mut commands: Vec<String> = Cli::command()
+        .get_subcommands()
+        .map(|c| c.get_name().to_string())
+        .filter(|name| name != "shell")
+        .chain(cx.config.aliases.keys().cloned())
+        .chain(cx.config.macros.keys().cloned())
+        .chain(["exit".to_string()])
+        .collect();
+    commands.sort();
+    let mut editor: Editor<ShellHelper, FileHistory> = Editor::new()?;
+    editor.set_helper(Some(ShellHelper {
+        commands,
+        names: Vec::new(),
+        hinter: HistoryHinter::new(),
+    }));
+    let history = state::history_path();
+    if let Some(history) = &history {
+        // There is no history yet on the first run.
+        let _ = editor.load_history(history);
+    }
+
+    loop {
+        let prompt = refresh(&mut editor, cx);
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let words = match config::split_words(&line) {
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                continue;
+            }
+        };
+        match words.first().map(String::as_str) {
+            None => continue,
+            Some("exit" | "quit") => break,
+            Some(_) => {}
+        }
+        editor.add_history_entry(line.as_str())?;
+        let line_argv: Vec<String> = prefix.iter().cloned().chain(words).collect();
+        let name = command_name(&line_argv);
+        let result = Cli::try_parse_from(&line_argv)
+            .map_err(Into::into)
+            .and_then(|cli| match cli.command {
+                Commands::Shell => Err("already in the shell".into()),
+                _ => expand(cli, &line_argv, &cx.config),
+            })
+            .and_then(|steps| run_tracked(name.as_deref(), steps, cx));
+        if let Err(e) = result {
+            match e.downcast::<clap::Error>() {
+                // Help and version requests are errors to clap.
+                Ok(e) => {
+                    let _ = e.print();
+                }
+                Err(e) => eprintln!("Error: {e}"),
+            }
+        }
+    }
+
+    if let Some(history) = &history {
+        if let Some(dir) = history.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        editor.save_history(history)?;
+    }
+    Ok(())
+}
+
+/// Reload the names to complete from the tree and return the prompt, which
+/// shows the current space.
+fn refresh(editor: &mut Editor<ShellHelper, FileHistory>, cx: &Context) -> String {
+    let root = cx.store.load().ok();
+    if let Some(helper) = editor.helper_mut() {
+        let mut names: Vec<String> = root
+            .iter()
+            .flat_map(search::entries)
+            .map(|entry| entry.target.name().to_string())
+            .collect();
+        names.sort();
+        names.dedup();
+        helper.names = names;
+    }
+    let current = match cx.state.current.get(&cx.state_key) {
+        Some(segments) => path::format(&segments.iter().map(String::as_str).collect::<Vec<_>>()),
+        None => root.map(|root| root.name().to_string()).unwrap_or_default(),
+    };
+    format!("{current}> ")
+}
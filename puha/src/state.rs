@@ -0,0 +1,105 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use puha_lib::Timestamp;
+use puha_lib::storage::write_atomically;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// What the CLI remembers between invocations, kept apart from the
+/// configuration because puha writes it itself.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// The current space set with `cd`, as a path from the root, for each
+    /// data file by its absolute path.
+    pub current: BTreeMap<String, Vec<String>>,
+    /// Photos `intake` has already offered, by absolute path, for each data
+    /// file.
+    pub intake: BTreeMap<String, BTreeSet<String>>,
+    /// Unfinished audits by the path of the audited space, for each data
+    /// file.
+    pub audits: BTreeMap<String, BTreeMap<String, Audit>>,
+}
+
+/// An audit begun with `puha audit`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Audit {
+    /// Items checked since are done.
+    pub started: Timestamp,
+    /// Items found elsewhere, from their old path to the space they were
+    /// moved to.
+    #[serde(default)]
+    pub moved: Vec<(String, String)>,
+}
+
+impl State {
+    /// Load the state from `path`. A missing file yields the empty state.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        save_json(path, self)
+    }
+}
+
+/// Load a file puha keeps for itself, such as the state. A missing file
+/// yields the default.
+pub fn load_json<T: DeserializeOwned + Default>(
+    path: &Path,
+) -> Result<T, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let data = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(serde_json::from_str(&data).map_err(|e| format!("{}: {e}", path.display()))?)
+}
+
+/// Like [`load_json`], but a file that cannot be loaded is replaced by the
+/// default with a warning, since it only holds conveniences.
+pub fn load_json_or_reset<T: DeserializeOwned + Default + Serialize>(path: &Path) -> T {
+    load_json(path).unwrap_or_else(|e| {
+        eprintln!("warning: {e}; starting afresh");
+        let fresh = T::default();
+        let _ = save_json(path, &fresh);
+        fresh
+    })
+}
+
+/// Save a file puha keeps for itself, replacing the old one only once the
+/// new one is complete.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    write_atomically(path, |out| Ok(serde_json::to_writer_pretty(out, value)?))
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(())
+}
+
+/// The key under which the state of the data at `file` is kept.
+pub fn key(file: &str) -> String {
+    std::path::absolute(file)
+        .unwrap_or_else(|_| PathBuf::from(file))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The shell's command history, next to the state file.
+#[cfg(feature = "shell")]
+pub fn history_path() -> Option<PathBuf> {
+    Some(default_path()?.with_file_name("history"))
+}
+
+/// `$PUHA_STATE`, or `state.json` in the puha state directory.
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("PUHA_STATE") {
+        return Some(path.into());
+    }
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(base.join("puha/state.json"))
+}
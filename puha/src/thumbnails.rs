@@ -0,0 +1,39 @@
+//! Showing thumbnails in the terminal with the kitty graphics protocol.
+
+use std::io::Write;
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+/// Largest piece of base64 data the protocol takes in one escape sequence.
+const CHUNK: usize = 4096;
+
+/// Whether the terminal is known to understand the kitty graphics protocol.
+pub fn supported() -> bool {
+    let env = |name| std::env::var(name).unwrap_or_default();
+    std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || env("TERM").contains("kitty")
+        || env("TERM").contains("ghostty")
+        || env("TERM_PROGRAM") == "WezTerm"
+}
+
+/// Draw the PNG at `path` at the cursor.
+pub fn show(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let data = STANDARD.encode(std::fs::read(path)?);
+    let mut out = std::io::stdout().lock();
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(CHUNK).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let control = if i == 0 {
+            format!("a=T,f=100,m={more}")
+        } else {
+            format!("m={more}")
+        };
+        out.write_all(format!("\x1b_G{control};").as_bytes())?;
+        out.write_all(chunk)?;
+        out.write_all(b"\x1b\\")?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
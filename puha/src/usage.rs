@@ -0,0 +1,94 @@
+//! Local record of how often each command runs and how long it takes.
+//!
+//! Only kept when the `usage_stats` configuration setting is on, and only
+//! ever written to a file next to the state file.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::state;
+
+/// Counters per command name, as typed: aliases and macros count under
+/// their own names.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub commands: BTreeMap<String, CommandUsage>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandUsage {
+    pub runs: u64,
+    pub failures: u64,
+    /// Time taken by all runs together, in microseconds.
+    pub total_us: u64,
+    /// Time taken by the slowest run, in microseconds.
+    pub max_us: u64,
+}
+
+impl Usage {
+    /// Load the usage from `path`. A missing file yields no usage, and one
+    /// that cannot be loaded is started afresh.
+    pub fn load(path: &Path) -> Self {
+        state::load_json_or_reset(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        state::save_json(path, self)
+    }
+
+    pub fn record(&mut self, command: &str, elapsed: Duration, ok: bool) {
+        let entry = self.commands.entry(command.to_string()).or_default();
+        let us = elapsed.as_micros() as u64;
+        entry.runs += 1;
+        entry.failures += u64::from(!ok);
+        entry.total_us += us;
+        entry.max_us = entry.max_us.max(us);
+    }
+
+    /// A table of the commands, most used first.
+    pub fn table(&self) -> String {
+        let mut commands: Vec<_> = self.commands.iter().collect();
+        commands.sort_by(|a, b| b.1.runs.cmp(&a.1.runs).then(a.0.cmp(b.0)));
+        let width = commands
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["command".len()])
+            .max()
+            .unwrap_or_default();
+        let mut out = format!(
+            "{:width$}  {:>6}  {:>8}  {:>8}  {:>8}\n",
+            "command", "runs", "failures", "avg ms", "max ms"
+        );
+        for (name, usage) in commands {
+            out.push_str(&format!(
+                "{name:width$}  {:>6}  {:>8}  {:>8.1}  {:>8.1}\n",
+                usage.runs,
+                usage.failures,
+                usage.total_us as f64 / usage.runs.max(1) as f64 / 1000.0,
+                usage.max_us as f64 / 1000.0
+            ));
+        }
+        out
+    }
+}
+
+/// `usage.json`, next to the state file.
+pub fn default_path() -> Option<PathBuf> {
+    Some(state::default_path()?.with_file_name("usage.json"))
+}
+
+/// Add a run of `command` to the usage file. Statistics are a convenience,
+/// so failing to record them never fails the command.
+pub fn track(command: &str, elapsed: Duration, ok: bool) {
+    let Some(path) = default_path() else {
+        return;
+    };
+    let mut usage = Usage::load(&path);
+    usage.record(command, elapsed, ok);
+    let _ = usage.save(&path);
+}
@@ -0,0 +1,174 @@
+//! Spreadsheet export.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+
+use puha_lib::search::{self, Target};
+use puha_lib::{Item, Space, path};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+/// Columns before the metadata columns.
+const COLUMNS: [&str; 13] = [
+    "Path",
+    "Name",
+    "Description",
+    "Quantity",
+    "Minimum",
+    "Tags",
+    "Category",
+    "Purchased",
+    "Price",
+    "Warranty until",
+    "Last verified",
+    "Size (cm)",
+    "Weight",
+];
+
+/// Longest sheet name Excel accepts.
+const MAX_SHEET_NAME: usize = 31;
+
+/// Write a workbook with one sheet per space directly below `root`, listing
+/// every item in it and its child spaces. Items directly in `root` get a
+/// sheet of their own first. `progress` is called with the number of items
+/// written so far and the total after each sheet. `-` for `out` writes to
+/// standard output.
+pub fn write(
+    root: &Space,
+    out: &Path,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let total = search::entries(root)
+        .iter()
+        .filter(|entry| matches!(entry.target, Target::Item(_)))
+        .count();
+    let mut done = 0;
+    let mut workbook = Workbook::new();
+    let mut names = HashSet::new();
+    let header = Format::new().set_bold();
+
+    if !root.items().is_empty() {
+        let rows: Vec<_> = root
+            .items()
+            .iter()
+            .map(|item| (root.name().to_string(), item))
+            .collect();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(sheet_name(root.name(), &mut names))?;
+        fill(sheet, &rows, &header)?;
+        done += rows.len();
+        progress(done, total);
+    }
+    for space in root.spaces() {
+        let rows: Vec<_> = search::entries(space)
+            .into_iter()
+            .filter_map(|entry| match entry.target {
+                Target::Item(item) => {
+                    let mut location = vec![root.name()];
+                    location.extend(&entry.path);
+                    let (last, parents) = location.split_last()?;
+                    Some((path::join(parents, last), item))
+                }
+                Target::Space(_) => None,
+            })
+            .collect();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(sheet_name(space.name(), &mut names))?;
+        fill(sheet, &rows, &header)?;
+        done += rows.len();
+        progress(done, total);
+    }
+    if root.items().is_empty() && root.spaces().is_empty() {
+        workbook
+            .add_worksheet()
+            .set_name(sheet_name(root.name(), &mut names))?;
+    }
+    crate::write_output(out, &workbook.save_to_buffer()?)
+}
+
+/// Write the header and one row per item, given with the path of its space.
+fn fill(sheet: &mut Worksheet, rows: &[(String, &Item)], header: &Format) -> Result<(), XlsxError> {
+    let keys: BTreeSet<&str> = rows
+        .iter()
+        .flat_map(|(_, item)| item.metadata().keys().map(String::as_str))
+        .collect();
+    let headings = COLUMNS.iter().copied().chain(keys.iter().copied());
+    for (col, heading) in headings.enumerate() {
+        sheet.write_string_with_format(0, col as u16, heading, header)?;
+    }
+    let money = Format::new().set_num_format("0.00");
+    for (i, (location, item)) in rows.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, location)?;
+        sheet.write_string(row, 1, item.name())?;
+        sheet.write_string(row, 2, item.description())?;
+        if let Some(quantity) = item.quantity() {
+            sheet.write_number(row, 3, quantity)?;
+        }
+        if let Some(min_quantity) = item.min_quantity() {
+            sheet.write_number(row, 4, min_quantity)?;
+        }
+        if !item.tags().is_empty() {
+            let tags: Vec<&str> = item.tags().iter().map(String::as_str).collect();
+            sheet.write_string(row, 5, tags.join(", "))?;
+        }
+        if let Some(category) = item.category() {
+            sheet.write_string(row, 6, category)?;
+        }
+        if let Some(date) = item.purchase_date() {
+            sheet.write_string(row, 7, date.to_string())?;
+        }
+        if let Some(price) = item.purchase_price() {
+            sheet.write_number_with_format(row, 8, price.0 as f64 / 100.0, &money)?;
+        }
+        if let Some(date) = item.warranty_until() {
+            sheet.write_string(row, 9, date.to_string())?;
+        }
+        if let Some(at) = item.last_verified_at() {
+            sheet.write_string(row, 10, at.to_string())?;
+        }
+        if let Some(size) = item.size() {
+            sheet.write_string(row, 11, size.to_string())?;
+        }
+        if let Some(weight) = item.weight() {
+            sheet.write_string(row, 12, weight.to_string())?;
+        }
+        for (j, key) in keys.iter().enumerate() {
+            if let Some(value) = item.metadata().get(*key) {
+                sheet.write_string(row, (COLUMNS.len() + j) as u16, value)?;
+            }
+        }
+    }
+    let last_col = (COLUMNS.len() + keys.len() - 1) as u16;
+    sheet.autofilter(0, 0, rows.len() as u32, last_col)?;
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    Ok(())
+}
+
+/// A sheet name for `name` that Excel accepts and that is not in `used` yet.
+fn sheet_name(name: &str, used: &mut HashSet<String>) -> String {
+    let clean: String = name
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .collect();
+    let clean = clean.trim_matches('\'');
+    let clean = if clean.is_empty() { "Sheet" } else { clean };
+    let mut n = 1;
+    loop {
+        let suffix = if n == 1 {
+            String::new()
+        } else {
+            format!(" ({n})")
+        };
+        let room = MAX_SHEET_NAME - suffix.chars().count();
+        let candidate: String = clean.chars().take(room).chain(suffix.chars()).collect();
+        // Excel compares sheet names case-insensitively.
+        if used.insert(candidate.to_lowercase()) {
+            return candidate;
+        }
+        n += 1;
+    }
+}